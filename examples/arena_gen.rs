@@ -0,0 +1,48 @@
+//  NOTE: draws a random arena, the arena's seed is in its name in the game. `cargo test --lib
+//  arena` checks the generator itself.
+//
+//  cargo run --release --example arena_gen -- <seed> [size] [easy|normal|hard]
+use std::env;
+
+use snake::{
+    arena::generate,
+    settings::{ArenaDifficulty, DEFAULT_BOARD_SIZE},
+    snake::Position,
+};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let seed = args.next().map_or(0, |arg| arg.parse().expect("seed"));
+    let size = args
+        .next()
+        .map_or(DEFAULT_BOARD_SIZE, |arg| arg.parse().expect("size"));
+    let difficulty = match args.next().as_deref() {
+        Some("easy") => ArenaDifficulty::Easy,
+        Some("hard") => ArenaDifficulty::Hard,
+        _ => ArenaDifficulty::Normal,
+    };
+    draw(seed, size, difficulty);
+}
+
+fn draw(seed: u64, size: i32, difficulty: ArenaDifficulty) {
+    let level = generate(size, difficulty, seed);
+    let snake = level.snake();
+    //  NOTE: top row first, y grows upwards on the board
+    for y in (0..size).rev() {
+        let row: String = (0..size)
+            .map(|x| {
+                let pos = Position::new(x, y);
+                if pos == level.start {
+                    '@'
+                } else if snake.contains(&pos) {
+                    'o'
+                } else if level.is_wall(pos) {
+                    '#'
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        println!("{row}");
+    }
+}
@@ -0,0 +1,50 @@
+//  NOTE: steps a batch of environments with random actions, a starting point for hooking up a
+//  training loop and a quick way to see how many steps per second the environments manage.
+//
+//  cargo run --release --example gym_random -- [environments] [steps]
+use std::{env, time::Instant};
+
+use rand::seq::SliceRandom;
+use snake::{
+    gym::{EnvConfig, ObservationKind, VecEnv},
+    snake::Direction,
+};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let count: usize = args
+        .next()
+        .map_or(64, |arg| arg.parse().expect("environments"));
+    let steps: usize = args.next().map_or(1000, |arg| arg.parse().expect("steps"));
+
+    let config = EnvConfig {
+        observation: ObservationKind::Grid,
+        ..Default::default()
+    };
+    let mut envs = VecEnv::new(config, count);
+    let observations = envs.reset(0);
+    println!("observation shape {:?}", observations[0].shape);
+
+    let mut rng = rand::thread_rng();
+    let mut episodes = 0;
+    let mut total_reward = 0.0;
+    let started = Instant::now();
+    for _ in 0..steps {
+        let actions: Vec<Direction> = (0..count)
+            .map(|_| *Direction::ALL.choose(&mut rng).unwrap())
+            .collect();
+        for (_, reward, done, _) in envs.step(&actions) {
+            total_reward += reward;
+            episodes += done as usize;
+        }
+    }
+
+    let elapsed = started.elapsed();
+    println!(
+        "{} steps in {:.2?}, {:.0} steps/s, {episodes} episodes, mean reward {:.3} per step",
+        count * steps,
+        elapsed,
+        (count * steps) as f64 / elapsed.as_secs_f64(),
+        total_reward / (count * steps) as f32
+    );
+}
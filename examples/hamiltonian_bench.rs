@@ -0,0 +1,60 @@
+//  NOTE: plays the Hamiltonian bot headlessly over a range of seeds and reports how long it takes
+//  to fill the board.
+//
+//  cargo run --release --example hamiltonian_bench -- [board size] [seeds]
+use std::{env, time::Instant};
+
+use snake::{
+    bot::Bot,
+    settings::{AiPlayer, WallsMode, DEFAULT_BOARD_SIZE},
+    sim::{Game, Outcome, Rules},
+};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let size: i32 = args
+        .next()
+        .map_or(DEFAULT_BOARD_SIZE, |arg| arg.parse().expect("board size"));
+    let seeds: u64 = args.next().map_or(20, |arg| arg.parse().expect("seeds"));
+
+    let rules = Rules::new(size, WallsMode::Solid);
+    let bot = Bot::new(AiPlayer::Hamiltonian, size);
+    let started = Instant::now();
+    let mut completed: Vec<u32> = Vec::new();
+
+    for seed in 0..seeds {
+        let mut game = Game::new(rules.clone(), seed);
+        let outcome = loop {
+            let outcome = game.step(bot.next_direction(&game));
+            if outcome.is_over() {
+                break outcome;
+            }
+        };
+
+        match outcome {
+            Outcome::Won => completed.push(game.moves()),
+            _ => println!(
+                "seed {seed}: {outcome:?} with score {} after {} moves",
+                game.score(),
+                game.moves()
+            ),
+        }
+    }
+
+    let elapsed = started.elapsed();
+    println!("board {size}x{size}, {seeds} seeds");
+    println!("completed {}/{seeds}", completed.len());
+    if !completed.is_empty() {
+        let total: u64 = completed.iter().map(|&moves| moves as u64).sum();
+        println!(
+            "moves to completion: avg {:.0}, min {}, max {}",
+            total as f64 / completed.len() as f64,
+            completed.iter().min().unwrap(),
+            completed.iter().max().unwrap()
+        );
+    }
+    println!("took {elapsed:.2?}");
+    if seeds > 0 {
+        println!("{:.2?} per game", elapsed / seeds as u32);
+    }
+}
@@ -0,0 +1,52 @@
+//  NOTE: plays battle royales between the built-in bots and reports how long they last and how
+//  big the winners grow. The rules themselves are checked by the tests in `royale.rs`.
+//
+//  cargo run --release --example royale_bots -- [games] [snakes]
+use std::{env, process, time::Instant};
+
+use snake::{
+    royale::{RoyaleGame, DEFAULT_ROYALE_SNAKES, MAX_ROYALE_SNAKES, MIN_ROYALE_SNAKES},
+    versus::VersusOutcome,
+};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let games: u64 = args.next().map_or(20, |arg| arg.parse().expect("games"));
+    let snakes: usize = args
+        .next()
+        .map_or(DEFAULT_ROYALE_SNAKES, |arg| arg.parse().expect("snakes"));
+    if !(MIN_ROYALE_SNAKES..=MAX_ROYALE_SNAKES).contains(&snakes) {
+        eprintln!("snakes must be between {MIN_ROYALE_SNAKES} and {MAX_ROYALE_SNAKES}");
+        process::exit(2);
+    }
+
+    let started = Instant::now();
+    let mut moves = 0;
+    let mut draws = 0;
+    let mut winner_lengths = 0;
+    for seed in 0..games {
+        let mut game = RoyaleGame::new(snakes, seed);
+        while game.outcome() == VersusOutcome::Running {
+            let turns: Vec<_> = (0..snakes).map(|player| game.plan(player)).collect();
+            game.step(&turns);
+        }
+
+        let state = game.state();
+        match game.outcome() {
+            VersusOutcome::Over {
+                winner: Some(winner),
+            } => winner_lengths += state.snakes[winner].body.len(),
+            _ => draws += 1,
+        }
+        moves += state.tick;
+    }
+
+    let won = games - draws;
+    println!(
+        "{games} games of {snakes} snakes in {:.1?}: {:.0} moves on average, {draws} draws, \
+         winners {:.1} long",
+        started.elapsed(),
+        moves as f64 / games as f64,
+        winner_lengths as f64 / won.max(1) as f64
+    );
+}
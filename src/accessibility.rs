@@ -0,0 +1,117 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::prelude::*;
+use bevy_egui::EguiSettings;
+
+use crate::{board::TILE_SIZE, food::Food, settings::Settings, snake::SnakeHead};
+
+//  NOTE: yellow reads well against every theme's tiles, including the monochrome ones
+const OUTLINE_COLOR: Color = Color::rgb(1.0, 0.83, 0.0);
+const OUTLINE_WIDTH: f32 = 4.0;
+const PATTERN_COLOR: Color = Color::rgb(1.0, 1.0, 1.0);
+
+//  NOTE: backplate drawn behind the head and food so they stand out without relying on colour
+#[derive(Component, Debug)]
+struct Outline;
+
+//  NOTE: shape drawn over the food so it can be told apart from the snake by shape alone
+#[derive(Component, Debug)]
+struct FoodPattern;
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (attach_outlines, attach_food_patterns))
+            .add_systems(
+                Update,
+                (update_overlay_visibility, update_ui_scale).run_if(resource_changed::<Settings>),
+            );
+    }
+}
+
+fn visibility(enabled: bool) -> Visibility {
+    if enabled {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn attach_outlines(
+    mut commands: Commands,
+    query: Query<Entity, Or<(Added<SnakeHead>, Added<Food>)>>,
+    settings: Res<Settings>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).with_children(|builder| {
+            builder.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: OUTLINE_COLOR,
+                        custom_size: Some(Vec2::splat(TILE_SIZE + OUTLINE_WIDTH)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, 0.0, -0.5),
+                    visibility: visibility(settings.accessibility.outlines),
+                    ..default()
+                },
+                Outline,
+            ));
+        });
+    }
+}
+
+fn attach_food_patterns(
+    mut commands: Commands,
+    query: Query<Entity, Added<Food>>,
+    settings: Res<Settings>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).with_children(|builder| {
+            builder.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: PATTERN_COLOR,
+                        custom_size: Some(Vec2::splat(TILE_SIZE * 0.25)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, 0.0, 0.5)
+                        .with_rotation(Quat::from_rotation_z(FRAC_PI_4)),
+                    visibility: visibility(settings.accessibility.food_patterns),
+                    ..default()
+                },
+                FoodPattern,
+            ));
+        });
+    }
+}
+
+fn update_overlay_visibility(
+    settings: Res<Settings>,
+    mut outline_query: Query<&mut Visibility, (With<Outline>, Without<FoodPattern>)>,
+    mut pattern_query: Query<&mut Visibility, (With<FoodPattern>, Without<Outline>)>,
+) {
+    let outlines = visibility(settings.accessibility.outlines);
+    for mut outline_visibility in outline_query.iter_mut() {
+        if *outline_visibility != outlines {
+            *outline_visibility = outlines;
+        }
+    }
+
+    let patterns = visibility(settings.accessibility.food_patterns);
+    for mut pattern_visibility in pattern_query.iter_mut() {
+        if *pattern_visibility != patterns {
+            *pattern_visibility = patterns;
+        }
+    }
+}
+
+//  NOTE: egui is scaled on its own, the board keeps following the camera breakpoints
+fn update_ui_scale(settings: Res<Settings>, mut egui_settings: ResMut<EguiSettings>) {
+    let scale = settings.accessibility.ui_scale;
+    if egui_settings.scale_factor != scale {
+        egui_settings.scale_factor = scale;
+    }
+}
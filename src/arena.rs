@@ -0,0 +1,232 @@
+use std::collections::{HashSet, VecDeque};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    level::Level,
+    settings::{ArenaDifficulty, WallsMode},
+    sim::Grid,
+    snake::{Direction, Position},
+};
+
+//  NOTE: random arenas, a `Level` with obstacles for "Random arena". Every free cell stays
+//  reachable from every other and the cells around the snake's start stay clear. The same seed,
+//  size and difficulty always give the same arena
+
+//  NOTE: cells kept clear in front of the head, behind the tail and to either side of the snake
+const CLEAR_AHEAD: i32 = 5;
+const CLEAR_BEHIND: i32 = 1;
+const CLEAR_SIDES: i32 = 2;
+//  NOTE: cells between two posts of the maze, plus one
+const MAZE_SPACING: i32 = 4;
+//  NOTE: placements tried for every obstacle wanted before giving up on a crowded board
+const ATTEMPTS_PER_OBSTACLE: usize = 20;
+
+impl ArenaDifficulty {
+    //  NOTE: share of the board covered in walls
+    fn density(&self) -> f32 {
+        match self {
+            Self::Easy => 0.05,
+            Self::Normal => 0.1,
+            Self::Hard => 0.2,
+        }
+    }
+}
+
+pub fn generate(size: i32, difficulty: ArenaDifficulty, seed: u64) -> Level {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut level = Level::new(&format!("Arena {seed}"), size);
+    let clear = clear_area(&level);
+    let target = (size * size) as f32 * difficulty.density();
+
+    let mut walls: HashSet<Position> = HashSet::new();
+    //  NOTE: obstacles may overlap, maze walls share their posts
+    let mut place = |level: &mut Level, obstacle: Vec<Position>| {
+        if obstacle.iter().any(|pos| clear.contains(pos)) {
+            return;
+        }
+        let added: Vec<Position> = obstacle
+            .into_iter()
+            .filter(|pos| !walls.contains(pos))
+            .collect();
+        let mut placed = walls.clone();
+        placed.extend(added.iter().copied());
+        if !added.is_empty() && connected(size, &placed, level.start) {
+            walls = placed;
+            level.walls.extend(added);
+        }
+    };
+
+    match difficulty {
+        ArenaDifficulty::Easy | ArenaDifficulty::Normal => {
+            let attempts = target as usize * ATTEMPTS_PER_OBSTACLE;
+            for _ in 0..attempts {
+                if level.walls.len() as f32 >= target {
+                    break;
+                }
+                let obstacle = random_obstacle(size, &mut rng);
+                place(&mut level, obstacle);
+            }
+        }
+        ArenaDifficulty::Hard => {
+            let mut segments = maze_segments(size);
+            segments.shuffle(&mut rng);
+            for segment in segments {
+                if level.walls.len() as f32 >= target {
+                    break;
+                }
+                place(&mut level, segment);
+            }
+        }
+    }
+    level
+}
+
+//  NOTE: the snake, the cells in front of it and a margin around both
+fn clear_area(level: &Level) -> HashSet<Position> {
+    let start = level.start;
+    let (ahead, behind) = (start.x + CLEAR_AHEAD, start.x - 2 - CLEAR_BEHIND);
+    (behind..=ahead)
+        .flat_map(|x| (start.y - CLEAR_SIDES..=start.y + CLEAR_SIDES).map(move |y| (x, y)))
+        .map(|(x, y)| Position::new(x, y))
+        .collect()
+}
+
+//  NOTE: a straight bar, sometimes with a bend, cut off at the edges of the board
+fn random_obstacle(size: i32, rng: &mut StdRng) -> Vec<Position> {
+    let grid = Grid {
+        size,
+        walls: WallsMode::Solid,
+    };
+    let mut pos = Position::new(rng.gen_range(0..size), rng.gen_range(0..size));
+    let mut direction = *Direction::ALL.choose(rng).expect("four directions");
+    let mut obstacle = vec![pos];
+    for _ in 1..rng.gen_range(2..=5) {
+        pos = grid.advance(pos, direction);
+        obstacle.push(pos);
+    }
+    if rng.gen_bool(0.3) {
+        direction = match direction {
+            Direction::Up | Direction::Down => *[Direction::Left, Direction::Right]
+                .choose(rng)
+                .expect("two directions"),
+            Direction::Left | Direction::Right => *[Direction::Up, Direction::Down]
+                .choose(rng)
+                .expect("two directions"),
+        };
+        for _ in 0..rng.gen_range(1..=2) {
+            pos = grid.advance(pos, direction);
+            obstacle.push(pos);
+        }
+    }
+    obstacle.retain(|&pos| grid.contains(pos));
+    obstacle
+}
+
+//  NOTE: posts every `MAZE_SPACING` cells, centered on the board, and the walls that could join
+//  two neighbouring ones. The corridors between them are three cells wide
+fn maze_segments(size: i32) -> Vec<Vec<Position>> {
+    let count = (size - 3) / MAZE_SPACING + 1;
+    let offset = (size - 1 - (count - 1) * MAZE_SPACING) / 2;
+    let posts: Vec<i32> = (0..count).map(|i| offset + i * MAZE_SPACING).collect();
+    let mut segments = Vec::new();
+    for &y in &posts {
+        for &x in &posts {
+            if posts.contains(&(x + MAZE_SPACING)) {
+                segments.push(
+                    (x..=x + MAZE_SPACING)
+                        .map(|x| Position::new(x, y))
+                        .collect(),
+                );
+            }
+            if posts.contains(&(y + MAZE_SPACING)) {
+                segments.push(
+                    (y..=y + MAZE_SPACING)
+                        .map(|y| Position::new(x, y))
+                        .collect(),
+                );
+            }
+        }
+    }
+    segments
+}
+
+//  NOTE: whether every cell that isn't a wall can be reached from `from`, on solid walls so it
+//  holds with wrapping ones too
+pub fn connected(size: i32, walls: &HashSet<Position>, from: Position) -> bool {
+    let grid = Grid {
+        size,
+        walls: WallsMode::Solid,
+    };
+    let mut seen = HashSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(pos) = queue.pop_front() {
+        for direction in Direction::ALL {
+            if let Some(next) = grid.step(pos, direction) {
+                if !walls.contains(&next) && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    seen.len() == grid.cells() - walls.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{MAX_BOARD_SIZE, MIN_BOARD_SIZE};
+
+    //  NOTE: arenas per difficulty, spread over every board size
+    const ARENAS: u64 = 200;
+
+    fn arenas() -> impl Iterator<Item = (ArenaDifficulty, i32, u64)> {
+        ArenaDifficulty::ALL.into_iter().flat_map(|difficulty| {
+            (0..ARENAS).map(move |seed| {
+                let size =
+                    MIN_BOARD_SIZE + (seed % (MAX_BOARD_SIZE - MIN_BOARD_SIZE + 1) as u64) as i32;
+                (difficulty, size, seed)
+            })
+        })
+    }
+
+    #[test]
+    fn free_cells_are_connected() {
+        for (difficulty, size, seed) in arenas() {
+            let level = generate(size, difficulty, seed);
+            let walls: HashSet<Position> = level.walls.iter().copied().collect();
+            assert!(
+                connected(size, &walls, level.start),
+                "{difficulty:?} arena {seed} on {size}: cut off cells"
+            );
+            for walls in [WallsMode::Solid, WallsMode::Wrap] {
+                if let Err(err) = level.validate(walls) {
+                    panic!("{difficulty:?} arena {seed} on {size}: {err}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn start_is_clear() {
+        for (difficulty, size, seed) in arenas() {
+            let level = generate(size, difficulty, seed);
+            let clear = clear_area(&level);
+            assert!(
+                level.walls.iter().all(|pos| !clear.contains(pos)),
+                "{difficulty:?} arena {seed} on {size}: walls around the start"
+            );
+        }
+    }
+
+    #[test]
+    fn same_seed_same_arena() {
+        for (difficulty, size, seed) in arenas() {
+            assert_eq!(
+                generate(size, difficulty, seed),
+                generate(size, difficulty, seed),
+                "{difficulty:?} arena {seed} on {size}"
+            );
+        }
+    }
+}
@@ -1,7 +1,15 @@
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
+use serde::Deserialize;
 
-#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+use crate::{
+    sprite_pack::{SpritePack, SpritePackLoader},
+    state::AppState,
+    theme::{Theme, ThemeLoader},
+};
+
+//  NOTE: the discriminants are the tile indices in `snake-graphics.png`, the built-in pack
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy, Deserialize)]
 pub enum SpritePart {
     BodyTopRight = 0,
     BodyHorizontal = 1,
@@ -20,18 +28,100 @@ pub enum SpritePart {
     TailDown = 19,
 }
 
+impl SpritePart {
+    pub const ALL: [SpritePart; 15] = [
+        SpritePart::BodyTopRight,
+        SpritePart::BodyHorizontal,
+        SpritePart::BodyTopLeft,
+        SpritePart::HeadUp,
+        SpritePart::HeadRight,
+        SpritePart::BodyBottomRight,
+        SpritePart::BodyVertical,
+        SpritePart::HeadLeft,
+        SpritePart::HeadDown,
+        SpritePart::BodyBottomLeft,
+        SpritePart::TailUp,
+        SpritePart::TailRight,
+        SpritePart::Apple,
+        SpritePart::TailLeft,
+        SpritePart::TailDown,
+    ];
+}
+
+//  NOTE: everything the game can't start without, mirrors the paths of the collections loaded
+//  in `AppState::Loading` so the loading screen can report progress
+pub const REQUIRED_ASSETS: [&str; 5] = [
+    "snake-graphics.png",
+    "play.png",
+    "trophy.png",
+    "settings.png",
+    "apple.ogg",
+];
+
 #[derive(AssetCollection, Resource)]
 pub struct ImageAssets {
+    #[asset(path = "play.png")]
+    pub play_icon: Handle<Image>,
+    #[asset(path = "trophy.png")]
+    pub trophy_icon: Handle<Image>,
+    #[asset(path = "settings.png")]
+    pub settings_icon: Handle<Image>,
+    //  NOTE: built-in sprite sheet, used when no sprite pack manifest is available
     #[asset(path = "snake-graphics.png")]
     pub sprite_sheet: Handle<Image>,
     #[asset(texture_atlas_layout(tile_size_x = 64., tile_size_y = 64., columns = 5, rows = 4,))]
     pub sprite_sheet_layout: Handle<TextureAtlasLayout>,
 }
 
+#[derive(AssetCollection, Resource)]
+pub struct AudioAssets {
+    #[asset(path = "apple.ogg")]
+    pub eat: Handle<AudioSource>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct ThemeAssets {
+    #[asset(
+        paths(
+            "themes/classic.theme.ron",
+            "themes/dark.theme.ron",
+            "themes/high_contrast.theme.ron",
+            "themes/retro.theme.ron",
+            "themes/deuteranopia.theme.ron",
+            "themes/protanopia.theme.ron",
+            "themes/tritanopia.theme.ron",
+        ),
+        collection(typed)
+    )]
+    pub themes: Vec<Handle<Theme>>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct SpritePackAssets {
+    #[asset(
+        paths("sprite_packs/classic.pack.ron", "sprite_packs/critters.pack.ron"),
+        collection(typed)
+    )]
+    pub packs: Vec<Handle<SpritePack>>,
+}
+
 pub struct AssetLoaderPlugin;
 
 impl Plugin for AssetLoaderPlugin {
     fn build(&self, app: &mut App) {
-        app.init_collection::<ImageAssets>();
+        app.init_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .init_asset::<SpritePack>()
+            .init_asset_loader::<SpritePackLoader>()
+            .add_loading_state(
+                LoadingState::new(AppState::Loading)
+                    .continue_to_state(AppState::Running)
+                    .on_failure_continue_to_state(AppState::LoadFailed)
+                    .load_collection::<ImageAssets>()
+                    .load_collection::<AudioAssets>(),
+            )
+            //  NOTE: themes and sprite packs are optional, a broken one shouldn't block the game
+            .init_collection::<ThemeAssets>()
+            .init_collection::<SpritePackAssets>();
     }
 }
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use bevy::{audio::Volume, prelude::*};
+
+use crate::{
+    asset_loader::AudioAssets,
+    food::FoodEvent,
+    schedule::InGameSet,
+    settings::Settings,
+    snake::{Direction, MovementTimer, TurnEvent},
+    state::GameState,
+};
+
+//  NOTE: background loop, one note per beat, 0.0 is a rest
+const MELODY: [f32; 16] = [
+    261.63, 0.0, 329.63, 392.00, 440.00, 0.0, 392.00, 329.63, //
+    293.66, 0.0, 349.23, 440.00, 392.00, 0.0, 329.63, 0.0,
+];
+
+//  NOTE: a beat lasts this many snake moves, so the music speeds up with the game
+const MOVES_PER_BEAT: u32 = 2;
+
+#[derive(Resource, Debug)]
+struct Sounds {
+    turn_up: Handle<Pitch>,
+    turn_down: Handle<Pitch>,
+    turn_side: Handle<Pitch>,
+    game_over: Handle<Pitch>,
+    melody: Vec<Option<Handle<Pitch>>>,
+}
+
+#[derive(Resource, Debug, Default)]
+struct MusicPlayer {
+    timer: Timer,
+    beat: usize,
+}
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicPlayer>()
+            .add_systems(Startup, load_sounds)
+            .add_systems(OnEnter(GameState::NewGame), reset_music)
+            .add_systems(OnEnter(GameState::GameOver), play_game_over)
+            .add_systems(
+                Update,
+                (play_food_eaten, play_turn, play_music).in_set(InGameSet::EntityUpdates),
+            );
+    }
+}
+
+fn load_sounds(mut commands: Commands, mut pitches: ResMut<Assets<Pitch>>) {
+    let blip = Duration::from_millis(40);
+    commands.insert_resource(Sounds {
+        turn_up: pitches.add(Pitch::new(660.0, blip)),
+        turn_down: pitches.add(Pitch::new(440.0, blip)),
+        turn_side: pitches.add(Pitch::new(550.0, blip)),
+        game_over: pitches.add(Pitch::new(110.0, Duration::from_millis(600))),
+        melody: MELODY
+            .iter()
+            .map(|&frequency| {
+                (frequency > 0.0)
+                    .then(|| pitches.add(Pitch::new(frequency, Duration::from_millis(120))))
+            })
+            .collect(),
+    });
+}
+
+fn reset_music(mut music: ResMut<MusicPlayer>) {
+    music.beat = 0;
+    music.timer.reset();
+}
+
+fn play_food_eaten(
+    mut commands: Commands,
+    mut food_event_reader: EventReader<FoodEvent>,
+    audio_assets: Res<AudioAssets>,
+    settings: Res<Settings>,
+) {
+    for _ in food_event_reader.read() {
+        commands.spawn(AudioBundle {
+            source: audio_assets.eat.clone(),
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(Volume::new(settings.audio.sfx_volume())),
+        });
+    }
+}
+
+fn play_turn(
+    mut commands: Commands,
+    mut turn_event_reader: EventReader<TurnEvent>,
+    sounds: Res<Sounds>,
+    settings: Res<Settings>,
+) {
+    for &TurnEvent { direction } in turn_event_reader.read() {
+        let source = match direction {
+            Direction::Up => sounds.turn_up.clone(),
+            Direction::Down => sounds.turn_down.clone(),
+            Direction::Left | Direction::Right => sounds.turn_side.clone(),
+        };
+        commands.spawn(PitchBundle {
+            source,
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(Volume::new(settings.audio.sfx_volume() * 0.3)),
+        });
+    }
+}
+
+fn play_game_over(mut commands: Commands, sounds: Res<Sounds>, settings: Res<Settings>) {
+    commands.spawn(PitchBundle {
+        source: sounds.game_over.clone(),
+        settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(settings.audio.sfx_volume())),
+    });
+}
+
+fn play_music(
+    mut commands: Commands,
+    mut music: ResMut<MusicPlayer>,
+    movement_timer: Res<MovementTimer>,
+    sounds: Res<Sounds>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    //  NOTE: follow the game speed, the movement step can change between games
+    let beat = movement_timer.step() * MOVES_PER_BEAT;
+    if music.timer.duration() != beat {
+        music.timer = Timer::new(beat, TimerMode::Repeating);
+    }
+
+    music.timer.tick(time.delta());
+    if !music.timer.just_finished() {
+        return;
+    }
+
+    let note = music.beat % sounds.melody.len();
+    music.beat += 1;
+
+    let volume = settings.audio.music_volume();
+    if volume <= 0.0 {
+        return;
+    }
+
+    if let Some(source) = &sounds.melody[note] {
+        commands.spawn(PitchBundle {
+            source: source.clone(),
+            settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(volume * 0.25)),
+        });
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::{
+    board::Board,
+    food::Food,
+    hamiltonian::HamiltonianSolver,
+    hazard::Enemy,
+    level::{Portal, Wall},
+    neuro::{Network, GENOME_FILE},
+    schedule::InGameSet,
+    settings::{AiPlayer, Settings},
+    sim::Grid,
+    snake::{Direction, Position, SnakeHead, SnakeHeadDirection, SnakeSegment},
+    state::GameState,
+};
+
+//  NOTE: when on, the player's snake is handed to the autopilot, the "Watch AI" menu entry
+#[derive(Resource, Debug, Default)]
+pub struct Autopilot {
+    pub enabled: bool,
+}
+
+//  NOTE: snake heads with this component are steered by `plan_direction` instead of the player,
+//  multi-snake modes can spawn opponents with it directly
+#[derive(Component, Debug, Default)]
+pub struct AiControlled {
+    //  NOTE: the head position the last direction was planned from, so we plan once per move
+    planned_from: Option<Position>,
+}
+
+//  NOTE: the network `AiPlayer::Neural` plays, read from `GENOME_FILE` on first use. `None` until
+//  then, `Some(None)` when it couldn't be loaded
+#[derive(Resource, Default)]
+struct Genome(Option<Option<Network>>);
+
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Autopilot>()
+            .init_resource::<Genome>()
+            //  NOTE: every new board starts with the player in control again, and reads the genome
+            //  again in case a newer one was trained since
+            .add_systems(
+                OnEnter(GameState::NewGame),
+                (disable_autopilot, forget_genome),
+            )
+            .add_systems(Update, (sync_player_autopilot, switch_genome))
+            .add_systems(Update, autopilot_controls.in_set(InGameSet::UserInput));
+    }
+}
+
+fn disable_autopilot(mut autopilot: ResMut<Autopilot>) {
+    autopilot.enabled = false;
+}
+
+fn forget_genome(mut genome: ResMut<Genome>) {
+    genome.0 = None;
+}
+
+//  NOTE: picking the neural AI in the settings reads the genome again too
+fn switch_genome(
+    mut genome: ResMut<Genome>,
+    settings: Res<Settings>,
+    mut ai: Local<Option<AiPlayer>>,
+) {
+    let picked = settings.gameplay.ai;
+    if ai.replace(picked) != Some(picked) && picked == AiPlayer::Neural {
+        genome.0 = None;
+    }
+}
+
+fn sync_player_autopilot(
+    mut commands: Commands,
+    autopilot: Res<Autopilot>,
+    query: Query<(Entity, Has<AiControlled>), With<SnakeHead>>,
+    added_query: Query<(), Added<SnakeHead>>,
+) {
+    if !autopilot.is_changed() && added_query.is_empty() {
+        return;
+    }
+
+    for (entity, ai_controlled) in query.iter() {
+        if autopilot.enabled && !ai_controlled {
+            commands.entity(entity).insert(AiControlled::default());
+        } else if !autopilot.enabled && ai_controlled {
+            commands.entity(entity).remove::<AiControlled>();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn autopilot_controls(
+    board: Res<Board>,
+    settings: Res<Settings>,
+    mut snake_head_query: Query<
+        (&mut SnakeHeadDirection, &mut AiControlled, &Position),
+        With<SnakeHead>,
+    >,
+    snake_body_query: Query<&Position, (With<SnakeSegment>, Without<SnakeHead>)>,
+    food_query: Query<&Position, With<Food>>,
+    //  NOTE: the bots steer clear of portals, the path finding doesn't know where they lead, and
+    //  of enemies where they are right now
+    wall_query: Query<&Position, Or<(With<Wall>, With<Portal>, With<Enemy>)>>,
+    mut hamiltonian: Local<Option<HamiltonianSolver>>,
+    mut genome: ResMut<Genome>,
+) {
+    let Ok((mut head_direction, mut ai, head_pos)) = snake_head_query.get_single_mut() else {
+        return;
+    };
+
+    //  NOTE: wait for the previous decision to be used up before planning the next one
+    if head_direction.has_queued() || ai.planned_from == Some(*head_pos) {
+        return;
+    }
+    ai.planned_from = Some(*head_pos);
+
+    //  NOTE: same order as `update_position` walks the segments, head first and tail last
+    let snake: Vec<Position> = std::iter::once(*head_pos)
+        .chain(snake_body_query.iter().copied())
+        .collect();
+    let food: Vec<Position> = food_query.iter().copied().collect();
+    let walls: HashSet<Position> = wall_query.iter().copied().collect();
+
+    let grid = board.grid(settings.gameplay.walls());
+    let planned = match settings.gameplay.ai {
+        AiPlayer::Pathfinding => None,
+        //  NOTE: the cycle only knows open boards, levels path find
+        AiPlayer::Hamiltonian if !walls.is_empty() => None,
+        AiPlayer::Hamiltonian => {
+            //  NOTE: the cycle only depends on the board size, build it again when that changes
+            if hamiltonian.as_ref().map(|solver| solver.size()) != Some(board.size) {
+                *hamiltonian = Some(HamiltonianSolver::new(board.size));
+            }
+            hamiltonian
+                .as_ref()
+                .and_then(|solver| solver.plan_direction(&grid, &snake, &food))
+        }
+        AiPlayer::Neural => {
+            let network = genome
+                .0
+                .get_or_insert_with(|| match Network::load(GENOME_FILE) {
+                    Ok(network) => Some(network),
+                    Err(err) => {
+                        warn!("{err}, path finding instead");
+                        None
+                    }
+                });
+            network.as_ref().map(|network| {
+                network.plan_direction(&grid, &snake, &food, &walls, head_direction.current())
+            })
+        }
+    };
+    //  NOTE: the cycle can't be followed until the snake lines up with it, path find until then
+    let planned = planned.or_else(|| plan_direction(&grid, &snake, &walls, &food));
+
+    if let Some(direction) = planned {
+        if direction != head_direction.current() {
+            head_direction.queue_direction(direction);
+        }
+    }
+}
+
+impl Grid {
+    //  NOTE: breadth first search, returns the cells walked from `from` (excluded) to the closest
+    //  of `targets` (included)
+    fn path(
+        &self,
+        from: Position,
+        targets: &HashSet<Position>,
+        blocked: &HashSet<Position>,
+    ) -> Option<Vec<Position>> {
+        let mut came_from: HashMap<Position, Position> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(pos) = queue.pop_front() {
+            for direction in Direction::ALL {
+                let Some(next) = self.step(pos, direction) else {
+                    continue;
+                };
+                if next == from || came_from.contains_key(&next) {
+                    continue;
+                }
+
+                if targets.contains(&next) {
+                    let mut path = vec![next];
+                    let mut cursor = pos;
+                    while cursor != from {
+                        path.push(cursor);
+                        cursor = came_from[&cursor];
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+
+                if !blocked.contains(&next) {
+                    came_from.insert(next, pos);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    //  NOTE: how many free cells can still be reached from `from`
+    fn reachable(&self, from: Position, blocked: &HashSet<Position>) -> usize {
+        let mut seen = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(pos) = queue.pop_front() {
+            for direction in Direction::ALL {
+                if let Some(next) = self.step(pos, direction) {
+                    if !blocked.contains(&next) && seen.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        seen.len()
+    }
+
+    fn direction_to(&self, from: Position, to: Position) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|&direction| self.step(from, direction) == Some(to))
+    }
+}
+
+//  NOTE: cells the head can't move into, the tail is left out since it moves away on the same tick
+fn blocked_cells(snake: &[Position], obstacles: &HashSet<Position>) -> HashSet<Position> {
+    snake[..snake.len() - 1]
+        .iter()
+        .chain(obstacles.iter())
+        .copied()
+        .collect()
+}
+
+//  NOTE: `snake` is ordered head first, `obstacles` holds anything else to avoid like other snakes.
+//  1. take the shortest path to food if the tail can still be reached once it's eaten
+//  2. otherwise chase the tail, following it always leaves a way out
+//  3. otherwise move to whichever neighbour keeps the most room open
+pub fn plan_direction(
+    grid: &Grid,
+    snake: &[Position],
+    obstacles: &HashSet<Position>,
+    food: &[Position],
+) -> Option<Direction> {
+    let (&head, _) = snake.split_first()?;
+    let tail = *snake.last()?;
+    let blocked = blocked_cells(snake, obstacles);
+
+    let targets: HashSet<Position> = food.iter().copied().collect();
+    if let Some(path) = grid.path(head, &targets, &blocked) {
+        //  NOTE: where the snake ends up after eating, it grows by one segment
+        let mut future: Vec<Position> = path.iter().rev().copied().collect();
+        future.extend_from_slice(snake);
+        future.truncate(snake.len() + 1);
+
+        let future_tail = HashSet::from([*future.last()?]);
+        let future_blocked = blocked_cells(&future, obstacles);
+        if grid
+            .path(future[0], &future_tail, &future_blocked)
+            .is_some()
+        {
+            return grid.direction_to(head, path[0]);
+        }
+    }
+
+    if snake.len() > 2 {
+        if let Some(path) = grid.path(head, &HashSet::from([tail]), &blocked) {
+            return grid.direction_to(head, path[0]);
+        }
+    }
+
+    Direction::ALL
+        .into_iter()
+        .filter_map(|direction| {
+            let next = grid.step(head, direction)?;
+            (!blocked.contains(&next)).then_some((direction, next))
+        })
+        .max_by_key(|&(_, next)| {
+            let mut after = blocked.clone();
+            after.insert(next);
+            grid.reachable(next, &after)
+        })
+        .map(|(direction, _)| direction)
+}
@@ -0,0 +1,526 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    asset_loader::SpritePart,
+    autopilot::Autopilot,
+    board::{Board, LoadBoard, TILE_SIZE},
+    camera::CameraFocus,
+    royale::{RoyaleGame, MAX_ROYALE_HUMANS, MAX_ROYALE_SNAKES, MIN_ROYALE_SNAKES},
+    schedule::SteerSet,
+    score::Score,
+    settings::Settings,
+    snake::{segment_part, Direction, Position, SteerEvent},
+    sprite_pack::CurrentSpritePack,
+    state::{GameState, MenuState},
+    theme::{color32, CurrentTheme},
+    versus::VersusSnake,
+};
+
+//  NOTE: seconds to find your snake before the first move
+const START_DELAY: f64 = 1.5;
+//  NOTE: turns typed ahead of the snake, like `SnakeHeadDirection`
+const MAX_QUEUED_TURNS: usize = 2;
+//  NOTE: the second player's snake, the first one keeps the theme's color
+const SECOND_PLAYER_TINT: Color = Color::rgb(1.0, 1.0, 0.55);
+//  NOTE: the bots are tinted so everyone can share the sprite sheet
+const BOT_TINTS: [Color; 8] = [
+    Color::rgb(0.55, 0.7, 1.0),
+    Color::rgb(1.0, 0.6, 0.6),
+    Color::rgb(0.75, 0.6, 1.0),
+    Color::rgb(1.0, 0.85, 0.45),
+    Color::rgb(0.5, 0.95, 0.85),
+    Color::rgb(1.0, 0.6, 0.9),
+    Color::rgb(0.7, 0.7, 0.7),
+    Color::rgb(0.95, 0.7, 0.45),
+];
+//  NOTE: laid over the closed off cells, the board shows through
+const WALL_TINT: Color = Color::rgba(0.1, 0.1, 0.1, 0.6);
+const MINIMAP_SIZE: f32 = 150.0;
+const MINIMAP_FOOD: egui::Color32 = egui::Color32::from_rgb(231, 71, 29);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RoyaleStatus {
+    #[default]
+    Off,
+    //  NOTE: picked in the menu, the match is set up on the next frame
+    Starting,
+    //  NOTE: until the next `NewGame`, local game systems are off
+    Playing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoyaleResult {
+    //  NOTE: the best place of the players
+    pub place: usize,
+    pub snakes: usize,
+    //  NOTE: who got it when several players shared the keyboard, `None` for a single player
+    pub player: Option<usize>,
+}
+
+impl RoyaleResult {
+    pub fn label(&self) -> String {
+        let who = match self.player {
+            Some(player) => format!("Player {}", player + 1),
+            None => "You".to_string(),
+        };
+        if self.place == 1 {
+            return format!("{who} won the battle royale!");
+        }
+        let suffix = match (self.place % 10, self.place % 100) {
+            (_, 11..=13) => "th",
+            (1, _) => "st",
+            (2, _) => "nd",
+            (3, _) => "rd",
+            _ => "th",
+        };
+        format!("{who} placed {}{suffix} of {}", self.place, self.snakes)
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct BattleRoyale {
+    status: RoyaleStatus,
+    //  NOTE: how the last match went, shown in the menu
+    last_result: Option<RoyaleResult>,
+    game: Option<RoyaleGame>,
+    //  NOTE: seconds not yet played as moves, starts negative to wait `START_DELAY`
+    clock: f64,
+    //  NOTE: the players steer the first snakes, the bots from `RoyaleGame::plan` the rest
+    humans: usize,
+    //  NOTE: turns typed ahead, one queue per player
+    turns: Vec<VecDeque<Direction>>,
+}
+
+impl BattleRoyale {
+    pub fn status(&self) -> RoyaleStatus {
+        self.status
+    }
+
+    pub fn last_result(&self) -> Option<RoyaleResult> {
+        self.last_result
+    }
+
+    pub fn start(&mut self) {
+        self.status = RoyaleStatus::Starting;
+        self.last_result = None;
+    }
+
+    pub fn in_match(&self) -> bool {
+        self.status == RoyaleStatus::Playing
+    }
+
+    //  NOTE: the players' places, `None` for those still alive
+    fn places(&self) -> impl Iterator<Item = (usize, Option<usize>)> + '_ {
+        self.game
+            .iter()
+            .flat_map(|game| game.state().places.iter().copied().take(self.humans))
+            .enumerate()
+    }
+
+    fn finish(&mut self) {
+        let snakes = self
+            .game
+            .as_ref()
+            .map_or(0, |game| game.state().snakes.len());
+        let best = self
+            .places()
+            .filter_map(|(player, place)| Some((player, place?)))
+            .min_by_key(|&(_, place)| place);
+        if let Some((player, place)) = best {
+            self.last_result = Some(RoyaleResult {
+                place,
+                snakes,
+                player: (self.humans > 1).then_some(player),
+            });
+        }
+        self.turns.clear();
+    }
+}
+
+pub fn in_battle_royale(royale: Res<BattleRoyale>) -> bool {
+    royale.in_match()
+}
+
+#[derive(Component, Debug)]
+struct RoyaleSprite;
+
+pub struct BattleRoyalePlugin;
+
+impl Plugin for BattleRoyalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BattleRoyale>()
+            .add_systems(OnEnter(GameState::NewGame), leave_finished_match)
+            .add_systems(Update, start_match)
+            .add_systems(
+                Update,
+                (royale_controls, advance_match)
+                    .chain()
+                    .after(start_match)
+                    .after(SteerSet)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_battle_royale),
+            )
+            .add_systems(
+                Update,
+                (render_match, update_royale_hud, update_minimap)
+                    .after(advance_match)
+                    .run_if(in_battle_royale),
+            );
+    }
+}
+
+//  NOTE: the snakes and the food went with `despawn_all_entities`, the wall overlays aren't board
+//  cells and are left to us
+fn leave_finished_match(
+    mut commands: Commands,
+    mut royale: ResMut<BattleRoyale>,
+    mut focus: ResMut<CameraFocus>,
+    query: Query<Entity, With<RoyaleSprite>>,
+) {
+    if royale.in_match() {
+        royale.status = RoyaleStatus::Off;
+        royale.game = None;
+        *focus = CameraFocus::default();
+        for entity in query.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_match(
+    mut commands: Commands,
+    mut royale: ResMut<BattleRoyale>,
+    mut autopilot: ResMut<Autopilot>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    mut load_board: EventWriter<LoadBoard>,
+    settings: Res<Settings>,
+    local_query: Query<Entity, (With<Position>, Without<RoyaleSprite>)>,
+) {
+    if royale.status != RoyaleStatus::Starting {
+        return;
+    }
+
+    let snakes = settings
+        .gameplay
+        .royale_snakes
+        .clamp(MIN_ROYALE_SNAKES, MAX_ROYALE_SNAKES);
+    let humans = settings.gameplay.royale_humans.clamp(1, MAX_ROYALE_HUMANS);
+    let game = RoyaleGame::new(snakes, rand::random());
+    load_board.send(LoadBoard {
+        size: game.grid().size,
+    });
+    royale.game = Some(game);
+    royale.status = RoyaleStatus::Playing;
+    royale.clock = -START_DELAY;
+    royale.humans = humans;
+    royale.turns = vec![VecDeque::new(); humans];
+
+    //  NOTE: clear the local board, it comes back with the next `NewGame`
+    for entity in local_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    autopilot.enabled = false;
+    next_menu_state.set(MenuState::Off);
+    next_state.set(GameState::Playing);
+}
+
+fn royale_controls(
+    mut steer_event_reader: EventReader<SteerEvent>,
+    mut royale: ResMut<BattleRoyale>,
+) {
+    let royale = royale.bypass_change_detection();
+    let Some(game) = &royale.game else {
+        steer_event_reader.clear();
+        return;
+    };
+
+    for &SteerEvent { direction, seat } in steer_event_reader.read() {
+        //  NOTE: a single player takes every seat
+        let player = seat.min(royale.humans - 1);
+        let (Some(snake), Some(turns)) = (
+            game.state().snakes.get(player),
+            royale.turns.get_mut(player),
+        ) else {
+            continue;
+        };
+        //  NOTE: same rule as `queue_direction`, no repeats and no turning back into the neck
+        let heading = turns.back().copied().unwrap_or(snake.direction);
+        if direction == heading
+            || direction == heading.opposite()
+            || turns.len() >= MAX_QUEUED_TURNS
+        {
+            continue;
+        }
+        turns.push_back(direction);
+    }
+}
+
+fn advance_match(
+    mut royale: ResMut<BattleRoyale>,
+    mut score: ResMut<Score>,
+    mut next_state: ResMut<NextState<GameState>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let step = settings.gameplay.speed.step().as_secs_f64();
+
+    //  NOTE: only redraw when a move was made
+    let royale_ref = royale.bypass_change_detection();
+    let Some(game) = &mut royale_ref.game else {
+        return;
+    };
+    royale_ref.clock += time.delta_seconds_f64();
+    let humans = royale_ref.humans;
+    let mut stepped = false;
+    let mut over = false;
+    while royale_ref.clock >= step && !over {
+        royale_ref.clock -= step;
+        let turns: Vec<Option<Direction>> = (0..game.state().snakes.len())
+            .map(|player| match royale_ref.turns.get_mut(player) {
+                Some(turns) => turns.pop_front(),
+                None => game.plan(player),
+            })
+            .collect();
+        game.step(&turns);
+        stepped = true;
+
+        //  NOTE: the match is over for us once every player is out, the bots aren't worth
+        //  waiting on
+        over = game.state().places[..humans].iter().all(Option::is_some);
+    }
+    if !stepped {
+        return;
+    }
+
+    let players = &game.state().snakes[..humans];
+    score.value = players.iter().map(|snake| snake.score).max().unwrap_or(0) as i32;
+    royale.set_changed();
+    if over {
+        royale.finish();
+        next_state.set(GameState::GameOver);
+    }
+}
+
+fn render_match(
+    mut commands: Commands,
+    royale: Res<BattleRoyale>,
+    sprites: Res<CurrentSpritePack>,
+    theme: Res<CurrentTheme>,
+    mut focus: ResMut<CameraFocus>,
+    query: Query<Entity, With<RoyaleSprite>>,
+) {
+    if !royale.is_changed() {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let Some(game) = &royale.game else {
+        return;
+    };
+    let state = game.state();
+    let grid = game.grid();
+    //  NOTE: the arena's own board, `Board` catches up once `LoadBoard` is handled
+    let board = Board::new(grid.size);
+
+    let mut spawn = |pos: Position, part: SpritePart, color: Color, z: f32| {
+        commands.spawn((
+            SpriteSheetBundle {
+                atlas: TextureAtlas {
+                    layout: sprites.layout.clone(),
+                    index: sprites.index(part),
+                },
+                texture: sprites.texture.clone(),
+                transform: Transform::from_xyz(
+                    board.position_translate(pos.x),
+                    board.position_translate(pos.y),
+                    z,
+                ),
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+            RoyaleSprite,
+            pos,
+        ));
+    };
+
+    for &food in &state.food {
+        spawn(food, SpritePart::Apple, theme.food, 1.0);
+    }
+
+    for (player, snake) in state.snakes.iter().enumerate() {
+        //  NOTE: dead snakes live on as the food they left behind
+        if !snake.alive {
+            continue;
+        }
+        let color = match player {
+            0 => theme.snake,
+            _ if player < royale.humans => SECOND_PLAYER_TINT,
+            _ => BOT_TINTS[(player - royale.humans) % BOT_TINTS.len()],
+        };
+        let directions = snake.directions(grid);
+        for (i, &pos) in snake.body.iter().enumerate() {
+            let z = if i == 0 { 3.0 } else { 2.0 };
+            spawn(pos, segment_part(&directions, i), color, z);
+        }
+    }
+
+    //  NOTE: one strip per side over the closed off cells, corners are covered twice
+    let margin = state.margin as f32 * TILE_SIZE;
+    if margin > 0.0 {
+        let half = board.physical_size / 2.0;
+        for (center, size) in [
+            (
+                Vec2::new(0.0, half - margin / 2.0),
+                Vec2::new(board.physical_size, margin),
+            ),
+            (
+                Vec2::new(0.0, -half + margin / 2.0),
+                Vec2::new(board.physical_size, margin),
+            ),
+            (
+                Vec2::new(-half + margin / 2.0, 0.0),
+                Vec2::new(margin, board.physical_size),
+            ),
+            (
+                Vec2::new(half - margin / 2.0, 0.0),
+                Vec2::new(margin, board.physical_size),
+            ),
+        ] {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: WALL_TINT,
+                        custom_size: Some(size),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(center.extend(0.8)),
+                    ..default()
+                },
+                RoyaleSprite,
+            ));
+        }
+    }
+
+    //  NOTE: in between the players still alive, looking ahead only while there's one of them
+    let alive: Vec<&VersusSnake> = state.snakes[..royale.humans]
+        .iter()
+        .filter(|snake| snake.alive)
+        .collect();
+    if !alive.is_empty() {
+        let heads: Vec2 = alive
+            .iter()
+            .map(|snake| {
+                let head = snake.body[0];
+                Vec2::new(
+                    board.position_translate(head.x),
+                    board.position_translate(head.y),
+                )
+            })
+            .sum();
+        focus.target = Some(heads / alive.len() as f32);
+        focus.heading = match alive[..] {
+            [snake] => Some(snake.direction),
+            _ => None,
+        };
+    }
+}
+
+fn update_royale_hud(
+    mut contexts: EguiContexts,
+    royale: Res<BattleRoyale>,
+    settings: Res<Settings>,
+    theme: Res<CurrentTheme>,
+) {
+    let Some(game) = &royale.game else {
+        return;
+    };
+    let state = game.state();
+
+    let lengths: Vec<String> = state.snakes[..royale.humans]
+        .iter()
+        .map(|snake| snake.body.len().to_string())
+        .collect();
+    let mut text = format!(
+        "{} of {} left  ·  length {}",
+        state.alive(),
+        state.snakes.len(),
+        lengths.join(" / ")
+    );
+    match game.moves_to_shrink() {
+        Some(moves) => {
+            let seconds = moves as f32 * settings.gameplay.speed.step().as_secs_f32();
+            text.push_str(&format!("  ·  walls close in {seconds:.0}s"));
+        }
+        None => text.push_str("  ·  final arena"),
+    }
+    if state.tick == 0 {
+        text.push_str("  ·  get ready");
+    }
+
+    egui::Area::new(egui::Id::new("royale-hud"))
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 70.0))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                egui::RichText::new(text)
+                    .color(color32(theme.text))
+                    .font(egui::FontId::monospace(18.0)),
+            );
+        });
+}
+
+//  NOTE: the whole arena in a corner, the camera only shows the part around the player
+fn update_minimap(mut contexts: EguiContexts, royale: Res<BattleRoyale>, theme: Res<CurrentTheme>) {
+    let Some(game) = &royale.game else {
+        return;
+    };
+    let state = game.state();
+    let size = game.grid().size as f32;
+
+    egui::Area::new(egui::Id::new("royale-minimap"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-15.0, -15.0))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let (rect, _) = ui
+                .allocate_exact_size(egui::vec2(MINIMAP_SIZE, MINIMAP_SIZE), egui::Sense::hover());
+            let painter = ui.painter_at(rect);
+            let cell = MINIMAP_SIZE / size;
+            //  NOTE: board rows count up, screen rows count down
+            let cell_rect = |pos: Position| {
+                let min = rect.left_top()
+                    + egui::vec2(pos.x as f32 * cell, (size - 1.0 - pos.y as f32) * cell);
+                egui::Rect::from_min_size(min, egui::vec2(cell, cell))
+            };
+
+            painter.rect_filled(rect, 4.0, color32(WALL_TINT));
+            let open = rect.shrink(state.margin as f32 * cell);
+            painter.rect_filled(open, 0.0, color32(theme.board));
+            for &food in &state.food {
+                painter.rect_filled(cell_rect(food), 0.0, MINIMAP_FOOD);
+            }
+            for (player, snake) in state.snakes.iter().enumerate() {
+                if !snake.alive {
+                    continue;
+                }
+                let color = if player < royale.humans {
+                    egui::Color32::WHITE
+                } else {
+                    color32(BOT_TINTS[(player - royale.humans) % BOT_TINTS.len()])
+                };
+                for &pos in &snake.body {
+                    painter.rect_filled(cell_rect(pos), 0.0, color);
+                }
+            }
+            painter.rect_stroke(rect, 4.0, egui::Stroke::new(1.0, color32(theme.text)));
+        });
+}
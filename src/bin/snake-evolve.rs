@@ -0,0 +1,128 @@
+//  NOTE: trains the "Neural network" AI with a genetic algorithm, every generation is played
+//  headlessly and the best genome so far is saved after each one, pick "Neural network" in the
+//  gameplay settings and hit "Watch AI" to see it play.
+//
+//  cargo run --release --bin snake-evolve -- --generations 200 --population 300 --out genome.ron
+use std::{env, process, time::Instant};
+
+use snake::{
+    neuro::{Trainer, TrainerConfig, GENOME_FILE},
+    settings::{WallsMode, DEFAULT_BOARD_SIZE, MAX_BOARD_SIZE, MIN_BOARD_SIZE},
+    sim::Rules,
+};
+
+const USAGE: &str = "usage: snake-evolve [options]
+  --generations <n>       generations to train (default 100)
+  --population <n>        genomes per generation (default 200)
+  --games <n>             games each genome plays per generation (default 3)
+  --elite <n>             best genomes kept unchanged (default 10)
+  --mutation-rate <p>     chance of each weight being nudged (default 0.05)
+  --mutation-strength <x> largest nudge (default 0.5)
+  --hidden <a,b,..>       hidden layer sizes (default 16,16)
+  --board <size>          board size (default 20)
+  --walls <solid|wrap>    walls mode (default solid)
+  --starvation <moves>    moves without eating before a game is called off
+  --seed <n>              seed for the initial population and breeding (default 0)
+  --out <path>            where the best genome is saved (default genome.ron)";
+
+fn fail(message: &str) -> ! {
+    eprintln!("snake-evolve: {message}\n\n{USAGE}");
+    process::exit(2);
+}
+
+fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    let Some(value) = value else {
+        fail(&format!("missing value for {flag}"));
+    };
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("invalid value for {flag}: {value:?}")))
+}
+
+fn main() {
+    let mut config = TrainerConfig::default();
+    let mut generations: u32 = 100;
+    let mut board = DEFAULT_BOARD_SIZE;
+    let mut walls = WallsMode::Solid;
+    let mut starvation: Option<u32> = None;
+    let mut seed: u64 = 0;
+    let mut out = GENOME_FILE.to_string();
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--generations" => generations = value(&flag, args.next()),
+            "--population" => config.population = value(&flag, args.next()),
+            "--games" => config.games = value(&flag, args.next()),
+            "--elite" => config.elite = value(&flag, args.next()),
+            "--mutation-rate" => config.mutation_rate = value(&flag, args.next()),
+            "--mutation-strength" => config.mutation_strength = value(&flag, args.next()),
+            "--hidden" => {
+                let layers: String = value(&flag, args.next());
+                config.hidden = layers
+                    .split(',')
+                    .map(|size| {
+                        size.trim()
+                            .parse()
+                            .unwrap_or_else(|_| fail(&format!("invalid layer size {size:?}")))
+                    })
+                    .collect();
+            }
+            "--board" => board = value(&flag, args.next()),
+            "--walls" => {
+                walls = match value::<String>(&flag, args.next()).as_str() {
+                    "solid" => WallsMode::Solid,
+                    "wrap" => WallsMode::Wrap,
+                    other => fail(&format!("unknown walls mode {other:?}")),
+                }
+            }
+            "--starvation" => starvation = Some(value(&flag, args.next())),
+            "--seed" => seed = value(&flag, args.next()),
+            "--out" => out = value(&flag, args.next()),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            other => fail(&format!("unknown option {other:?}")),
+        }
+    }
+
+    if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&board) {
+        fail(&format!(
+            "board size must be between {MIN_BOARD_SIZE} and {MAX_BOARD_SIZE}"
+        ));
+    }
+    if !(0.0..=1.0).contains(&config.mutation_rate) {
+        fail("mutation rate must be between 0 and 1");
+    }
+    if config.mutation_strength <= 0.0 {
+        fail("mutation strength must be positive");
+    }
+
+    config.rules = Rules::new(board, walls);
+    if let Some(starvation) = starvation {
+        config.rules.starvation = starvation;
+    }
+
+    let mut trainer = Trainer::new(config, seed);
+    let started = Instant::now();
+    for _ in 0..generations {
+        let stats = trainer.evolve();
+        println!(
+            "generation {:>4}: best fitness {:>8.1}, mean fitness {:>8.1}, best score {:>6.2}, {:.1?}",
+            stats.generation,
+            stats.best_fitness,
+            stats.mean_fitness,
+            stats.best_score,
+            started.elapsed()
+        );
+
+        if let Some(best) = trainer.best() {
+            if let Err(err) = best.save(&out) {
+                eprintln!("snake-evolve: {out}: {err}");
+                process::exit(1);
+            }
+        }
+    }
+    println!("best genome saved to {out}");
+}
@@ -0,0 +1,425 @@
+//  NOTE: relay server for online versus. Players connect over WebSockets, get paired with the
+//  next player asking for the same board and the server runs the match, the clients only send
+//  their turns and draw the ticks they're sent. With `--rollback` the clients run the match
+//  themselves and the server only passes their inputs along, see `snake::rollback`. It also
+//  relays game streams to spectators, see `snake::stream`.
+//
+//  cargo run --release --bin snake-server -- --addr 127.0.0.1:7878
+use std::{
+    collections::HashMap,
+    env,
+    io::ErrorKind,
+    net::{TcpListener, TcpStream},
+    process,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use snake::{
+    protocol::{
+        socket::{pump, POLL},
+        ClientMessage, EndReason, MatchMode, MatchRules, PeerEvent, ServerMessage,
+    },
+    settings::{MAX_BOARD_SIZE, MIN_BOARD_SIZE},
+    sim::Grid,
+    snake::Direction,
+    stream::{StreamEvent, StreamSnapshot},
+    versus::{ScheduledTurns, VersusGame, VersusOutcome},
+};
+
+const USAGE: &str = "usage: snake-server [options]
+  --addr <host:port>  address to listen on (default 127.0.0.1:7878)
+  --rollback          relay inputs between peers instead of running the matches";
+
+//  NOTE: time for both clients to set up the board before the first move
+const START_DELAY: Duration = Duration::from_secs(2);
+
+//  NOTE: what a client asked for with its first message
+enum Greeting {
+    Play(MatchRules),
+    Broadcast(String),
+    Watch(String),
+}
+
+//  NOTE: a named stream, spectators can show up before the broadcaster does
+#[derive(Default)]
+struct Stream {
+    broadcaster: Option<Peer>,
+    spectators: Vec<Peer>,
+    //  NOTE: kept up to date with the deltas for spectators joining late
+    snapshot: Option<StreamSnapshot>,
+    over: bool,
+}
+
+impl Stream {
+    fn join(&mut self, spectator: Peer) {
+        match &self.snapshot {
+            Some(snapshot) => {
+                spectator.send(ServerMessage::Stream(StreamEvent::Snapshot(
+                    snapshot.clone(),
+                )));
+                if self.over {
+                    spectator.send(ServerMessage::Stream(StreamEvent::GameOver));
+                }
+            }
+            None => spectator.send(ServerMessage::Waiting),
+        }
+        self.spectators.push(spectator);
+    }
+
+    //  NOTE: `false` once the broadcaster left and the stream can go
+    fn relay(&mut self, name: &str) -> bool {
+        self.spectators.retain(|spectator| spectator.poll().is_ok());
+        let Some(broadcaster) = &self.broadcaster else {
+            return !self.spectators.is_empty();
+        };
+
+        let messages = match broadcaster.poll() {
+            Ok(messages) => messages,
+            Err(reason) => {
+                println!("stream {name:?} ended: {reason}");
+                for spectator in &self.spectators {
+                    spectator.send(ServerMessage::StreamEnded);
+                }
+                return false;
+            }
+        };
+        self.publish(messages);
+        true
+    }
+
+    fn publish(&mut self, messages: impl IntoIterator<Item = ClientMessage>) {
+        for message in messages {
+            let ClientMessage::Stream(event) = message else {
+                continue;
+            };
+            match &event {
+                StreamEvent::Snapshot(snapshot) => {
+                    self.snapshot = Some(snapshot.clone());
+                    self.over = false;
+                }
+                StreamEvent::Tick(delta) => match &mut self.snapshot {
+                    Some(snapshot) => snapshot.apply(delta),
+                    //  NOTE: nothing to apply it to, spectators wait for the next snapshot
+                    None => continue,
+                },
+                StreamEvent::GameOver => self.over = true,
+            }
+            for spectator in &self.spectators {
+                spectator.send(ServerMessage::Stream(event.clone()));
+            }
+        }
+    }
+}
+
+struct Peer {
+    name: String,
+    outgoing: Sender<ServerMessage>,
+    incoming: Receiver<PeerEvent<ClientMessage>>,
+}
+
+impl Peer {
+    fn send(&self, message: ServerMessage) {
+        let _ = self.outgoing.send(message);
+    }
+
+    //  NOTE: the messages received since the last call, `Err` once the peer is gone. Pings are
+    //  answered here so every caller keeps the latency readout going
+    fn poll(&self) -> Result<Vec<ClientMessage>, String> {
+        let mut messages = Vec::new();
+        loop {
+            match self.incoming.try_recv() {
+                Ok(PeerEvent::Message(ClientMessage::Ping { sent })) => {
+                    self.send(ServerMessage::Pong { sent })
+                }
+                Ok(PeerEvent::Message(message)) => messages.push(message),
+                Ok(PeerEvent::Closed(reason)) => return Err(reason),
+                Err(TryRecvError::Empty) => return Ok(messages),
+                Err(TryRecvError::Disconnected) => return Err("connection closed".to_string()),
+            }
+        }
+    }
+}
+
+fn main() {
+    let mut addr = "127.0.0.1:7878".to_string();
+    let mut mode = MatchMode::Server;
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--addr" => match args.next() {
+                Some(value) => addr = value,
+                None => fail("missing value for --addr"),
+            },
+            "--rollback" => mode = MatchMode::Rollback,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            other => fail(&format!("unknown option {other:?}")),
+        }
+    }
+
+    let listener = TcpListener::bind(&addr).unwrap_or_else(|err| {
+        eprintln!("snake-server: could not listen on {addr}: {err}");
+        process::exit(1);
+    });
+    listener
+        .set_nonblocking(true)
+        .expect("listener can be made non-blocking");
+    println!("listening on ws://{addr} ({mode:?} matches)");
+
+    let (connected_tx, connected_rx) = mpsc::channel();
+    //  NOTE: connected but no `Hello` yet
+    let mut greeting: Vec<Peer> = Vec::new();
+    let mut lobby: HashMap<MatchRules, Peer> = HashMap::new();
+    let mut streams: HashMap<String, Stream> = HashMap::new();
+    let mut matches: u64 = 0;
+
+    loop {
+        loop {
+            match listener.accept() {
+                Ok((stream, address)) => {
+                    let connected_tx = connected_tx.clone();
+                    thread::spawn(move || connect(stream, address.to_string(), connected_tx));
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    eprintln!("snake-server: accept failed: {err}");
+                    break;
+                }
+            }
+        }
+        greeting.extend(connected_rx.try_iter());
+
+        //  NOTE: drop whoever left the lobby first so nobody gets paired with a closed socket
+        lobby.retain(|_, peer| match peer.poll() {
+            Ok(_) => true,
+            Err(reason) => {
+                println!("{} left the lobby: {reason}", peer.name);
+                false
+            }
+        });
+
+        let mut still_greeting = Vec::new();
+        for peer in greeting.drain(..) {
+            let messages = match peer.poll() {
+                Ok(messages) => messages,
+                Err(reason) => {
+                    println!("{} left before saying hello: {reason}", peer.name);
+                    continue;
+                }
+            };
+            let mut messages = messages.into_iter();
+            let hello = messages.by_ref().find_map(|message| match message {
+                ClientMessage::Hello(rules) => Some(Greeting::Play(rules)),
+                ClientMessage::Broadcast { name } => Some(Greeting::Broadcast(name)),
+                ClientMessage::Watch { name } => Some(Greeting::Watch(name)),
+                _ => None,
+            });
+            match hello {
+                Some(Greeting::Play(rules)) if valid(&rules) => {
+                    join(&mut lobby, peer, rules, mode, &mut matches)
+                }
+                Some(Greeting::Play(rules)) => {
+                    println!("{} asked for an unsupported board: {rules:?}", peer.name)
+                }
+                Some(Greeting::Broadcast(name)) => {
+                    let stream = streams.entry(name.clone()).or_default();
+                    if stream.broadcaster.is_some() {
+                        println!("{} tried to take over stream {name:?}", peer.name);
+                        continue;
+                    }
+                    println!("{} is streaming {name:?}", peer.name);
+                    //  NOTE: the first snapshot usually comes in right behind the greeting
+                    stream.publish(messages);
+                    stream.broadcaster = Some(peer);
+                }
+                Some(Greeting::Watch(name)) => {
+                    println!("{} is watching {name:?}", peer.name);
+                    streams.entry(name).or_default().join(peer);
+                }
+                None => still_greeting.push(peer),
+            }
+        }
+        greeting = still_greeting;
+
+        streams.retain(|name, stream| stream.relay(name));
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn join(
+    lobby: &mut HashMap<MatchRules, Peer>,
+    peer: Peer,
+    rules: MatchRules,
+    mode: MatchMode,
+    matches: &mut u64,
+) {
+    match lobby.remove(&rules) {
+        Some(waiting) => {
+            *matches += 1;
+            let seed = *matches;
+            println!("match {seed}: {} vs {}", waiting.name, peer.name);
+            let players = [waiting, peer];
+            match mode {
+                MatchMode::Server => thread::spawn(move || run_match(players, rules, seed)),
+                MatchMode::Rollback => thread::spawn(move || relay_match(players, rules, seed)),
+            };
+        }
+        None => {
+            println!("{} is waiting for an opponent", peer.name);
+            peer.send(ServerMessage::Waiting);
+            lobby.insert(rules, peer);
+        }
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("snake-server: {message}\n\n{USAGE}");
+    process::exit(2);
+}
+
+fn connect(stream: TcpStream, name: String, connected: Sender<Peer>) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_nodelay(true);
+    let socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(err) => {
+            println!("{name}: handshake failed: {err}");
+            return;
+        }
+    };
+    let _ = socket.get_ref().set_read_timeout(Some(POLL));
+
+    let (outgoing_tx, outgoing_rx) = mpsc::channel();
+    let (incoming_tx, incoming_rx) = mpsc::channel();
+    println!("{name} connected");
+    let peer = Peer {
+        name,
+        outgoing: outgoing_tx,
+        incoming: incoming_rx,
+    };
+    if connected.send(peer).is_ok() {
+        pump(socket, outgoing_rx, incoming_tx);
+    }
+}
+
+fn run_match(players: [Peer; 2], rules: MatchRules, seed: u64) {
+    let grid = Grid {
+        size: rules.board_size,
+        walls: rules.walls,
+    };
+    let mut game = VersusGame::new(grid, players.len(), seed);
+    for (player, peer) in players.iter().enumerate() {
+        peer.send(ServerMessage::Start {
+            player,
+            rules,
+            seed,
+            mode: MatchMode::Server,
+        });
+        peer.send(ServerMessage::Tick(game.state().clone()));
+    }
+
+    let step = rules.speed.step();
+    let mut next = Instant::now() + START_DELAY;
+    let mut pending: [ScheduledTurns; 2] = Default::default();
+
+    loop {
+        while Instant::now() < next {
+            for (player, peer) in players.iter().enumerate() {
+                match peer.poll() {
+                    Ok(messages) => {
+                        for message in messages {
+                            if let ClientMessage::Input { tick, direction } = message {
+                                pending[player].push(game.state().tick, tick, direction);
+                            }
+                        }
+                    }
+                    Err(reason) => {
+                        println!("{} left the match: {reason}", peer.name);
+                        let other = 1 - player;
+                        players[other].send(ServerMessage::GameOver {
+                            winner: Some(other),
+                            reason: EndReason::OpponentLeft,
+                        });
+                        return;
+                    }
+                }
+            }
+            thread::sleep(POLL.min(next.saturating_duration_since(Instant::now())));
+        }
+        next += step;
+
+        let tick = game.state().tick + 1;
+        let directions: Vec<Option<Direction>> =
+            pending.iter_mut().map(|turns| turns.pop(tick)).collect();
+
+        let outcome = game.step(&directions);
+        for peer in &players {
+            peer.send(ServerMessage::Tick(game.state().clone()));
+        }
+        if let VersusOutcome::Over { winner } = outcome {
+            println!(
+                "{} vs {}: {}",
+                players[0].name,
+                players[1].name,
+                winner.map_or("draw".to_string(), |winner| format!(
+                    "{} won",
+                    players[winner].name
+                ))
+            );
+            for peer in &players {
+                peer.send(ServerMessage::GameOver {
+                    winner,
+                    reason: EndReason::Finished,
+                });
+            }
+            return;
+        }
+    }
+}
+
+//  NOTE: the peers simulate the match, the server forwards their messages until one of them
+//  leaves, which they do once the outcome is settled on their side
+fn relay_match(players: [Peer; 2], rules: MatchRules, seed: u64) {
+    for (player, peer) in players.iter().enumerate() {
+        peer.send(ServerMessage::Start {
+            player,
+            rules,
+            seed,
+            mode: MatchMode::Rollback,
+        });
+    }
+
+    loop {
+        for (player, peer) in players.iter().enumerate() {
+            let other = 1 - player;
+            match peer.poll() {
+                Ok(messages) => {
+                    for message in messages {
+                        if let ClientMessage::Relay(message) = message {
+                            players[other].send(ServerMessage::Relay(message));
+                        }
+                    }
+                }
+                Err(reason) => {
+                    println!("{} left the match: {reason}", peer.name);
+                    players[other].send(ServerMessage::GameOver {
+                        winner: Some(other),
+                        reason: EndReason::OpponentLeft,
+                    });
+                    return;
+                }
+            }
+        }
+        thread::sleep(POLL);
+    }
+}
+
+//  NOTE: board sizes the game can't draw are turned away before they reach the lobby
+fn valid(rules: &MatchRules) -> bool {
+    (MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&rules.board_size)
+}
@@ -0,0 +1,340 @@
+//  NOTE: plays batches of headless games with one of the bots and prints statistics, no window or
+//  GPU needed so it runs on CI boxes.
+//
+//  cargo run --release --bin snake-sim -- --bot hamiltonian --games 100 --board 20 --format csv
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::File,
+    io::{self, Write},
+    process,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use snake::{
+    bot::Bot,
+    level::Level,
+    neuro::{Network, GENOME_FILE},
+    settings::{
+        AiPlayer, GameMode, Speed, WallsMode, DEFAULT_BOARD_SIZE, MAX_BOARD_SIZE, MIN_BOARD_SIZE,
+    },
+    sim::{DeathCause, Game, Outcome, Rules},
+};
+
+const USAGE: &str = "usage: snake-sim [options]
+  --bot <pathfinding|hamiltonian|neural>
+                                   bot playing the games (default pathfinding)
+  --genome <path>                  genome for the neural bot (default genome.ron)
+  --games <n>                      number of games (default 100)
+  --seed <n>                       seed of the first game, the others count up from it (default 0)
+  --board <size>                   board size (default 20)
+  --walls <solid|wrap>             walls mode (default solid)
+  --level <name>                   play a saved level instead of the open board, it sets the size
+  --mode <classic|time-attack|survival|zen>
+                                   game mode (default classic)
+  --speed <slow|normal|fast>       speed the mode clocks and spikes count moves at (default normal)
+  --starvation <moves>             moves without eating before a game is called off
+  --format <json|csv>              output format (default json)
+  --out <path>                     write to a file instead of stdout";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Serialize)]
+struct Config {
+    bot: &'static str,
+    games: u64,
+    seed: u64,
+    board: i32,
+    walls: &'static str,
+    level: Option<String>,
+    mode: &'static str,
+    speed: &'static str,
+    starvation: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct GameRecord {
+    seed: u64,
+    score: u32,
+    moves: u32,
+    outcome: &'static str,
+    millis: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    games: usize,
+    wins: usize,
+    mean_score: f64,
+    median_score: u32,
+    min_score: u32,
+    max_score: u32,
+    mean_moves: f64,
+    //  NOTE: how many games ended on each score
+    scores: BTreeMap<u32, usize>,
+    //  NOTE: how many games ended each way, "won" included
+    outcomes: BTreeMap<&'static str, usize>,
+    total_millis: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    config: Config,
+    summary: Summary,
+    games: Vec<GameRecord>,
+}
+
+fn outcome_label(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Won => "won",
+        Outcome::Died(DeathCause::Wall) => "wall",
+        Outcome::TimeUp => "time",
+        Outcome::Died(DeathCause::Snake) => "snake",
+        Outcome::Died(DeathCause::Enemy) => "enemy",
+        Outcome::Died(DeathCause::Starvation) => "starvation",
+        Outcome::Moved | Outcome::Ate => "running",
+    }
+}
+
+fn mode_label(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Classic => "classic",
+        GameMode::TimeAttack => "time-attack",
+        GameMode::Survival => "survival",
+        GameMode::Zen => "zen",
+    }
+}
+
+fn speed_label(speed: Speed) -> &'static str {
+    match speed {
+        Speed::Slow => "slow",
+        Speed::Normal => "normal",
+        Speed::Fast => "fast",
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("snake-sim: {message}\n\n{USAGE}");
+    process::exit(2);
+}
+
+fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    let Some(value) = value else {
+        fail(&format!("missing value for {flag}"));
+    };
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("invalid value for {flag}: {value:?}")))
+}
+
+fn main() {
+    let mut player = AiPlayer::Pathfinding;
+    let mut games: u64 = 100;
+    let mut seed: u64 = 0;
+    let mut board = DEFAULT_BOARD_SIZE;
+    let mut walls = WallsMode::Solid;
+    let mut level: Option<String> = None;
+    let mut mode = GameMode::Classic;
+    let mut speed = Speed::Normal;
+    let mut starvation: Option<u32> = None;
+    let mut format = Format::Json;
+    let mut out: Option<String> = None;
+    let mut genome = GENOME_FILE.to_string();
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--bot" => {
+                player = match value::<String>(&flag, args.next()).as_str() {
+                    "pathfinding" => AiPlayer::Pathfinding,
+                    "hamiltonian" => AiPlayer::Hamiltonian,
+                    "neural" => AiPlayer::Neural,
+                    other => fail(&format!("unknown bot {other:?}")),
+                }
+            }
+            "--games" => games = value(&flag, args.next()),
+            "--seed" => seed = value(&flag, args.next()),
+            "--board" => board = value(&flag, args.next()),
+            "--walls" => {
+                walls = match value::<String>(&flag, args.next()).as_str() {
+                    "solid" => WallsMode::Solid,
+                    "wrap" => WallsMode::Wrap,
+                    other => fail(&format!("unknown walls mode {other:?}")),
+                }
+            }
+            "--level" => level = Some(value(&flag, args.next())),
+            "--mode" => {
+                let picked = value::<String>(&flag, args.next());
+                mode = GameMode::ALL
+                    .into_iter()
+                    .find(|&mode| mode_label(mode) == picked)
+                    .unwrap_or_else(|| fail(&format!("unknown mode {picked:?}")));
+            }
+            "--speed" => {
+                let picked = value::<String>(&flag, args.next());
+                speed = Speed::ALL
+                    .into_iter()
+                    .find(|&speed| speed_label(speed) == picked)
+                    .unwrap_or_else(|| fail(&format!("unknown speed {picked:?}")));
+            }
+            "--starvation" => starvation = Some(value(&flag, args.next())),
+            "--format" => {
+                format = match value::<String>(&flag, args.next()).as_str() {
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    other => fail(&format!("unknown format {other:?}")),
+                }
+            }
+            "--out" => out = Some(value(&flag, args.next())),
+            "--genome" => genome = value(&flag, args.next()),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            other => fail(&format!("unknown option {other:?}")),
+        }
+    }
+
+    //  NOTE: the spawn positions in `util.rs` need at least this much room
+    if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&board) {
+        fail(&format!(
+            "board size must be between {MIN_BOARD_SIZE} and {MAX_BOARD_SIZE}"
+        ));
+    }
+
+    let mut rules = Rules::new(board, walls).with_mode(mode).with_speed(speed);
+    if let Some(name) = &level {
+        let level = Level::load(name)
+            .and_then(|level| level.validate(rules.grid.walls).map(|_| level))
+            .unwrap_or_else(|err| {
+                eprintln!("snake-sim: level {name}: {err}");
+                process::exit(1);
+            });
+        rules = rules.with_level(level);
+    }
+    if let Some(starvation) = starvation {
+        rules.starvation = starvation;
+    }
+
+    let bot = match player {
+        //  NOTE: a missing genome is an error here rather than quietly path finding
+        AiPlayer::Neural => match Network::load(&genome) {
+            Ok(network) => Bot::Neural(Some(network)),
+            Err(err) => {
+                eprintln!("snake-sim: {genome}: {err}");
+                process::exit(1);
+            }
+        },
+        _ => Bot::new(player, rules.grid.size),
+    };
+    let records: Vec<GameRecord> = (seed..seed + games)
+        .map(|seed| play(&bot, &rules, seed))
+        .collect();
+
+    let report = Report {
+        config: Config {
+            bot: player.label(),
+            games,
+            seed,
+            board: rules.grid.size,
+            walls: match rules.grid.walls {
+                WallsMode::Solid => "solid",
+                WallsMode::Wrap => "wrap",
+            },
+            level,
+            mode: mode_label(mode),
+            speed: speed_label(speed),
+            starvation: rules.starvation,
+        },
+        summary: summarize(&records),
+        games: records,
+    };
+
+    let result = match out {
+        Some(path) => File::create(&path).and_then(|file| write_report(file, &report, format)),
+        None => write_report(io::stdout().lock(), &report, format),
+    };
+    if let Err(err) = result {
+        eprintln!("snake-sim: could not write the report: {err}");
+        process::exit(1);
+    }
+}
+
+fn play(bot: &Bot, rules: &Rules, seed: u64) -> GameRecord {
+    let started = Instant::now();
+    let mut game = Game::new(rules.clone(), seed);
+    let outcome = loop {
+        let outcome = game.step(bot.next_direction(&game));
+        if outcome.is_over() {
+            break outcome;
+        }
+    };
+
+    GameRecord {
+        seed,
+        score: game.score(),
+        moves: game.moves(),
+        outcome: outcome_label(outcome),
+        millis: millis(started.elapsed()),
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn summarize(records: &[GameRecord]) -> Summary {
+    let mut scores: Vec<u32> = records.iter().map(|record| record.score).collect();
+    scores.sort_unstable();
+
+    let mut distribution = BTreeMap::new();
+    let mut outcomes = BTreeMap::new();
+    for record in records {
+        *distribution.entry(record.score).or_insert(0) += 1;
+        *outcomes.entry(record.outcome).or_insert(0) += 1;
+    }
+
+    let games = records.len().max(1) as f64;
+    Summary {
+        games: records.len(),
+        wins: outcomes.get("won").copied().unwrap_or(0),
+        mean_score: scores.iter().map(|&score| score as f64).sum::<f64>() / games,
+        median_score: scores.get(scores.len() / 2).copied().unwrap_or(0),
+        min_score: scores.first().copied().unwrap_or(0),
+        max_score: scores.last().copied().unwrap_or(0),
+        mean_moves: records
+            .iter()
+            .map(|record| record.moves as f64)
+            .sum::<f64>()
+            / games,
+        scores: distribution,
+        outcomes,
+        total_millis: records.iter().map(|record| record.millis).sum(),
+    }
+}
+
+fn write_report(mut writer: impl Write, report: &Report, format: Format) -> io::Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, report)?;
+            writeln!(writer)
+        }
+        //  NOTE: one row per game, the summary is easy to get back from the rows
+        Format::Csv => {
+            writeln!(writer, "seed,score,moves,outcome,millis")?;
+            for game in &report.games {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{:.3}",
+                    game.seed, game.score, game.moves, game.outcome, game.millis
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
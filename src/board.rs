@@ -1,8 +1,17 @@
 use bevy::prelude::*;
 
-use crate::colors::COLORS;
+use crate::{
+    level::CurrentLevel,
+    royale::ROYALE_BOARD_SIZE,
+    settings::{Settings, WallsMode, DEFAULT_BOARD_SIZE, MAX_BOARD_SIZE, MIN_BOARD_SIZE},
+    sim::Grid,
+    snake::Position,
+    state::GameState,
+    theme::CurrentTheme,
+};
 
 pub const TILE_SIZE: f32 = 30.0;
+const GRID_LINE_WIDTH: f32 = 1.0;
 
 #[derive(Resource, Debug)]
 pub struct Board {
@@ -12,7 +21,7 @@ pub struct Board {
 }
 
 impl Board {
-    fn new(size: i32) -> Self {
+    pub fn new(size: i32) -> Self {
         let physical_size: f32 = size as f32 * TILE_SIZE;
         Self {
             size,
@@ -25,48 +34,276 @@ impl Board {
         let offset = -&self.physical_size / 2.0 + 0.5 * TILE_SIZE;
         offset + pos as f32 * TILE_SIZE
     }
+
+    //  NOTE: `position_translate` the other way around, `None` off the board
+    pub fn cell_at(&self, point: Vec2) -> Option<Position> {
+        let offset = self.position_translate(0);
+        let cell = ((point - offset) / TILE_SIZE).round();
+        let pos = Position::new(cell.x as i32, cell.y as i32);
+        let inside = (0..self.size).contains(&pos.x) && (0..self.size).contains(&pos.y);
+        inside.then_some(pos)
+    }
+
+    pub fn grid(&self, walls: WallsMode) -> Grid {
+        Grid {
+            size: self.size,
+            walls,
+        }
+    }
+}
+
+//  NOTE: swap the board for one of another size mid-game, spectated streams and battle royales
+//  bring their own
+#[derive(Event, Debug)]
+pub struct LoadBoard {
+    pub size: i32,
+}
+
+#[derive(Component, Debug)]
+struct BoardBackground;
+
+#[derive(Component, Debug)]
+struct GridLine;
+
+//  NOTE: checkerboard tile, `dark` picks the alternate tile colour
+#[derive(Component, Debug)]
+struct BoardTile {
+    dark: bool,
 }
 
 pub struct BoardPlugin;
 
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Board::new(20))
-            .add_systems(Startup, load_board);
+        app.insert_resource(Board::new(DEFAULT_BOARD_SIZE))
+            .add_event::<LoadBoard>()
+            .add_systems(Startup, load_board)
+            //  NOTE: board size changes are picked up between games
+            .add_systems(OnEnter(GameState::GameOver), load_board)
+            .add_systems(OnEnter(GameState::Resetting), load_board)
+            .add_systems(Update, load_requested_board)
+            .add_systems(
+                Update,
+                update_grid_lines.run_if(resource_changed::<Settings>),
+            )
+            .add_systems(
+                Update,
+                apply_board_theme.run_if(resource_changed::<CurrentTheme>),
+            );
     }
 }
 
-fn load_board(mut commands: Commands, board: Res<Board>) {
+fn load_board(
+    mut commands: Commands,
+    mut board: ResMut<Board>,
+    settings: Res<Settings>,
+    theme: Res<CurrentTheme>,
+    level: Res<CurrentLevel>,
+    background_query: Query<Entity, With<BoardBackground>>,
+) {
+    //  NOTE: levels bring their own size
+    let size = level
+        .0
+        .as_ref()
+        .map_or(settings.gameplay.board_size, |level| level.size);
+    build_board(
+        &mut commands,
+        &mut board,
+        size,
+        &settings,
+        &theme,
+        &background_query,
+    );
+}
+
+fn load_requested_board(
+    mut commands: Commands,
+    mut events: EventReader<LoadBoard>,
+    mut board: ResMut<Board>,
+    settings: Res<Settings>,
+    theme: Res<CurrentTheme>,
+    background_query: Query<Entity, With<BoardBackground>>,
+) {
+    if let Some(&LoadBoard { size }) = events.read().last() {
+        build_board(
+            &mut commands,
+            &mut board,
+            size,
+            &settings,
+            &theme,
+            &background_query,
+        );
+    }
+}
+
+fn build_board(
+    commands: &mut Commands,
+    board: &mut Board,
+    size: i32,
+    settings: &Settings,
+    theme: &CurrentTheme,
+    background_query: &Query<Entity, With<BoardBackground>>,
+) {
+    //  NOTE: battle royales bring a board larger than the settings allow
+    let size = size.clamp(MIN_BOARD_SIZE, MAX_BOARD_SIZE.max(ROYALE_BOARD_SIZE));
+    if size == board.size && !background_query.is_empty() {
+        return;
+    }
+
+    for entity in background_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *board = Board::new(size);
+
+    let grid_visibility = if settings.video.grid_lines {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
     commands
-        .spawn(SpriteBundle {
-            sprite: Sprite {
-                color: COLORS.board,
-                custom_size: Some(Vec2::new(board.physical_size, board.physical_size)),
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: theme.board,
+                    custom_size: Some(Vec2::new(board.physical_size, board.physical_size)),
+                    ..default()
+                },
                 ..default()
             },
-            ..default()
-        })
+            BoardBackground,
+        ))
         .with_children(|builder| {
             for x in 0..board.size {
                 for y in 0..board.size {
-                    builder.spawn(SpriteBundle {
-                        sprite: Sprite {
-                            color: if (x + y) % 2 == 0 {
-                                COLORS.tile_placeholder
-                            } else {
-                                COLORS.tile_placeholder_dark
+                    let dark = (x + y) % 2 != 0;
+                    builder.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: if dark {
+                                    theme.tile_dark
+                                } else {
+                                    theme.tile_light
+                                },
+                                custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                                ..default()
                             },
-                            custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                            transform: Transform::from_xyz(
+                                board.position_translate(x),
+                                board.position_translate(y),
+                                0.0,
+                            ),
                             ..default()
                         },
-                        transform: Transform::from_xyz(
-                            board.position_translate(x),
-                            board.position_translate(y),
-                            0.0,
-                        ),
-                        ..default()
-                    });
+                        BoardTile { dark },
+                    ));
+                }
+            }
+
+            //  NOTE: grid lines sit between the tiles, one vertical and one horizontal per cell edge
+            let half = board.physical_size / 2.0;
+            for i in 1..board.size {
+                let offset = -half + i as f32 * TILE_SIZE;
+                for (position, size) in [
+                    (
+                        Vec3::new(offset, 0.0, 0.5),
+                        Vec2::new(GRID_LINE_WIDTH, board.physical_size),
+                    ),
+                    (
+                        Vec3::new(0.0, offset, 0.5),
+                        Vec2::new(board.physical_size, GRID_LINE_WIDTH),
+                    ),
+                ] {
+                    builder.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: theme.grid_line,
+                                custom_size: Some(size),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(position),
+                            visibility: grid_visibility,
+                            ..default()
+                        },
+                        GridLine,
+                    ));
                 }
             }
         });
 }
+
+fn update_grid_lines(settings: Res<Settings>, mut query: Query<&mut Visibility, With<GridLine>>) {
+    let visibility = if settings.video.grid_lines {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    for mut grid_visibility in query.iter_mut() {
+        if *grid_visibility != visibility {
+            *grid_visibility = visibility;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_board_theme(
+    theme: Res<CurrentTheme>,
+    mut background_query: Query<&mut Sprite, With<BoardBackground>>,
+    mut tile_query: Query<(&mut Sprite, &BoardTile), Without<BoardBackground>>,
+    mut grid_query: Query<
+        &mut Sprite,
+        (With<GridLine>, Without<BoardBackground>, Without<BoardTile>),
+    >,
+) {
+    for mut sprite in background_query.iter_mut() {
+        sprite.color = theme.board;
+    }
+    for (mut sprite, tile) in tile_query.iter_mut() {
+        sprite.color = if tile.dark {
+            theme.tile_dark
+        } else {
+            theme.tile_light
+        };
+    }
+    for mut sprite in grid_query.iter_mut() {
+        sprite.color = theme.grid_line;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_translate_back_from_anywhere_on_their_tile() {
+        let board = Board::new(DEFAULT_BOARD_SIZE);
+        for x in 0..board.size {
+            for y in 0..board.size {
+                let centre = Vec2::new(board.position_translate(x), board.position_translate(y));
+                for nudge in [Vec2::ZERO, Vec2::splat(0.45), Vec2::new(-0.45, 0.3)] {
+                    let point = centre + nudge * TILE_SIZE;
+                    assert_eq!(board.cell_at(point), Some(Position::new(x, y)), "{point}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn points_off_the_board_have_no_cell() {
+        let board = Board::new(MIN_BOARD_SIZE);
+        let (inside, before, after) = (
+            board.position_translate(0),
+            board.position_translate(-1),
+            board.position_translate(board.size),
+        );
+        for point in [
+            Vec2::new(before, inside),
+            Vec2::new(inside, before),
+            Vec2::new(after, inside),
+            Vec2::new(inside, after),
+        ] {
+            assert_eq!(board.cell_at(point), None, "{point}");
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{
+    autopilot::plan_direction,
+    hamiltonian::HamiltonianSolver,
+    neuro::{Network, GENOME_FILE},
+    settings::AiPlayer,
+    sim::Game,
+    snake::{Direction, Position},
+};
+
+//  NOTE: an `AiPlayer` ready to play headless games, see `sim.rs`
+#[derive(Debug, Clone)]
+pub enum Bot {
+    Pathfinding,
+    Hamiltonian(HamiltonianSolver),
+    //  NOTE: `None` when no genome could be loaded, it path finds instead
+    Neural(Option<Network>),
+}
+
+impl Bot {
+    pub fn new(player: AiPlayer, board_size: i32) -> Self {
+        match player {
+            AiPlayer::Pathfinding => Self::Pathfinding,
+            AiPlayer::Hamiltonian => Self::Hamiltonian(HamiltonianSolver::new(board_size)),
+            AiPlayer::Neural => Self::Neural(Network::load(GENOME_FILE).ok()),
+        }
+    }
+
+    pub fn next_direction(&self, game: &Game) -> Direction {
+        let snake: Vec<Position> = game.snake().iter().copied().collect();
+        let food: Vec<Position> = game.food().into_iter().collect();
+        let obstacles = game.obstacles();
+
+        let planned = match self {
+            Self::Pathfinding | Self::Neural(None) => None,
+            //  NOTE: the cycle only knows open boards, levels path find
+            Self::Hamiltonian(_) if !obstacles.is_empty() => None,
+            Self::Hamiltonian(solver) => solver.plan_direction(game.grid(), &snake, &food),
+            Self::Neural(Some(network)) => Some(network.plan_direction(
+                game.grid(),
+                &snake,
+                &food,
+                &obstacles,
+                game.direction(),
+            )),
+        };
+        //  NOTE: same fallback as `autopilot_controls`, and keep going straight when boxed in
+        planned
+            .or_else(|| plan_direction(game.grid(), &snake, &obstacles, &food))
+            .unwrap_or(game.direction())
+    }
+}
@@ -1,15 +1,82 @@
-use bevy::{prelude::*, window::WindowResized};
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use bevy_egui::EguiContexts;
+
+use crate::{
+    board::{Board, TILE_SIZE},
+    settings::{CameraMode, Settings, DEFAULT_BOARD_SIZE, MAX_BOARD_SIZE},
+    snake::{Direction, SnakeHead, SnakeHeadDirection},
+};
+
+//  NOTE: where the camera sits when it shows the whole board, the top bar covers the top
+const CAMERA_OFFSET: Vec2 = Vec2::new(0.0, 20.0);
+//  NOTE: height of the top bar in `ui.rs`, the board is kept out from under it while following
+const TOP_BAR_HEIGHT: f32 = 60.0;
+//  NOTE: tiles across the screen while following at zoom 1, a regular board's worth
+const FOLLOW_VIEW: f32 = 30.0;
+//  NOTE: `CameraMode::Auto` follows boards larger than the settings allow, like battle royales
+const AUTO_FOLLOW_SIZE: i32 = MAX_BOARD_SIZE;
+//  NOTE: tiles the target can move away from the center before the camera moves along
+const DEADZONE: f32 = 2.0;
+//  NOTE: tiles shown ahead of the snake in the direction it's heading
+const LOOK_AHEAD: f32 = 4.0;
+//  NOTE: how quickly the camera catches up with its target and zoom, higher is snappier
+const FOLLOW_SPEED: f32 = 6.0;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+//  NOTE: zoom change per scroll wheel notch, touchpads scroll in pixels
+const SCROLL_ZOOM: f32 = 0.1;
+const PIXELS_PER_LINE: f32 = 100.0;
+
+//  NOTE: what the camera follows when the board doesn't fit on the screen, in world coordinates.
+//  The snake head fills it in, modes without one like battle royales set it themselves
+#[derive(Resource, Debug, Default)]
+pub struct CameraFocus {
+    pub target: Option<Vec2>,
+    //  NOTE: where the target is going, the camera looks ahead that way
+    pub heading: Option<Direction>,
+}
+
+//  NOTE: nudges the camera away from where it would be, for screen shake. Cleared by whoever
+//  sets it, see `juice.rs`
+#[derive(Resource, Debug, Default)]
+pub struct CameraShake(pub Vec2);
+
+//  NOTE: scroll and pinch zoom while following, 1 shows `FOLLOW_VIEW` tiles
+#[derive(Resource, Debug)]
+pub struct CameraZoom(pub f32);
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_camera)
-            .add_systems(Update, resize_camera);
+        app.init_resource::<CameraFocus>()
+            .init_resource::<CameraZoom>()
+            .init_resource::<CameraShake>()
+            .add_systems(Startup, spawn_camera)
+            .add_systems(
+                Update,
+                (
+                    track_snake_head,
+                    camera_controls,
+                    resize_camera,
+                    follow_focus,
+                )
+                    .chain(),
+            );
     }
 }
 
-enum Breakpoints {
+pub enum Breakpoints {
     XS = 380,
     SM = 455,
     MD = 660,
@@ -17,36 +84,233 @@ enum Breakpoints {
 
 fn spawn_camera(mut commands: Commands) {
     commands.spawn(Camera2dBundle {
-        transform: Transform::from_xyz(0.0, 20., 0.0),
+        transform: Transform::from_xyz(CAMERA_OFFSET.x, CAMERA_OFFSET.y, 0.0),
         ..Default::default()
     });
 }
 
+fn following(settings: &Settings, board: &Board, focus: &CameraFocus) -> bool {
+    focus.target.is_some()
+        && match settings.video.camera {
+            CameraMode::Auto => board.size > AUTO_FOLLOW_SIZE,
+            CameraMode::Fit => false,
+            CameraMode::Follow => true,
+        }
+}
+
+fn track_snake_head(
+    mut focus: ResMut<CameraFocus>,
+    query: Query<(&Transform, &SnakeHeadDirection), With<SnakeHead>>,
+) {
+    //  NOTE: the transform glides between cells, so the camera does too
+    if let Ok((transform, direction)) = query.get_single() {
+        focus.target = Some(transform.translation.truncate());
+        focus.heading = Some(direction.current());
+    }
+}
+
+//  NOTE: F flips between following and showing the whole board, the wheel and two fingers zoom
+#[allow(clippy::too_many_arguments)]
+fn camera_controls(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<Settings>,
+    mut zoom: ResMut<CameraZoom>,
+    mut wheel_events: EventReader<MouseWheel>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    touches: Res<Touches>,
+    board: Res<Board>,
+    focus: Res<CameraFocus>,
+) {
+    let scrolled: f32 = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+
+    //  NOTE: typing in a text field or scrolling a menu shouldn't move the board behind it
+    let ctx = contexts.ctx_mut();
+    if keyboard_input.just_pressed(KeyCode::KeyF) && !ctx.wants_keyboard_input() {
+        settings.video.camera = if following(&settings, &board, &focus) {
+            CameraMode::Fit
+        } else {
+            CameraMode::Follow
+        };
+    }
+    if ctx.wants_pointer_input() {
+        return;
+    }
+    let mut factor = 1.0 + scrolled * SCROLL_ZOOM;
+
+    let fingers: Vec<_> = touches.iter().collect();
+    if let [first, second] = fingers[..] {
+        let before = first
+            .previous_position()
+            .distance(second.previous_position());
+        let after = first.position().distance(second.position());
+        if before > 0.0 {
+            factor *= after / before;
+        }
+    }
+
+    if factor != 1.0 && factor > 0.0 {
+        zoom.0 = (zoom.0 * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn resize_camera(
-    mut resize_events: EventReader<WindowResized>,
+    window: Query<&Window, With<PrimaryWindow>>,
     mut query: Query<&mut Transform, With<Camera>>,
+    board: Res<Board>,
+    focus: Res<CameraFocus>,
+    zoom: Res<CameraZoom>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut last_layout: Local<(Vec2, i32)>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+    if window_size == Vec2::ZERO {
+        return;
+    }
+
+    let baseline = if window_size.x < Breakpoints::XS as i32 as f32 {
+        200.0
+    } else if window_size.x < Breakpoints::SM as i32 as f32 {
+        225.0
+    } else if window_size.x < Breakpoints::MD as i32 as f32 {
+        450.0
+    } else {
+        720.0
+    };
+
+    //  NOTE: tiles across the shorter side of the window
+    let view = if following(&settings, &board, &focus) {
+        (FOLLOW_VIEW / zoom.0).min(board.size as f32)
+    } else {
+        board.size as f32
+    };
+    let scale = window_size.min_element() / baseline * view / DEFAULT_BOARD_SIZE as f32;
+
+    //  NOTE: snap when the window or the board changed, glide when zooming or switching modes
+    let layout = (window_size, board.size);
+    let snap = *last_layout != layout || settings.accessibility.reduced_motion;
+    *last_layout = layout;
+    let catch_up = 1.0 - (-FOLLOW_SPEED * time.delta_seconds()).exp();
+
+    for mut transform in query.iter_mut() {
+        let current = transform.scale.x;
+        let next = if snap || (current - scale).abs() < 0.001 {
+            scale
+        } else {
+            current + (scale - current) * catch_up
+        };
+        if next != current {
+            transform.scale = Vec3::new(next, next, 1.0);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn follow_focus(
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut query: Query<&mut Transform, With<Camera>>,
+    board: Res<Board>,
+    focus: Res<CameraFocus>,
+    shake: Res<CameraShake>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    //  NOTE: the point the deadzone is centered on
+    mut anchor: Local<Option<Vec2>>,
+    //  NOTE: the shake added last frame, taken back out before moving on
+    mut shaken: Local<Vec2>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+
+    let target = focus
+        .target
+        .filter(|_| following(&settings, &board, &focus));
+    let Some(target) = target else {
+        *anchor = None;
+        move_camera(
+            &mut query,
+            CAMERA_OFFSET,
+            &settings,
+            &time,
+            shake.0,
+            &mut shaken,
+        );
+        return;
+    };
+
+    //  NOTE: the camera only moves along once the target leaves the deadzone
+    let deadzone = DEADZONE * TILE_SIZE;
+    let center = anchor.get_or_insert(target);
+    let offset = target - *center;
+    *center += offset - offset.clamp(Vec2::splat(-deadzone), Vec2::splat(deadzone));
+
+    let ahead = match focus.heading {
+        Some(Direction::Up) => Vec2::Y,
+        Some(Direction::Down) => Vec2::NEG_Y,
+        Some(Direction::Left) => Vec2::NEG_X,
+        Some(Direction::Right) => Vec2::X,
+        None => Vec2::ZERO,
+    };
+    let mut goal = *center + ahead * LOOK_AHEAD * TILE_SIZE;
+
+    //  NOTE: keep the board edges at the screen edges, or the board centered if it fits
+    let scale = query
+        .iter()
+        .next()
+        .map_or(1.0, |transform| transform.scale.x);
+    let half_view = window_size * scale / 2.0;
+    let top_bar = TOP_BAR_HEIGHT * scale;
+    let half_board = board.physical_size / 2.0;
+    goal.x = if half_view.x >= half_board {
+        0.0
+    } else {
+        goal.x
+            .clamp(-half_board + half_view.x, half_board - half_view.x)
+    };
+    goal.y = if half_view.y * 2.0 - top_bar >= board.physical_size {
+        CAMERA_OFFSET.y
+    } else {
+        goal.y.clamp(
+            -half_board + half_view.y,
+            half_board - half_view.y + top_bar,
+        )
+    };
+
+    move_camera(&mut query, goal, &settings, &time, shake.0, &mut shaken);
+}
+
+fn move_camera(
+    query: &mut Query<&mut Transform, With<Camera>>,
+    goal: Vec2,
+    settings: &Settings,
+    time: &Time,
+    shake: Vec2,
+    shaken: &mut Vec2,
 ) {
-    for ev in resize_events.read() {
-        for mut transform in query.iter_mut() {
-            let baseline = if ev.width < Breakpoints::XS as i32 as f32 {
-                200.0
-            } else if ev.width < Breakpoints::SM as i32 as f32 {
-                225.0
-            } else if ev.width < Breakpoints::MD as i32 as f32 {
-                450.0
-            } else {
-                720.0
-            };
-
-            // println!(
-            //     "dimension: {:?} / baseline: {:?}",
-            //     ev.width.min(ev.height),
-            //     baseline
-            // );
-
-            let scale = ev.width.min(ev.height) / baseline;
-            // println!("scale {:?}", scale);
-            transform.scale = Vec3::new(scale, scale, 1.0);
+    //  NOTE: the same share of the distance every second, whatever the frame rate
+    let catch_up = 1.0 - (-FOLLOW_SPEED * time.delta_seconds()).exp();
+    for mut transform in query.iter_mut() {
+        let position = transform.translation.truncate() - *shaken;
+        let next = if settings.accessibility.reduced_motion || position.distance(goal) < 0.5 {
+            goal
+        } else {
+            position.lerp(goal, catch_up)
+        };
+        if next != position || shake != *shaken {
+            transform.translation = (next + shake).extend(transform.translation.z);
         }
     }
+    *shaken = shake;
 }
@@ -2,8 +2,12 @@ use bevy::prelude::*;
 
 use crate::{
     board::Board,
+    hazard::Enemy,
+    level::{CurrentLevel, Wall},
     schedule::InGameSet,
-    snake::{Position, SnakeHead, SnakeSegment},
+    settings::{GameMode, Settings},
+    sim::{crash, Crash},
+    snake::{Position, PreviousPosition, SnakeHead, SnakeSegment},
     state::GameState,
 };
 
@@ -11,60 +15,77 @@ pub struct CollisionDetectionPlugin;
 
 impl Plugin for CollisionDetectionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.add_event::<CollisionEvent>().add_systems(
             Update,
             collision_detection.in_set(InGameSet::CollisionDetection),
         );
     }
 }
-//  NOTE: Maybe I can have a handle_collision event handler that delegates different kind of
-//  events?
-//  - Maybe the CollisionEvent has two entities and the second one is an option? if it's not
-//  present, then that's a snake hit wall event?
-//  Collisions
-//  1. Snake and SnakeBody
-//  2. Snake and Food
-//  3. Snake and wall (not an entity)
+//  NOTE: sent when the snake dies, `collided_entity` is the segment, level wall or enemy it ran into or
+//  `None` for the edge of the board. The snake starts dying on the same frame, see `juice.rs` for
+//  what reacts to it
+#[derive(Event, Debug)]
+pub struct CollisionEvent {
+    pub entity: Entity,
+    pub collided_entity: Option<Entity>,
+}
 
-// #[derive(Event, Debug)]
-// struct CollisionEvent {
-//     entity: Entity,
-//     collided_entity: Option<Entity>,
-// }
-//
-// impl CollisionEvent {
-//     fn new(entity: Entity, collided_entity: Option<Entity>) -> Self {
-//         Self {
-//             entity,
-//             collided_entity,
-//         }
-//     }
-// }
+impl CollisionEvent {
+    fn new(entity: Entity, collided_entity: Option<Entity>) -> Self {
+        Self {
+            entity,
+            collided_entity,
+        }
+    }
+}
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn collision_detection(
-    snake_head_query: Query<(&Position, Entity), With<SnakeHead>>,
+    snake_head_query: Query<(&Position, &PreviousPosition, Entity), With<SnakeHead>>,
     snake_body_query: Query<(&Position, Entity), (With<SnakeSegment>, Without<SnakeHead>)>,
+    wall_query: Query<(&Position, Entity), With<Wall>>,
+    enemy_query: Query<(&Enemy, Entity)>,
     // food_query: Query<(&Transform, Entity), With<Food>>,
     board: Res<Board>,
+    settings: Res<Settings>,
+    level: Res<CurrentLevel>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut collision_event_writer: EventWriter<CollisionEvent>,
 ) {
-    let Ok((head_pos, _)) = snake_head_query.get_single() else {
+    let Ok((head_pos, PreviousPosition(head_prev), head_entity)) = snake_head_query.get_single()
+    else {
         return;
     };
 
-    //  NOTE:the snake hits a wall
-    if head_pos.x < 0 || head_pos.y < 0 || head_pos.x == board.size || head_pos.y == board.size {
-        next_state.set(GameState::GameOver);
-        // CollisionEvent::new(head_entity, None);
+    //  NOTE: nothing kills the snake in zen games, it wraps around the board and slides over
+    //  everything else
+    if settings.gameplay.mode == GameMode::Zen {
+        return;
     }
 
-    // NOTE: the snake hits itself
-    for (segment_pos, _) in snake_body_query.iter() {
-        if head_pos == segment_pos {
-            next_state.set(GameState::GameOver);
-            // CollisionEvent::new(head_entity, Some(segment_entity));
-        }
-    }
+    //  NOTE: the edge of the board, one of the level's walls, an enemy or the snake itself, see
+    //  `sim::crash`
+    let grid = board.grid(settings.gameplay.walls());
+    let crashed = crash(
+        &grid,
+        level.0.as_ref(),
+        *head_pos,
+        *head_prev,
+        snake_body_query.iter().map(|(pos, _)| pos),
+        enemy_query.iter().map(|(enemy, _)| enemy),
+    );
+    let collided_entity = match crashed {
+        None => return,
+        Some(Crash::Edge) => None,
+        Some(Crash::Wall) => wall_query
+            .iter()
+            .find(|&(wall_pos, _)| wall_pos == head_pos)
+            .map(|(_, entity)| entity),
+        Some(Crash::Enemy(i)) => enemy_query.iter().nth(i).map(|(_, entity)| entity),
+        Some(Crash::Body(i)) => snake_body_query.iter().nth(i).map(|(_, entity)| entity),
+    };
+    next_state.set(GameState::Dying);
+    collision_event_writer.send(CollisionEvent::new(head_entity, collided_entity));
 
     //  TODO: add collision with food
 }
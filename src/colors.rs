@@ -1,13 +1,26 @@
 use bevy::prelude::Color;
 
+//  NOTE: the classic palette, used until the theme files are loaded (see `theme.rs`)
 pub struct Colors {
+    pub background: Color,
     pub board: Color,
     pub tile_placeholder: Color,
     pub tile_placeholder_dark: Color,
+    pub grid_line: Color,
+    pub panel: Color,
+    pub menu: Color,
+    pub button: Color,
+    pub text: Color,
 }
 
 pub const COLORS: Colors = Colors {
+    background: Color::rgb(0.34, 0.54, 0.2),
     board: Color::rgb(0.42, 0.63, 0.07),
     tile_placeholder: Color::rgb(0.62, 0.83, 0.27),
     tile_placeholder_dark: Color::rgb(0.57, 0.78, 0.22),
+    grid_line: Color::rgba(0.0, 0.0, 0.0, 0.15),
+    panel: Color::rgb(0.29, 0.46, 0.17),
+    menu: Color::rgb(0.3, 0.76, 0.98),
+    button: Color::rgb(0.07, 0.33, 0.8),
+    text: Color::WHITE,
 };
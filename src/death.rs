@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{settings::Settings, snake::SnakeSegment, state::GameState};
+
+//  NOTE: times a second the segments that are left blink
+const BLINK_RATE: f32 = 8.0;
+
+//  NOTE: the segments to hide, tail first, and how far along the animation is
+#[derive(Resource, Debug, Default)]
+struct DeathAnimation {
+    segments: Vec<Entity>,
+    timer: Timer,
+}
+
+pub struct DeathPlugin;
+
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DeathAnimation>()
+            .add_systems(OnEnter(GameState::Dying), start_dying)
+            .add_systems(Update, animate_death.run_if(in_state(GameState::Dying)));
+    }
+}
+
+fn start_dying(
+    mut animation: ResMut<DeathAnimation>,
+    segment_query: Query<Entity, With<SnakeSegment>>,
+    settings: Res<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let duration = Duration::from_secs_f32(settings.video.death_animation.max(0.0));
+    if duration.is_zero() {
+        next_state.set(GameState::GameOver);
+        return;
+    }
+
+    //  NOTE: the query goes head to tail, like the direction queue
+    let mut segments: Vec<Entity> = segment_query.iter().collect();
+    segments.reverse();
+    *animation = DeathAnimation {
+        segments,
+        timer: Timer::new(duration, TimerMode::Once),
+    };
+}
+
+fn animate_death(
+    mut animation: ResMut<DeathAnimation>,
+    mut visibility_query: Query<&mut Visibility, With<SnakeSegment>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    animation.timer.tick(time.delta());
+    if animation.timer.finished() {
+        //  NOTE: `despawn_all_entities` cleans the hidden segments up
+        next_state.set(GameState::GameOver);
+        return;
+    }
+
+    let gone = (animation.timer.fraction() * animation.segments.len() as f32) as usize;
+    //  NOTE: reduced motion keeps what's left steady instead of blinking
+    let shown = settings.accessibility.reduced_motion
+        || (animation.timer.elapsed_secs() * BLINK_RATE).fract() < 0.5;
+    for (i, &segment) in animation.segments.iter().enumerate() {
+        let Ok(mut visibility) = visibility_query.get_mut(segment) else {
+            continue;
+        };
+        let next = if i >= gone && shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != next {
+            *visibility = next;
+        }
+    }
+}
@@ -6,7 +6,9 @@ pub struct DespawnPlugin;
 
 impl Plugin for DespawnPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::GameOver), despawn_all_entities);
+        app.add_systems(OnEnter(GameState::GameOver), despawn_all_entities)
+            .add_systems(OnEnter(GameState::Resetting), despawn_all_entities)
+            .add_systems(OnEnter(GameState::Editing), despawn_all_entities);
     }
 }
 
@@ -0,0 +1,125 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{
+    egui::{self, TopBottomPanel},
+    EguiContexts,
+};
+
+use crate::{
+    camera::Breakpoints,
+    schedule::SteerSet,
+    settings::Settings,
+    snake::{Direction, SteerEvent},
+    state::{GameState, MenuState},
+    theme::{color32, CurrentTheme, Theme},
+};
+
+pub struct DpadPlugin;
+
+impl Plugin for DpadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_dpad
+                .in_set(SteerSet)
+                .run_if(in_state(MenuState::Off).and_then(dpad_enabled)),
+        );
+    }
+}
+
+fn dpad_enabled(settings: Res<Settings>) -> bool {
+    settings.controls.dpad
+}
+
+//  NOTE: follow the same breakpoints as `resize_camera` so the pad grows with the board
+fn button_size(width: f32) -> f32 {
+    if width < Breakpoints::XS as i32 as f32 {
+        44.0
+    } else if width < Breakpoints::SM as i32 as f32 {
+        52.0
+    } else if width < Breakpoints::MD as i32 as f32 {
+        60.0
+    } else {
+        68.0
+    }
+}
+
+fn update_dpad(
+    mut contexts: EguiContexts,
+    mut steer_event_writer: EventWriter<SteerEvent>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    game_state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    theme: Res<CurrentTheme>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+
+    let size = button_size(window.width());
+    let mut pressed: Option<Direction> = None;
+
+    TopBottomPanel::bottom("dpad")
+        .show_separator_line(false)
+        .frame(egui::Frame {
+            fill: egui::Color32::TRANSPARENT,
+            inner_margin: egui::Margin::same(10.0),
+            ..Default::default()
+        })
+        .show(contexts.ctx_mut(), |ui| {
+            ui.spacing_mut().item_spacing = egui::vec2(4.0, 4.0);
+            let offset = (ui.available_width() - size * 3.0 - 8.0) / 2.0;
+
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ui.add_space(offset + size + 4.0);
+                    dpad_button(ui, &theme, "⬆", size, Direction::Up, &mut pressed);
+                });
+                ui.horizontal(|ui| {
+                    ui.add_space(offset);
+                    dpad_button(ui, &theme, "⬅", size, Direction::Left, &mut pressed);
+                    ui.add_space(size + 4.0);
+                    dpad_button(ui, &theme, "➡", size, Direction::Right, &mut pressed);
+                });
+                ui.horizontal(|ui| {
+                    ui.add_space(offset + size + 4.0);
+                    dpad_button(ui, &theme, "⬇", size, Direction::Down, &mut pressed);
+                });
+            });
+        });
+
+    let Some(direction) = pressed else {
+        return;
+    };
+
+    steer_event_writer.send(SteerEvent { direction, seat: 0 });
+
+    //  NOTE: like the arrow keys, pressing the pad on a fresh board starts the game
+    if game_state.get() == &GameState::NewGame {
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn dpad_button(
+    ui: &mut egui::Ui,
+    theme: &Theme,
+    label: &str,
+    size: f32,
+    direction: Direction,
+    pressed: &mut Option<Direction>,
+) {
+    let response = ui.add(
+        egui::Button::new(
+            egui::RichText::new(label)
+                .color(color32(theme.text))
+                .font(egui::FontId::proportional(size * 0.5)),
+        )
+        .min_size(egui::vec2(size, size))
+        .rounding(8.0)
+        .fill(color32(theme.panel.with_a(0.8))),
+    );
+
+    //  NOTE: react on press instead of release, waiting for a click feels laggy
+    if response.is_pointer_button_down_on() && ui.input(|i| i.pointer.any_pressed()) {
+        *pressed = Some(direction);
+    }
+}
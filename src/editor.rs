@@ -0,0 +1,421 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    asset_loader::SpritePart,
+    board::{Board, LoadBoard, TILE_SIZE},
+    hazard::hazard_sprite,
+    level::{portal_sprite, wall_sprite, CurrentLevel, Hazard, Level},
+    settings::{Settings, MAX_BOARD_SIZE, MIN_BOARD_SIZE},
+    snake::{segment_part, Direction, Position},
+    sprite_pack::CurrentSpritePack,
+    state::{GameState, MenuState},
+    theme::{color32, CurrentTheme},
+};
+
+//  NOTE: the level editor, a side panel and clicks on the board. Left click paints with the
+//  selected tool, right click clears the cell
+
+const DEFAULT_LEVEL_NAME: &str = "My level";
+const PANEL_WIDTH: f32 = 220.0;
+const HELP: &str = "Left click paints, right click clears. Food marks the only cells food \
+                    appears on. A portal takes two clicks, one for each end, and so does a \
+                    patrol, one for each end of its way.";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    #[default]
+    Wall,
+    Food,
+    Start,
+    Portal,
+    Patrol,
+    Spikes,
+    Hunter,
+    Erase,
+}
+
+impl Tool {
+    const ALL: [Tool; 8] = [
+        Self::Wall,
+        Self::Food,
+        Self::Start,
+        Self::Portal,
+        Self::Patrol,
+        Self::Spikes,
+        Self::Hunter,
+        Self::Erase,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Wall => "Wall",
+            Self::Food => "Food",
+            Self::Start => "Start",
+            Self::Portal => "Portal",
+            Self::Patrol => "Patrol",
+            Self::Spikes => "Spikes",
+            Self::Hunter => "Hunter",
+            Self::Erase => "Erase",
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct Editor {
+    level: Level,
+    tool: Tool,
+    //  NOTE: the first end of the portal or patrol being placed, the next click places the other
+    //  one
+    first_end: Option<Position>,
+    //  NOTE: names in the levels folder, read when the editor opens and after saving
+    saved: Vec<String>,
+    message: Option<String>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            level: Level::new(DEFAULT_LEVEL_NAME, MIN_BOARD_SIZE),
+            tool: Tool::default(),
+            first_end: None,
+            saved: Vec::new(),
+            message: None,
+        }
+    }
+}
+
+//  NOTE: the tiles drawn for the level being edited, they have no `Position` so the game leaves
+//  them alone
+#[derive(Component, Debug)]
+struct EditorSprite;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Editor>()
+            .add_systems(OnEnter(GameState::Editing), open_editor)
+            .add_systems(OnExit(GameState::Editing), clear_editor_sprites)
+            .add_systems(
+                Update,
+                (editor_panel, paint_tiles, draw_level)
+                    .chain()
+                    .run_if(in_state(GameState::Editing)),
+            );
+    }
+}
+
+//  NOTE: starts from the level being played, or an empty board the size the settings ask for
+fn open_editor(
+    mut editor: ResMut<Editor>,
+    mut load_board: EventWriter<LoadBoard>,
+    level: Res<CurrentLevel>,
+    settings: Res<Settings>,
+) {
+    editor.level = level.0.clone().unwrap_or_else(|| {
+        Level::new(
+            DEFAULT_LEVEL_NAME,
+            settings
+                .gameplay
+                .board_size
+                .clamp(MIN_BOARD_SIZE, MAX_BOARD_SIZE),
+        )
+    });
+    editor.saved = Level::saved();
+    editor.first_end = None;
+    editor.message = None;
+    load_board.send(LoadBoard {
+        size: editor.level.size,
+    });
+}
+
+fn clear_editor_sprites(mut commands: Commands, query: Query<Entity, With<EditorSprite>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn editor_panel(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<Editor>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut load_board: EventWriter<LoadBoard>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    settings: Res<Settings>,
+    theme: Res<CurrentTheme>,
+) {
+    let editor = &mut *editor;
+    let playable = editor.level.validate(settings.gameplay.walls);
+
+    egui::SidePanel::left("level-editor")
+        .exact_width(PANEL_WIDTH)
+        .resizable(false)
+        .frame(egui::Frame {
+            fill: color32(theme.panel),
+            inner_margin: egui::Margin::same(10.0),
+            ..Default::default()
+        })
+        .show(contexts.ctx_mut(), |ui| {
+            ui.heading(egui::RichText::new("Level editor").color(color32(theme.text)));
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut editor.level.name);
+            });
+            let mut size = editor.level.size;
+            if ui
+                .add(egui::Slider::new(&mut size, MIN_BOARD_SIZE..=MAX_BOARD_SIZE).text("Size"))
+                .changed()
+            {
+                editor.level.resize(size);
+                load_board.send(LoadBoard { size });
+            }
+
+            ui.separator();
+            ui.horizontal_wrapped(|ui| {
+                for tool in Tool::ALL {
+                    if ui
+                        .selectable_value(&mut editor.tool, tool, tool.label())
+                        .changed()
+                    {
+                        editor.first_end = None;
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Heading");
+                for (direction, arrow) in [
+                    (Direction::Up, "⬆"),
+                    (Direction::Down, "⬇"),
+                    (Direction::Left, "⬅"),
+                    (Direction::Right, "➡"),
+                ] {
+                    ui.selectable_value(&mut editor.level.direction, direction, arrow);
+                }
+            });
+            ui.label(egui::RichText::new(HELP).small());
+
+            ui.separator();
+            match &playable {
+                Ok(()) => ui.label("Playable"),
+                Err(err) => ui.colored_label(egui::Color32::LIGHT_RED, err.to_string()),
+            };
+            ui.horizontal_wrapped(|ui| {
+                if ui
+                    .add_enabled(playable.is_ok(), egui::Button::new("Play"))
+                    .clicked()
+                {
+                    //  NOTE: the board, snake and food are set up again for the level
+                    current_level.0 = Some(editor.level.clone());
+                    next_menu_state.set(MenuState::Off);
+                    next_state.set(GameState::Resetting);
+                }
+                if ui.button("Save").clicked() {
+                    editor.message = Some(match editor.level.save() {
+                        Ok(()) => format!("Saved {}", editor.level.name.trim()),
+                        Err(err) => err.to_string(),
+                    });
+                    editor.saved = Level::saved();
+                }
+                if ui.button("Clear").clicked() {
+                    editor.level = Level::new(&editor.level.name, editor.level.size);
+                }
+                if ui.button("Close").clicked() {
+                    next_menu_state.set(MenuState::On);
+                    next_state.set(GameState::Resetting);
+                }
+            });
+            if let Some(message) = &editor.message {
+                ui.label(egui::RichText::new(message).small());
+            }
+
+            ui.separator();
+            ui.label("Saved levels");
+            if editor.saved.is_empty() {
+                ui.label(egui::RichText::new("None yet").small());
+            }
+            let mut picked = None;
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for name in &editor.saved {
+                    if ui.button(name).clicked() {
+                        picked = Some(name.clone());
+                    }
+                }
+            });
+            if let Some(name) = picked {
+                match Level::load(&name) {
+                    Ok(level) => {
+                        load_board.send(LoadBoard { size: level.size });
+                        editor.level = level;
+                        editor.message = Some(format!("Loaded {name}"));
+                    }
+                    Err(err) => editor.message = Some(err.to_string()),
+                }
+            }
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn paint_tiles(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<Editor>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    board: Res<Board>,
+) {
+    //  NOTE: clicks on the panel aren't meant for the board underneath
+    if contexts.ctx_mut().wants_pointer_input() || contexts.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+    //  NOTE: wait for a resized board, its cells don't match the level's yet
+    if board.size != editor.level.size {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (window_query.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    let (screen, clear, pressed) = if let Some(touch) = touches.iter().next() {
+        let pressed = touches.just_pressed(touch.id());
+        (Some(touch.position()), false, pressed)
+    } else if mouse_input.pressed(MouseButton::Right) {
+        (
+            window.cursor_position(),
+            true,
+            mouse_input.just_pressed(MouseButton::Right),
+        )
+    } else if mouse_input.pressed(MouseButton::Left) {
+        (
+            window.cursor_position(),
+            false,
+            mouse_input.just_pressed(MouseButton::Left),
+        )
+    } else {
+        return;
+    };
+    let Some(pos) = screen
+        .and_then(|screen| camera.viewport_to_world_2d(camera_transform, screen))
+        .and_then(|point| board.cell_at(point))
+    else {
+        return;
+    };
+
+    let tool = if clear { Tool::Erase } else { editor.tool };
+    let level = &editor.level;
+    //  NOTE: only touch the level when the cell changes, dragging repaints the same cells
+    match tool {
+        Tool::Wall if !level.is_wall(pos) => editor.level.set_wall(pos, true),
+        Tool::Food if !level.is_food(pos) => editor.level.set_food(pos, true),
+        Tool::Start if pressed && level.start != pos => editor.level.start = pos,
+        Tool::Portal | Tool::Patrol if pressed => match editor.first_end.take() {
+            Some(end) if end != pos && tool == Tool::Portal => editor.level.add_portal(end, pos),
+            Some(end) if end != pos => editor.level.add_hazard(Hazard::Patrol([end, pos])),
+            Some(_) => {}
+            None => editor.first_end = Some(pos),
+        },
+        Tool::Spikes if !level.hazards.contains(&Hazard::Spikes(pos)) => {
+            editor.level.add_hazard(Hazard::Spikes(pos))
+        }
+        Tool::Hunter if pressed && !level.hazards.contains(&Hazard::Hunter(pos)) => {
+            editor.level.add_hazard(Hazard::Hunter(pos))
+        }
+        Tool::Erase
+            if level.is_wall(pos)
+                || level.is_food(pos)
+                || level.is_portal(pos)
+                || level.is_hazard(pos) =>
+        {
+            editor.level.set_wall(pos, false);
+            editor.level.set_food(pos, false);
+            editor.level.remove_portal(pos);
+            editor.level.remove_hazard(pos);
+        }
+        _ => {}
+    }
+}
+
+//  NOTE: egui takes `&mut` to the editor every frame, compare against what was last drawn instead
+fn draw_level(
+    mut commands: Commands,
+    editor: Res<Editor>,
+    board: Res<Board>,
+    sprites: Res<CurrentSpritePack>,
+    theme: Res<CurrentTheme>,
+    query: Query<Entity, With<EditorSprite>>,
+    mut drawn: Local<Option<(Level, Option<Position>, i32)>>,
+) {
+    let current = (editor.level.clone(), editor.first_end, board.size);
+    if drawn.as_ref() == Some(&current) && !sprites.is_changed() && !theme.is_changed() {
+        return;
+    }
+    *drawn = Some(current);
+
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let level = &editor.level;
+    for &pos in &level.walls {
+        commands.spawn((wall_sprite(&board, &theme, pos), EditorSprite));
+    }
+    for (i, pair) in level.portals.iter().enumerate() {
+        for &pos in pair {
+            commands.spawn((portal_sprite(&board, i, pos), EditorSprite));
+        }
+    }
+    //  NOTE: a patrol's way is marked with smaller, faded copies of it
+    for hazard in &level.hazards {
+        commands.spawn((
+            hazard_sprite(&board, hazard, hazard.position()),
+            EditorSprite,
+        ));
+        for pos in hazard.cells().into_iter().skip(1) {
+            let mut step = hazard_sprite(&board, hazard, pos);
+            step.sprite.color.set_a(0.4);
+            step.sprite.custom_size = Some(Vec2::splat(TILE_SIZE * 0.35));
+            commands.spawn((step, EditorSprite));
+        }
+    }
+    if let Some(pos) = editor.first_end {
+        let pending = match editor.tool {
+            Tool::Patrol => hazard_sprite(&board, &Hazard::Patrol([pos, pos]), pos),
+            _ => portal_sprite(&board, level.portals.len(), pos),
+        };
+        commands.spawn((pending, EditorSprite));
+    }
+
+    let tile = |pos: Position, part: SpritePart, z: f32| SpriteSheetBundle {
+        atlas: TextureAtlas {
+            layout: sprites.layout.clone(),
+            index: sprites.index(part),
+        },
+        texture: sprites.texture.clone(),
+        transform: Transform::from_xyz(
+            board.position_translate(pos.x),
+            board.position_translate(pos.y),
+            z,
+        ),
+        sprite: Sprite {
+            custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+            ..default()
+        },
+        ..default()
+    };
+    for &pos in &level.food {
+        commands.spawn((tile(pos, SpritePart::Apple, 1.0), EditorSprite));
+    }
+    let directions = VecDeque::from([level.direction; 3]);
+    for (i, pos) in level.snake().into_iter().enumerate() {
+        commands.spawn((tile(pos, segment_part(&directions, i), 2.0), EditorSprite));
+    }
+}
@@ -1,13 +1,18 @@
+use std::collections::HashSet;
+
 use bevy::prelude::*;
 
 use crate::{
-    asset_loader::{ImageAssets, SpritePart},
+    asset_loader::SpritePart,
     board::{Board, TILE_SIZE},
+    level::CurrentLevel,
     schedule::InGameSet,
     score::Score,
-    snake::{Direction, Position, SnakeDirectionQueue, SnakeHead, SnakeSegment},
+    settings::Settings,
+    sim::place_food,
+    snake::{Direction, Position, PreviousPosition, SnakeDirectionQueue, SnakeHead, SnakeSegment},
+    sprite_pack::CurrentSpritePack,
     state::GameState,
-    util::food_position,
 };
 
 #[derive(Component, Debug)]
@@ -29,7 +34,7 @@ impl Plugin for FoodPlugin {
 }
 
 #[derive(Event, Debug)]
-struct FoodEvent {
+pub struct FoodEvent {
     entity: Entity,
 }
 
@@ -39,15 +44,29 @@ impl FoodEvent {
     }
 }
 
-pub fn spawn_food(mut commands: Commands, board: Res<Board>, assets: Res<ImageAssets>) {
-    let food_pos = Position::new(board.size / 2 + 5, board.size / 2);
+pub fn spawn_food(
+    mut commands: Commands,
+    board: Res<Board>,
+    sprites: Res<CurrentSpritePack>,
+    level: Res<CurrentLevel>,
+) {
+    let food_pos = match &level.0 {
+        Some(level) => {
+            let occupied = level.snake().into_iter().collect();
+            let Some(food_pos) = level.food_position(&occupied, &mut rand::thread_rng()) else {
+                return;
+            };
+            food_pos
+        }
+        None => Position::new(board.size / 2 + 5, board.size / 2),
+    };
     commands.spawn((
         SpriteSheetBundle {
             atlas: TextureAtlas {
-                layout: assets.sprite_sheet_layout.clone(),
-                index: SpritePart::Apple as usize,
+                layout: sprites.layout.clone(),
+                index: sprites.index(SpritePart::Apple),
             },
-            texture: assets.sprite_sheet.clone(),
+            texture: sprites.texture.clone(),
             transform: Transform::from_xyz(
                 board.position_translate(food_pos.x),
                 board.position_translate(food_pos.y),
@@ -67,31 +86,40 @@ pub fn spawn_food(mut commands: Commands, board: Res<Board>, assets: Res<ImageAs
 
 fn handle_eat_food(
     mut food_event_write: EventWriter<FoodEvent>,
-    snake_head_query: Query<&Transform, With<SnakeHead>>,
-    food_query: Query<(&Transform, Entity), With<Food>>,
+    snake_head_query: Query<&Position, With<SnakeHead>>,
+    food_query: Query<(&Position, Entity), With<Food>>,
 ) {
-    let Ok(head_transform) = snake_head_query.get_single() else {
+    let Ok(head_pos) = snake_head_query.get_single() else {
         return;
     };
 
-    let Ok((food_tranform, food)) = food_query.get_single() else {
+    let Ok((food_pos, food)) = food_query.get_single() else {
         return;
     };
 
-    if head_transform.translation == food_tranform.translation {
+    //  NOTE: compare board cells, the head's transform glides between cells when interpolating
+    if head_pos == food_pos {
         food_event_write.send(FoodEvent::new(food));
         // do the scoreboard event send here?
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn apply_eat_food(
     mut commands: Commands,
     mut food_event_reader: EventReader<FoodEvent>,
-    snake_body_query: Query<(&mut Position, &SnakeSegment), Without<SnakeHead>>,
+    snake_body_query: Query<
+        (&Position, &PreviousPosition),
+        (With<SnakeSegment>, Without<SnakeHead>),
+    >,
+    snake_head_query: Query<&Position, With<SnakeHead>>,
     board: Res<Board>,
-    assets: Res<ImageAssets>,
+    sprites: Res<CurrentSpritePack>,
     mut snake_direction_queue: ResMut<SnakeDirectionQueue>,
     mut game_score: ResMut<Score>,
+    mut next_state: ResMut<NextState<GameState>>,
+    level: Res<CurrentLevel>,
+    settings: Res<Settings>,
 ) {
     for &FoodEvent { entity } in food_event_reader.read() {
         //  NOTE: food eaten, despawn food
@@ -101,22 +129,23 @@ fn apply_eat_food(
         let tail_direction = snake_direction_queue.directions.back().unwrap().clone();
         snake_direction_queue.directions.push_back(tail_direction);
 
-        let Some((&tail_pos, _)) = snake_body_query.iter().last() else {
+        //  NOTE: the new segment takes the cell the tail just left, as if the tail stayed put
+        let Some((_, &PreviousPosition(tail_pos))) = snake_body_query.iter().last() else {
             return;
         };
 
         commands.spawn((
             SpriteSheetBundle {
                 atlas: TextureAtlas {
-                    layout: assets.sprite_sheet_layout.clone(),
+                    layout: sprites.layout.clone(),
                     index: match tail_direction {
-                        Direction::Up => SpritePart::TailUp as usize,
-                        Direction::Down => SpritePart::TailDown as usize,
-                        Direction::Left => SpritePart::TailLeft as usize,
-                        Direction::Right => SpritePart::TailRight as usize,
+                        Direction::Up => sprites.index(SpritePart::TailUp),
+                        Direction::Down => sprites.index(SpritePart::TailDown),
+                        Direction::Left => sprites.index(SpritePart::TailLeft),
+                        Direction::Right => sprites.index(SpritePart::TailRight),
                     },
                 },
-                texture: assets.sprite_sheet.clone(),
+                texture: sprites.texture.clone(),
                 transform: Transform::from_xyz(
                     board.position_translate(tail_pos.x),
                     board.position_translate(tail_pos.y),
@@ -130,20 +159,33 @@ fn apply_eat_food(
             },
             SnakeSegment,
             Position::from(tail_pos),
+            PreviousPosition(tail_pos),
         ));
 
         //  NOTE: Increase score
         game_score.value += 1;
 
-        //  TODO: Food needs to spawn in a board position where the snake isn't at.
-        let food_pos = food_position(board.size);
+        let occupied: HashSet<Position> = snake_head_query
+            .iter()
+            .chain(snake_body_query.iter().map(|(pos, _)| pos))
+            .chain([&tail_pos])
+            .copied()
+            .collect();
+        let grid = board.grid(settings.gameplay.walls());
+        let food_pos = place_food(&grid, level.0.as_ref(), &occupied, &mut rand::thread_rng());
+        let Some(food_pos) = food_pos else {
+            //  NOTE: the snake covers the whole board, nothing left to eat
+            next_state.set(GameState::GameOver);
+            return;
+        };
+
         commands.spawn((
             SpriteSheetBundle {
                 atlas: TextureAtlas {
-                    layout: assets.sprite_sheet_layout.clone(),
-                    index: SpritePart::Apple as usize,
+                    layout: sprites.layout.clone(),
+                    index: sprites.index(SpritePart::Apple),
                 },
-                texture: assets.sprite_sheet.clone(),
+                texture: sprites.texture.clone(),
                 transform: Transform::from_xyz(
                     board.position_translate(food_pos.x),
                     board.position_translate(food_pos.y),
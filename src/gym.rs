@@ -0,0 +1,445 @@
+use std::{collections::HashSet, num::NonZeroUsize, thread};
+
+use crate::{
+    settings::WallsMode,
+    sim::{Game, Outcome, Rules},
+    snake::{Direction, Position},
+};
+
+//  NOTE: a gym style interface over the headless game in `sim.rs` for training agents, nothing
+//  in here touches bevy so it runs on machines without a GPU
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObservationKind {
+    //  NOTE: `CHANNELS` planes over the board with a one cell border around it for the walls,
+    //  laid out channel, row, column. On levels `Game::obstacles` go in the walls plane too
+    #[default]
+    Grid,
+    //  NOTE: `FEATURES` values describing the head's surroundings, see `features`
+    Features,
+}
+
+pub const CHANNELS: usize = 4;
+pub const HEAD_CHANNEL: usize = 0;
+pub const BODY_CHANNEL: usize = 1;
+pub const FOOD_CHANNEL: usize = 2;
+pub const WALLS_CHANNEL: usize = 3;
+
+pub const FEATURES: usize = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+//  NOTE: what the agent gets for each step, `approach` is paid for moving towards the food and
+//  taken away for moving away from it, leave it at 0 to only reward eating
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rewards {
+    pub food: f32,
+    pub win: f32,
+    pub death: f32,
+    pub step: f32,
+    pub approach: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Self {
+            food: 1.0,
+            win: 10.0,
+            death: -1.0,
+            step: -0.01,
+            approach: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EnvConfig {
+    pub rules: Rules,
+    pub observation: ObservationKind,
+    pub rewards: Rewards,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Info {
+    pub score: u32,
+    pub moves: u32,
+    pub outcome: Outcome,
+}
+
+//  NOTE: observation, reward, done, info
+pub type Transition = (Observation, f32, bool, Info);
+
+#[derive(Debug, Clone)]
+pub struct Env {
+    config: EnvConfig,
+    game: Game,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig) -> Self {
+        Self {
+            game: Game::new(config.rules.clone(), 0),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn observation_shape(&self) -> Vec<usize> {
+        match self.config.observation {
+            ObservationKind::Grid => {
+                let side = self.config.rules.grid.size as usize + 2;
+                vec![CHANNELS, side, side]
+            }
+            ObservationKind::Features => vec![FEATURES],
+        }
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::new(self.config.rules.clone(), seed);
+        self.observe()
+    }
+
+    //  NOTE: once `done` the game stays over until the next `reset`
+    pub fn step(&mut self, action: Direction) -> Transition {
+        let before = self.food_distance();
+        let outcome = self.game.step(action);
+        let rewards = &self.config.rewards;
+
+        let mut reward = rewards.step;
+        reward += match outcome {
+            Outcome::Moved => match (before, self.food_distance()) {
+                (Some(before), Some(after)) if after < before => rewards.approach,
+                (Some(before), Some(after)) if after > before => -rewards.approach,
+                _ => 0.0,
+            },
+            Outcome::Ate => rewards.food,
+            Outcome::Won => rewards.food + rewards.win,
+            Outcome::TimeUp => 0.0,
+            Outcome::Died(_) => rewards.death,
+        };
+
+        let info = Info {
+            score: self.game.score(),
+            moves: self.game.moves(),
+            outcome,
+        };
+        (self.observe(), reward, outcome.is_over(), info)
+    }
+
+    fn food_distance(&self) -> Option<i32> {
+        let grid = self.game.grid();
+        let head = self.game.head();
+        let food = self.game.food()?;
+        let axis = |from: i32, to: i32| {
+            let straight = (to - from).abs();
+            match grid.walls {
+                WallsMode::Wrap => straight.min(grid.size - straight),
+                WallsMode::Solid => straight,
+            }
+        };
+        Some(axis(head.x, food.x) + axis(head.y, food.y))
+    }
+
+    pub fn observe(&self) -> Observation {
+        let data = match self.config.observation {
+            ObservationKind::Grid => self.planes(),
+            ObservationKind::Features => self.features(),
+        };
+        Observation {
+            shape: self.observation_shape(),
+            data,
+        }
+    }
+
+    fn planes(&self) -> Vec<f32> {
+        let grid = self.game.grid();
+        let side = grid.size as usize + 2;
+        let mut data = vec![0.0; CHANNELS * side * side];
+        let mut set = |channel: usize, x: i32, y: i32| {
+            //  NOTE: shifted by one for the border
+            data[(channel * side + (y + 1) as usize) * side + (x + 1) as usize] = 1.0;
+        };
+
+        for &segment in self.game.snake().iter().skip(1) {
+            set(BODY_CHANNEL, segment.x, segment.y);
+        }
+        //  NOTE: after a crash into the wall the head is out in the border
+        let head = self.game.head();
+        if (-1..=grid.size).contains(&head.x) && (-1..=grid.size).contains(&head.y) {
+            set(HEAD_CHANNEL, head.x, head.y);
+        }
+        if let Some(food) = self.game.food() {
+            set(FOOD_CHANNEL, food.x, food.y);
+        }
+        if grid.walls == WallsMode::Solid {
+            for i in -1..=grid.size {
+                set(WALLS_CHANNEL, i, -1);
+                set(WALLS_CHANNEL, i, grid.size);
+                set(WALLS_CHANNEL, -1, i);
+                set(WALLS_CHANNEL, grid.size, i);
+            }
+        }
+        for obstacle in self.game.obstacles() {
+            set(WALLS_CHANNEL, obstacle.x, obstacle.y);
+        }
+        data
+    }
+
+    //  NOTE: danger straight ahead, to the left and to the right, the heading one hot as in
+    //  `Direction::ALL`, whether the food is up, down, left or right of the head and the length of
+    //  the snake as a share of the board. Danger is the edge, the body or one of the obstacles
+    fn features(&self) -> Vec<f32> {
+        let grid = self.game.grid();
+        let snake = self.game.snake();
+        let head = self.game.head();
+        let direction = self.game.direction();
+        let mut blocked: HashSet<Position> = snake.iter().take(snake.len() - 1).copied().collect();
+        blocked.extend(self.game.obstacles());
+        let danger = |direction: Direction| {
+            grid.step(head, direction)
+                .is_none_or(|next| blocked.contains(&next))
+        };
+        let flag = |value: bool| if value { 1.0 } else { 0.0 };
+
+        let mut features = vec![
+            flag(danger(direction)),
+            flag(danger(turn_left(direction))),
+            flag(danger(turn_right(direction))),
+        ];
+        features.extend(Direction::ALL.map(|heading| flag(heading == direction)));
+
+        let food = self.game.food();
+        features.extend([
+            flag(food.is_some_and(|food| food.y > head.y)),
+            flag(food.is_some_and(|food| food.y < head.y)),
+            flag(food.is_some_and(|food| food.x < head.x)),
+            flag(food.is_some_and(|food| food.x > head.x)),
+        ]);
+        features.push(snake.len() as f32 / grid.cells() as f32);
+        features
+    }
+}
+
+fn turn_left(direction: Direction) -> Direction {
+    match direction {
+        Direction::Up => Direction::Left,
+        Direction::Left => Direction::Down,
+        Direction::Down => Direction::Right,
+        Direction::Right => Direction::Up,
+    }
+}
+
+fn turn_right(direction: Direction) -> Direction {
+    turn_left(direction).opposite()
+}
+
+//  NOTE: a batch of environments stepped in parallel, finished games are reset straight away with
+//  the next unused seed so the observation returned for them belongs to the new game while the
+//  reward, done and info are the finished game's
+#[derive(Debug, Clone)]
+pub struct VecEnv {
+    envs: Vec<Env>,
+    next_seed: u64,
+}
+
+impl VecEnv {
+    pub fn new(config: EnvConfig, count: usize) -> Self {
+        Self {
+            envs: vec![Env::new(config); count],
+            next_seed: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    pub fn envs(&self) -> &[Env] {
+        &self.envs
+    }
+
+    //  NOTE: the environments get `seed`, `seed + 1`, ... in order
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.next_seed = seed + self.envs.len() as u64;
+        self.envs
+            .iter_mut()
+            .zip(seed..)
+            .map(|(env, seed)| env.reset(seed))
+            .collect()
+    }
+
+    pub fn step(&mut self, actions: &[Direction]) -> Vec<Transition> {
+        assert_eq!(actions.len(), self.envs.len(), "one action per environment");
+
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk = self.envs.len().div_ceil(threads).max(1);
+        let mut transitions: Vec<Transition> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .envs
+                .chunks_mut(chunk)
+                .zip(actions.chunks(chunk))
+                .map(|(envs, actions)| {
+                    scope.spawn(move || {
+                        envs.iter_mut()
+                            .zip(actions)
+                            .map(|(env, &action)| env.step(action))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        for (env, transition) in self.envs.iter_mut().zip(&mut transitions) {
+            if transition.2 {
+                transition.0 = env.reset(self.next_seed);
+                self.next_seed += 1;
+            }
+        }
+        transitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bot::Bot,
+        settings::{AiPlayer, MIN_BOARD_SIZE},
+        sim::DeathCause,
+    };
+
+    fn env(observation: ObservationKind) -> Env {
+        Env::new(EnvConfig {
+            observation,
+            ..EnvConfig::default()
+        })
+    }
+
+    #[test]
+    fn observations_have_the_advertised_shape() {
+        for observation in [ObservationKind::Grid, ObservationKind::Features] {
+            let mut env = env(observation);
+            let shape = env.observation_shape();
+            let first = env.reset(0);
+            assert_eq!(first.shape, shape);
+            assert_eq!(first.data.len(), shape.iter().product::<usize>());
+
+            let (next, ..) = env.step(Direction::Up);
+            assert_eq!(next.data.len(), shape.iter().product::<usize>());
+        }
+    }
+
+    #[test]
+    fn eating_pays_the_food_reward() {
+        //  NOTE: the food starts ten cells right of the head
+        let mut env = env(ObservationKind::Features);
+        let rewards = env.config().rewards;
+        for _ in 0..9 {
+            let (_, reward, done, info) = env.step(Direction::Right);
+            assert_eq!(
+                (reward, done, info.outcome),
+                (rewards.step, false, Outcome::Moved)
+            );
+        }
+        let (_, reward, done, info) = env.step(Direction::Right);
+        assert_eq!(info.outcome, Outcome::Ate);
+        assert_eq!(reward, rewards.step + rewards.food);
+        assert!(!done);
+        assert_eq!(info.score, 1);
+    }
+
+    #[test]
+    fn dying_pays_the_death_reward_and_stays_done() {
+        let mut env = env(ObservationKind::Grid);
+        let rewards = env.config().rewards;
+        let (reward, info) = loop {
+            let (_, reward, done, info) = env.step(Direction::Up);
+            if done {
+                break (reward, info);
+            }
+        };
+        assert_eq!(info.outcome, Outcome::Died(DeathCause::Wall));
+        assert_eq!(reward, rewards.step + rewards.death);
+
+        for direction in Direction::ALL {
+            let (_, _, done, after) = env.step(direction);
+            assert!(done);
+            assert_eq!(after, info);
+        }
+    }
+
+    #[test]
+    fn filling_the_board_pays_the_win_reward() {
+        let mut env = Env::new(EnvConfig {
+            rules: Rules::new(MIN_BOARD_SIZE, WallsMode::Solid),
+            ..EnvConfig::default()
+        });
+        let rewards = env.config().rewards;
+        let bot = Bot::new(AiPlayer::Hamiltonian, MIN_BOARD_SIZE);
+        let (reward, info) = loop {
+            let (_, reward, done, info) = env.step(bot.next_direction(env.game()));
+            if done {
+                break (reward, info);
+            }
+        };
+        assert_eq!(info.outcome, Outcome::Won);
+        assert_eq!(reward, rewards.step + rewards.food + rewards.win);
+    }
+
+    #[test]
+    fn vec_env_steps_like_separate_envs() {
+        const COUNT: usize = 5;
+        let config = EnvConfig {
+            rules: Rules::new(MIN_BOARD_SIZE, WallsMode::Solid),
+            ..EnvConfig::default()
+        };
+        let mut vec_env = VecEnv::new(config.clone(), COUNT);
+        let mut envs = vec![Env::new(config); COUNT];
+
+        let observations = vec_env.reset(7);
+        let mut next_seed = 7;
+        for (env, observation) in envs.iter_mut().zip(observations) {
+            assert_eq!(env.reset(next_seed), observation);
+            next_seed += 1;
+        }
+
+        //  NOTE: every env turns a different way, they run into the walls at different times
+        for step in 0..200 {
+            let actions: Vec<Direction> = (0..COUNT)
+                .map(|i| Direction::ALL[(step / (i + 2) + i) % Direction::ALL.len()])
+                .collect();
+            let transitions = vec_env.step(&actions);
+            for ((env, &action), transition) in envs.iter_mut().zip(&actions).zip(transitions) {
+                let mut expected = env.step(action);
+                if expected.2 {
+                    expected.0 = env.reset(next_seed);
+                    next_seed += 1;
+                }
+                assert_eq!(transition, expected, "step {step}");
+            }
+        }
+        assert!(next_seed > 7 + COUNT as u64, "no game ended");
+        for (env, separate) in vec_env.envs().iter().zip(&envs) {
+            assert_eq!(env.game().snake(), separate.game().snake());
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+
+use crate::{
+    sim::Grid,
+    snake::{Direction, Position},
+};
+
+//  NOTE: a closed walk through every cell of the board, following it can never run into the body.
+//  Odd boards have an odd number of cells and no such cycle exists, there the top right corner is
+//  left out and treated as a stand-in for the cell diagonal to it, both share one slot of the cycle
+#[derive(Debug, Clone)]
+struct Cycle {
+    size: i32,
+    order: Vec<Position>,
+    //  NOTE: index into `order` for every cell, row by row
+    slots: Vec<usize>,
+}
+
+impl Cycle {
+    //  NOTE: up along row 0, snaking through columns 1.. row by row and back down column 0
+    fn new(size: i32) -> Self {
+        let mut order: Vec<Position> = (0..size).map(|x| Position::new(x, 0)).collect();
+        let snaking_rows = if size % 2 == 0 { size - 1 } else { size - 3 };
+
+        for y in 1..=snaking_rows {
+            if y % 2 == 1 {
+                order.extend((1..size).rev().map(|x| Position::new(x, y)));
+            } else {
+                order.extend((1..size).map(|x| Position::new(x, y)));
+            }
+        }
+
+        let mut detour = None;
+        if size % 2 == 1 {
+            //  NOTE: the two top rows are walked column by column, going around the corner so the
+            //  cell diagonal to it sits between the corner's two neighbours
+            let (top, below) = (size - 1, size - 2);
+            order.extend([
+                Position::new(size - 1, below),
+                Position::new(size - 2, below),
+                Position::new(size - 2, top),
+            ]);
+            for (i, x) in (1..size - 2).rev().enumerate() {
+                if i % 2 == 0 {
+                    order.extend([Position::new(x, top), Position::new(x, below)]);
+                } else {
+                    order.extend([Position::new(x, below), Position::new(x, top)]);
+                }
+            }
+            detour = Some((Position::new(size - 1, top), Position::new(size - 2, below)));
+        }
+
+        order.extend((1..size).rev().map(|y| Position::new(0, y)));
+
+        let mut cycle = Self {
+            size,
+            order,
+            slots: vec![0; (size * size) as usize],
+        };
+        cycle.index(detour);
+        cycle
+    }
+
+    fn index(&mut self, detour: Option<(Position, Position)>) {
+        for (slot, &pos) in self.order.iter().enumerate() {
+            self.slots[(pos.y * self.size + pos.x) as usize] = slot;
+        }
+        if let Some((corner, stand_in)) = detour {
+            self.slots[(corner.y * self.size + corner.x) as usize] = self.slot(stand_in);
+        }
+    }
+
+    fn reversed(&self) -> Self {
+        let len = self.order.len();
+        Self {
+            size: self.size,
+            order: self.order.iter().rev().copied().collect(),
+            slots: self.slots.iter().map(|slot| len - 1 - slot).collect(),
+        }
+    }
+
+    fn slot(&self, pos: Position) -> usize {
+        self.slots[(pos.y * self.size + pos.x) as usize]
+    }
+
+    //  NOTE: how many slots forward along the cycle it takes to get from one cell to the other
+    fn distance(&self, from: Position, to: Position) -> usize {
+        let len = self.order.len();
+        (self.slot(to) + len - self.slot(from)) % len
+    }
+
+    //  NOTE: like `distance`, but landing on the corner's stand-in when the food is on the corner
+    //  (or the other way round) counts as having gone past it
+    fn to_food(&self, from: Position, food: Position) -> usize {
+        match self.distance(from, food) {
+            0 if from != food => self.order.len(),
+            distance => distance,
+        }
+    }
+
+    //  NOTE: whether every segment sits further along the cycle than the one behind it, only then
+    //  is following the cycle safe
+    fn follows(&self, snake: &[Position]) -> bool {
+        let walked: usize = snake
+            .windows(2)
+            .map(|pair| self.distance(pair[1], pair[0]))
+            .sum();
+        walked < self.order.len()
+    }
+
+    fn plan_direction(
+        &self,
+        grid: &Grid,
+        snake: &[Position],
+        food: &[Position],
+    ) -> Option<Direction> {
+        let (&head, _) = snake.split_first()?;
+        let tail = *snake.last()?;
+        let blocked: HashSet<Position> = snake[..snake.len() - 1].iter().copied().collect();
+        let to_tail = self.distance(head, tail);
+        //  NOTE: cutting across the cycle leaves holes behind the snake, stop once it's half the
+        //  board and needs every cell
+        let shortcuts = snake.len() * 2 < self.order.len();
+
+        Direction::ALL
+            .into_iter()
+            .filter_map(|direction| {
+                let next = grid.step(head, direction)?;
+                if blocked.contains(&next) {
+                    return None;
+                }
+
+                //  NOTE: skipping ahead is safe as long as the head stays behind the tail
+                let skipped = self.distance(head, next);
+                (skipped == 1 || shortcuts && skipped < to_tail).then_some((direction, next))
+            })
+            .min_by_key(|&(_, next)| food.first().map_or(0, |&food| self.to_food(next, food)))
+            .map(|(direction, _)| direction)
+    }
+}
+
+//  NOTE: perfect play bot, follows a Hamiltonian cycle and takes shortcuts to the food while the
+//  snake is short enough to afford them
+#[derive(Debug, Clone)]
+pub struct HamiltonianSolver {
+    //  NOTE: the cycle both ways round, the snake has to be lined up with one of them
+    cycles: [Cycle; 2],
+}
+
+impl HamiltonianSolver {
+    pub fn new(size: i32) -> Self {
+        let cycle = Cycle::new(size);
+        let reversed = cycle.reversed();
+        Self {
+            cycles: [cycle, reversed],
+        }
+    }
+
+    pub fn size(&self) -> i32 {
+        self.cycles[0].size
+    }
+
+    //  NOTE: `snake` is ordered head first, `None` when the body doesn't follow either direction
+    //  of the cycle yet
+    pub fn plan_direction(
+        &self,
+        grid: &Grid,
+        snake: &[Position],
+        food: &[Position],
+    ) -> Option<Direction> {
+        self.cycles
+            .iter()
+            .find(|cycle| cycle.follows(snake))?
+            .plan_direction(grid, snake, food)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bot::Bot,
+        settings::{AiPlayer, WallsMode},
+        sim::{Game, Outcome, Rules},
+    };
+
+    //  NOTE: `Rules::starvation` ends games that go round in circles, so this always finishes
+    fn fill_board(size: i32, seed: u64) {
+        let rules = Rules::new(size, WallsMode::Solid);
+        let bot = Bot::new(AiPlayer::Hamiltonian, size);
+        let mut game = Game::new(rules, seed);
+        let outcome = loop {
+            let outcome = game.step(bot.next_direction(&game));
+            if outcome.is_over() {
+                break outcome;
+            }
+        };
+
+        assert_eq!(
+            outcome,
+            Outcome::Won,
+            "{size}x{size}, seed {seed}: score {} after {} moves",
+            game.score(),
+            game.moves()
+        );
+        assert_eq!(game.snake().len(), game.grid().cells());
+    }
+
+    #[test]
+    fn fills_an_even_board() {
+        for seed in 0..5 {
+            fill_board(20, seed);
+        }
+    }
+
+    #[test]
+    fn fills_odd_boards() {
+        for size in [15, 21] {
+            for seed in 0..5 {
+                fill_board(size, seed);
+            }
+        }
+    }
+}
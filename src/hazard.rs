@@ -0,0 +1,331 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    board::{Board, TILE_SIZE},
+    level::{CurrentLevel, Hazard, Level},
+    schedule::InGameSet,
+    settings::Settings,
+    sim::Grid,
+    snake::{Direction, MovementTimer, Position, PreviousPosition, SnakeHead, SnakeSegment},
+    state::GameState,
+};
+
+//  NOTE: the level's hazards brought to life. Patrols and hunters move on the snake's movement
+//  tick, right after it, and spikes go in and out on their own clock. Running into one is caught
+//  by `collision_detection`
+
+//  NOTE: seconds spikes stay out, and then in
+const SPIKE_PERIOD: f32 = 2.0;
+//  NOTE: how see-through spikes are while pulled in
+const SPIKE_IN_ALPHA: f32 = 0.25;
+
+//  NOTE: anything on the board that kills the snake's head. Plain data moved by the functions
+//  below, so headless games in `sim.rs` run the same hazards as the bevy systems
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Enemy {
+    kind: EnemyKind,
+    pos: Position,
+    //  NOTE: where it was before the last move
+    prev: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum EnemyKind {
+    Patrol {
+        route: Vec<Position>,
+        index: usize,
+        forward: bool,
+    },
+    //  NOTE: out for the first `SPIKE_PERIOD` seconds, then in for as long and so on
+    Spikes {
+        elapsed: Duration,
+    },
+    //  NOTE: hunters sit out every other move, the snake can outrun them
+    Hunter {
+        resting: bool,
+    },
+}
+
+impl Enemy {
+    pub fn new(hazard: &Hazard) -> Self {
+        let kind = match hazard {
+            Hazard::Patrol(_) => EnemyKind::Patrol {
+                route: hazard.cells(),
+                index: 0,
+                forward: true,
+            },
+            Hazard::Spikes(_) => EnemyKind::Spikes {
+                elapsed: Duration::ZERO,
+            },
+            Hazard::Hunter(_) => EnemyKind::Hunter { resting: false },
+        };
+        let pos = hazard.position();
+        Self {
+            kind,
+            pos,
+            prev: pos,
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    pub fn previous(&self) -> Position {
+        self.prev
+    }
+
+    //  NOTE: spikes only hurt while they're out
+    pub fn armed(&self) -> bool {
+        match self.kind {
+            EnemyKind::Spikes { elapsed } => {
+                elapsed.as_secs_f32() % (SPIKE_PERIOD * 2.0) < SPIKE_PERIOD
+            }
+            _ => true,
+        }
+    }
+
+    //  NOTE: whether a head that just moved from `head_prev` to `head` runs into it, or it into
+    //  the head. Moving towards each other they can swap cells without ever sharing one
+    pub fn hits(&self, head: Position, head_prev: Position) -> bool {
+        match self.kind {
+            EnemyKind::Spikes { .. } => self.armed() && head == self.pos,
+            _ => head == self.pos || (head == self.prev && head_prev == self.pos),
+        }
+    }
+
+    //  NOTE: runs the spikes' clock, `true` when they went in or out
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        let armed = self.armed();
+        if let EnemyKind::Spikes { elapsed } = &mut self.kind {
+            *elapsed += delta;
+        }
+        armed != self.armed()
+    }
+}
+
+//  NOTE: one move for every patrol and hunter, right after the snake's. `snake` is head first
+pub fn move_enemies<'a>(
+    enemies: impl IntoIterator<Item = &'a mut Enemy>,
+    grid: &Grid,
+    level: Option<&Level>,
+    snake: &[Position],
+) {
+    let mut enemies: Vec<&mut Enemy> = enemies.into_iter().collect();
+    let Some((&head, body)) = snake.split_first() else {
+        return;
+    };
+
+    //  NOTE: patrols walk their route, turning back at either end
+    for enemy in enemies.iter_mut() {
+        enemy.prev = enemy.pos;
+        if let EnemyKind::Patrol {
+            route,
+            index,
+            forward,
+        } = &mut enemy.kind
+        {
+            let last = route.len() - 1;
+            if last == 0 {
+                continue;
+            }
+            if *index == last {
+                *forward = false;
+            } else if *index == 0 {
+                *forward = true;
+            }
+            *index = if *forward { *index + 1 } else { *index - 1 };
+            enemy.pos = route[*index];
+        }
+    }
+
+    //  NOTE: hunters go around the body, the patrols and each other, spikes can be walked over
+    let mut blocked: HashSet<Position> = body
+        .iter()
+        .copied()
+        .chain(
+            enemies
+                .iter()
+                .filter(|enemy| !matches!(enemy.kind, EnemyKind::Spikes { .. }))
+                .map(|enemy| enemy.pos),
+        )
+        .collect();
+    for enemy in enemies.iter_mut() {
+        let EnemyKind::Hunter { resting } = &mut enemy.kind else {
+            continue;
+        };
+        *resting = !*resting;
+        if *resting {
+            continue;
+        }
+        if let Some(step) = hunt(grid, level, &blocked, enemy.pos, head) {
+            blocked.remove(&enemy.pos);
+            blocked.insert(step);
+            enemy.pos = step;
+        }
+    }
+}
+
+//  NOTE: breadth first from the hunter until it reaches the head, then back to the first step
+//  taken. Walls and portals are in the way too
+fn hunt(
+    grid: &Grid,
+    level: Option<&Level>,
+    blocked: &HashSet<Position>,
+    from: Position,
+    head: Position,
+) -> Option<Position> {
+    let free = |pos: Position| {
+        !blocked.contains(&pos)
+            && !level.is_some_and(|level| level.is_wall(pos) || level.is_portal(pos))
+    };
+    let mut came_from = HashMap::from([(from, from)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(cell) = queue.pop_front() {
+        if cell == head {
+            break;
+        }
+        for direction in Direction::ALL {
+            let Some(next) = grid.step(cell, direction) else {
+                continue;
+            };
+            if (next == head || free(next)) && !came_from.contains_key(&next) {
+                came_from.insert(next, cell);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut step = came_from.contains_key(&head).then_some(head)?;
+    while came_from[&step] != from {
+        step = came_from[&step];
+    }
+    Some(step)
+}
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::NewGame), spawn_hazards)
+            .add_systems(
+                Update,
+                (move_hazards, toggle_spikes, update_hazard_position)
+                    .chain()
+                    .in_set(InGameSet::HazardUpdates),
+            );
+    }
+}
+
+fn spawn_hazards(mut commands: Commands, level: Res<CurrentLevel>, board: Res<Board>) {
+    let Some(level) = &level.0 else {
+        return;
+    };
+    for hazard in &level.hazards {
+        let pos = hazard.position();
+        commands.spawn((
+            hazard_sprite(&board, hazard, pos),
+            Enemy::new(hazard),
+            pos,
+            PreviousPosition(pos),
+        ));
+    }
+}
+
+//  NOTE: patrols are orange, hunters red and spikes steel grey, whatever the theme
+pub fn hazard_sprite(board: &Board, hazard: &Hazard, pos: Position) -> SpriteBundle {
+    let color = match hazard {
+        Hazard::Patrol(_) => Color::hsl(30.0, 0.9, 0.5),
+        Hazard::Spikes(_) => Color::hsl(220.0, 0.1, 0.6),
+        Hazard::Hunter(_) => Color::hsl(350.0, 0.85, 0.45),
+    };
+    SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::splat(TILE_SIZE * 0.7)),
+            ..default()
+        },
+        transform: Transform::from_xyz(
+            board.position_translate(pos.x),
+            board.position_translate(pos.y),
+            1.5,
+        ),
+        ..default()
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn move_hazards(
+    movement_timer: Res<MovementTimer>,
+    board: Res<Board>,
+    settings: Res<Settings>,
+    level: Res<CurrentLevel>,
+    snake_head_query: Query<&Position, (With<SnakeHead>, Without<Enemy>)>,
+    snake_body_query: Query<&Position, (With<SnakeSegment>, Without<SnakeHead>, Without<Enemy>)>,
+    mut enemy_query: Query<(&mut Enemy, &mut Position, &mut PreviousPosition)>,
+) {
+    if !movement_timer.just_moved() {
+        return;
+    }
+
+    //  NOTE: same order as `update_position` walks the segments, head first and tail last
+    let snake: Vec<Position> = snake_head_query
+        .iter()
+        .chain(snake_body_query.iter())
+        .copied()
+        .collect();
+    let grid = board.grid(settings.gameplay.walls());
+    move_enemies(
+        enemy_query
+            .iter_mut()
+            .map(|(enemy, _, _)| enemy.into_inner()),
+        &grid,
+        level.0.as_ref(),
+        &snake,
+    );
+    for (enemy, mut pos, mut prev) in enemy_query.iter_mut() {
+        *pos = enemy.position();
+        prev.0 = enemy.previous();
+    }
+}
+
+fn toggle_spikes(time: Res<Time>, mut query: Query<(&mut Enemy, &mut Sprite)>) {
+    for (mut enemy, mut sprite) in query.iter_mut() {
+        if enemy.tick(time.delta()) {
+            let alpha = if enemy.armed() { 1.0 } else { SPIKE_IN_ALPHA };
+            sprite.color.set_a(alpha);
+        }
+    }
+}
+
+//  NOTE: glide between cells like the snake does
+fn update_hazard_position(
+    board: Res<Board>,
+    movement_timer: Res<MovementTimer>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Transform, &Position, &PreviousPosition), With<Enemy>>,
+) {
+    let interpolate = settings.video.interpolation && !settings.accessibility.reduced_motion;
+    for (mut transform, pos, PreviousPosition(prev)) in query.iter_mut() {
+        let current = Vec2::new(
+            board.position_translate(pos.x),
+            board.position_translate(pos.y),
+        );
+        let adjacent = (pos.x - prev.x).abs() + (pos.y - prev.y).abs() <= 1;
+        let translation = if interpolate && adjacent {
+            let previous = Vec2::new(
+                board.position_translate(prev.x),
+                board.position_translate(prev.y),
+            );
+            previous.lerp(current, movement_timer.fraction())
+        } else {
+            current
+        };
+        transform.translation = translation.extend(transform.translation.z);
+    }
+}
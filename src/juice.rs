@@ -0,0 +1,259 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    board::{Board, TILE_SIZE},
+    camera::CameraShake,
+    collision_detection::CollisionEvent,
+    food::FoodEvent,
+    schedule::InGameSet,
+    settings::Settings,
+    snake::{Direction, Position, SnakeHead, TurnEvent},
+    state::GameState,
+    theme::CurrentTheme,
+};
+
+//  NOTE: effects that make the game feel alive without changing how it plays: a burst of
+//  particles when food is eaten, the screen shaking on death, the head squashing as it turns and
+//  the segment the snake ran into lighting up. Reduced motion keeps only the flash, which doesn't
+//  move
+
+const PARTICLES: usize = 12;
+const PARTICLE_SIZE: f32 = TILE_SIZE / 4.0;
+const PARTICLE_LIFETIME: f32 = 0.4;
+//  NOTE: tiles per second the particles fly out at, they slow down as they fade
+const PARTICLE_SPEED: f32 = 6.0;
+//  NOTE: screen shake, trauma goes from 1 to 0 and the offset grows with its square so small
+//  knocks barely move the screen
+const DEATH_TRAUMA: f32 = 1.0;
+const TRAUMA_DECAY: f32 = 1.8;
+const MAX_SHAKE: f32 = TILE_SIZE * 0.75;
+const SQUASH_TIME: f32 = 0.15;
+//  NOTE: how much thinner the head gets along the way it's now heading
+const SQUASH_AMOUNT: f32 = 0.3;
+const FLASH_TIME: f32 = 0.6;
+//  NOTE: above the snake segments
+const EFFECT_Z: f32 = 20.0;
+
+#[derive(Component, Debug)]
+struct Particle {
+    velocity: Vec2,
+    timer: Timer,
+}
+
+#[derive(Component, Debug)]
+struct Squash {
+    direction: Direction,
+    timer: Timer,
+}
+
+#[derive(Component, Debug)]
+struct Flash(Timer);
+
+#[derive(Resource, Debug, Default)]
+struct Trauma(f32);
+
+pub struct JuicePlugin;
+
+impl Plugin for JuicePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Trauma>()
+            .add_systems(OnEnter(GameState::NewGame), reset_trauma)
+            //  NOTE: the collision ends the game on the same frame, so these aren't in `InGameSet`
+            .add_systems(
+                Update,
+                (burst_on_food, squash_on_turn, react_to_collision).after(InGameSet::EntityUpdates),
+            )
+            .add_systems(
+                Update,
+                (update_particles, update_squash, update_flash, shake_camera)
+                    .run_if(not(in_state(GameState::Paused))),
+            );
+    }
+}
+
+fn reset_trauma(mut trauma: ResMut<Trauma>, mut shake: ResMut<CameraShake>) {
+    trauma.0 = 0.0;
+    shake.0 = Vec2::ZERO;
+}
+
+fn burst_on_food(
+    mut commands: Commands,
+    mut food_event_reader: EventReader<FoodEvent>,
+    head_query: Query<&Position, With<SnakeHead>>,
+    board: Res<Board>,
+    theme: Res<CurrentTheme>,
+    settings: Res<Settings>,
+) {
+    //  NOTE: the food sits where the head just got to
+    let eaten = food_event_reader.read().count() > 0;
+    if !eaten || settings.accessibility.reduced_motion {
+        return;
+    }
+    let Ok(head_pos) = head_query.get_single() else {
+        return;
+    };
+
+    let origin = Vec2::new(
+        board.position_translate(head_pos.x),
+        board.position_translate(head_pos.y),
+    );
+    let mut rng = rand::thread_rng();
+    for i in 0..PARTICLES {
+        let angle = TAU * i as f32 / PARTICLES as f32 + rng.gen_range(-0.2..0.2);
+        let speed = PARTICLE_SPEED * TILE_SIZE * rng.gen_range(0.6..1.0);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: theme.food,
+                    custom_size: Some(Vec2::splat(PARTICLE_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_translation(origin.extend(EFFECT_Z)),
+                ..default()
+            },
+            Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                timer: Timer::from_seconds(PARTICLE_LIFETIME, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn squash_on_turn(
+    mut commands: Commands,
+    mut turn_event_reader: EventReader<TurnEvent>,
+    head_query: Query<Entity, With<SnakeHead>>,
+    settings: Res<Settings>,
+) {
+    let Some(turn) = turn_event_reader.read().last() else {
+        return;
+    };
+    if settings.accessibility.reduced_motion {
+        return;
+    }
+    let Ok(head) = head_query.get_single() else {
+        return;
+    };
+
+    commands.entity(head).insert(Squash {
+        direction: turn.direction,
+        timer: Timer::from_seconds(SQUASH_TIME, TimerMode::Once),
+    });
+}
+
+fn react_to_collision(
+    mut commands: Commands,
+    mut collision_event_reader: EventReader<CollisionEvent>,
+    transforms: Query<&Transform>,
+    mut trauma: ResMut<Trauma>,
+    settings: Res<Settings>,
+) {
+    for event in collision_event_reader.read() {
+        if !settings.accessibility.reduced_motion {
+            trauma.0 = (trauma.0 + DEATH_TRAUMA).min(1.0);
+        }
+
+        //  NOTE: the segments blink out while dying, so the flash is a sprite of its own on top of
+        //  where the segment was. Running off the board lights up the head
+        let hit = event.collided_entity.unwrap_or(event.entity);
+        let Ok(transform) = transforms.get(hit) else {
+            continue;
+        };
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::WHITE,
+                    custom_size: Some(Vec2::splat(TILE_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_translation(
+                    transform.translation.truncate().extend(EFFECT_Z),
+                ),
+                ..default()
+            },
+            Flash(Timer::from_seconds(FLASH_TIME, TimerMode::Once)),
+        ));
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in query.iter_mut() {
+        particle.timer.tick(time.delta());
+        if particle.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let left = 1.0 - particle.timer.fraction();
+        transform.translation += (particle.velocity * left * time.delta_seconds()).extend(0.0);
+        transform.scale = Vec3::new(left, left, 1.0);
+        sprite.color.set_a(left);
+    }
+}
+
+fn update_squash(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Squash, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut squash, mut transform) in query.iter_mut() {
+        squash.timer.tick(time.delta());
+        if squash.timer.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<Squash>();
+            continue;
+        }
+
+        //  NOTE: in and back out again, the head gets wider as it gets shorter
+        let amount = SQUASH_AMOUNT * (PI * squash.timer.fraction()).sin();
+        let (along, across) = (1.0 - amount, 1.0 + amount);
+        transform.scale = match squash.direction {
+            Direction::Left | Direction::Right => Vec3::new(along, across, 1.0),
+            Direction::Up | Direction::Down => Vec3::new(across, along, 1.0),
+        };
+    }
+}
+
+fn update_flash(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Flash, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, mut sprite) in query.iter_mut() {
+        flash.0.tick(time.delta());
+        if flash.0.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        sprite.color.set_a(1.0 - flash.0.fraction());
+    }
+}
+
+fn shake_camera(
+    mut trauma: ResMut<Trauma>,
+    mut shake: ResMut<CameraShake>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    if settings.accessibility.reduced_motion {
+        trauma.0 = 0.0;
+    }
+    if trauma.0 <= 0.0 {
+        if shake.0 != Vec2::ZERO {
+            shake.0 = Vec2::ZERO;
+        }
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    let strength = trauma.0 * trauma.0 * MAX_SHAKE;
+    shake.0 = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * strength;
+    trauma.0 = (trauma.0 - TRAUMA_DECAY * time.delta_seconds()).max(0.0);
+}
@@ -0,0 +1,692 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+};
+
+use bevy::prelude::*;
+use rand::{seq::IteratorRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{Board, TILE_SIZE},
+    settings::{WallsMode, MAX_BOARD_SIZE, MIN_BOARD_SIZE},
+    sim::Grid,
+    snake::{Direction, Position},
+    state::GameState,
+    theme::CurrentTheme,
+    util::snake_starting_position,
+};
+
+//  NOTE: a hand-made board: walls, where the snake starts and where food may appear. Made with
+//  the editor in `editor.rs` and played instead of the open board while it's the `CurrentLevel`
+
+//  NOTE: hunters start at least this many cells from the head
+pub const HUNTER_CLEARANCE: i32 = 6;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    pub size: i32,
+    //  NOTE: the head, the other two segments trail behind it
+    pub start: Position,
+    pub direction: Direction,
+    pub walls: Vec<Position>,
+    //  NOTE: food only appears on these cells, anywhere free when there are none
+    pub food: Vec<Position>,
+    //  NOTE: pairs of cells, a head moving onto one end comes out of the other
+    #[serde(default)]
+    pub portals: Vec<[Position; 2]>,
+    //  NOTE: spawned as enemies when the level starts, see `hazard.rs`
+    #[serde(default)]
+    pub hazards: Vec<Hazard>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Hazard {
+    //  NOTE: walks from one end to the other and back, along the row of the first end and then
+    //  the column of the second
+    Patrol([Position; 2]),
+    //  NOTE: sticks out and pulls back in on a timer
+    Spikes(Position),
+    //  NOTE: chases the head, a cell every other move
+    Hunter(Position),
+}
+
+impl Hazard {
+    //  NOTE: where it starts
+    pub fn position(&self) -> Position {
+        match *self {
+            Self::Patrol([start, _]) | Self::Spikes(start) | Self::Hunter(start) => start,
+        }
+    }
+
+    //  NOTE: every cell it can end up on, except for hunters that go anywhere
+    pub fn cells(&self) -> Vec<Position> {
+        match *self {
+            Self::Patrol([from, to]) => {
+                let step = |from: i32, to: i32| if to < from { -1 } else { 1 };
+                let mut cells: Vec<Position> = std::iter::successors(Some(from.x), |&x| {
+                    (x != to.x).then(|| x + step(from.x, to.x))
+                })
+                .map(|x| Position::new(x, from.y))
+                .collect();
+                cells.extend(
+                    std::iter::successors(Some(from.y), |&y| {
+                        (y != to.y).then(|| y + step(from.y, to.y))
+                    })
+                    .skip(1)
+                    .map(|y| Position::new(to.x, y)),
+                );
+                cells
+            }
+            Self::Spikes(pos) | Self::Hunter(pos) => vec![pos],
+        }
+    }
+}
+
+impl Level {
+    //  NOTE: an open board, played like the classic game
+    pub fn new(name: &str, size: i32) -> Self {
+        Self {
+            name: name.to_string(),
+            size,
+            start: snake_starting_position(size)[0],
+            direction: Direction::Right,
+            walls: Vec::new(),
+            food: Vec::new(),
+            portals: Vec::new(),
+            hazards: Vec::new(),
+        }
+    }
+
+    //  NOTE: head first, like the segments are spawned
+    pub fn snake(&self) -> Vec<Position> {
+        let grid = Grid {
+            size: self.size,
+            walls: WallsMode::Solid,
+        };
+        let mut snake = vec![self.start];
+        for _ in 1..3 {
+            let last = snake[snake.len() - 1];
+            snake.push(grid.advance(last, self.direction.opposite()));
+        }
+        snake
+    }
+
+    pub fn is_wall(&self, pos: Position) -> bool {
+        self.walls.contains(&pos)
+    }
+
+    pub fn is_food(&self, pos: Position) -> bool {
+        self.food.contains(&pos)
+    }
+
+    pub fn is_portal(&self, pos: Position) -> bool {
+        self.portals.iter().any(|pair| pair.contains(&pos))
+    }
+
+    pub fn is_hazard(&self, pos: Position) -> bool {
+        self.hazards
+            .iter()
+            .any(|hazard| hazard.cells().contains(&pos))
+    }
+
+    //  NOTE: where a head moving onto `pos` comes out, `None` when it isn't a portal
+    pub fn portal_exit(&self, pos: Position) -> Option<Position> {
+        self.portals.iter().find_map(|&[a, b]| {
+            if a == pos {
+                Some(b)
+            } else if b == pos {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    }
+
+    //  NOTE: a cell holds a wall, a food spawn or a portal, never more than one. Hazards share
+    //  cells with food only
+    pub fn set_wall(&mut self, pos: Position, wall: bool) {
+        self.walls.retain(|&cell| cell != pos);
+        if wall {
+            self.food.retain(|&cell| cell != pos);
+            self.remove_portal(pos);
+            self.remove_hazard(pos);
+            self.walls.push(pos);
+        }
+    }
+
+    pub fn set_food(&mut self, pos: Position, food: bool) {
+        self.food.retain(|&cell| cell != pos);
+        if food {
+            self.walls.retain(|&cell| cell != pos);
+            self.remove_portal(pos);
+            self.food.push(pos);
+        }
+    }
+
+    pub fn add_portal(&mut self, a: Position, b: Position) {
+        for pos in [a, b] {
+            self.set_wall(pos, false);
+            self.set_food(pos, false);
+            self.remove_portal(pos);
+            self.remove_hazard(pos);
+        }
+        self.portals.push([a, b]);
+    }
+
+    //  NOTE: takes the other end along
+    pub fn remove_portal(&mut self, pos: Position) {
+        self.portals.retain(|pair| !pair.contains(&pos));
+    }
+
+    //  NOTE: clears the walls, portals and other hazards in its way
+    pub fn add_hazard(&mut self, hazard: Hazard) {
+        for pos in hazard.cells() {
+            self.set_wall(pos, false);
+            self.remove_portal(pos);
+            self.remove_hazard(pos);
+        }
+        self.hazards.push(hazard);
+    }
+
+    //  NOTE: a patrol goes when any cell along its way is cleared
+    pub fn remove_hazard(&mut self, pos: Position) {
+        self.hazards.retain(|hazard| !hazard.cells().contains(&pos));
+    }
+
+    //  NOTE: anything that no longer fits is dropped, the start is pulled back onto the board
+    pub fn resize(&mut self, size: i32) {
+        let grid = Grid {
+            size,
+            walls: WallsMode::Solid,
+        };
+        self.size = size;
+        self.walls.retain(|&pos| grid.contains(pos));
+        self.food.retain(|&pos| grid.contains(pos));
+        self.portals
+            .retain(|pair| pair.iter().all(|&pos| grid.contains(pos)));
+        self.hazards
+            .retain(|hazard| hazard.cells().iter().all(|&pos| grid.contains(pos)));
+        self.start = Position::new(
+            self.start.x.clamp(0, size - 1),
+            self.start.y.clamp(0, size - 1),
+        );
+    }
+
+    //  NOTE: a level is playable when the snake fits where it starts, has somewhere to go and can
+    //  get to every cell food can appear on
+    pub fn validate(&self, walls: WallsMode) -> Result<(), LevelError> {
+        if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&self.size) {
+            return Err(LevelError::Size(self.size));
+        }
+        let grid = Grid {
+            size: self.size,
+            walls,
+        };
+
+        let blocked = |pos: Position| !grid.contains(pos) || self.is_wall(pos);
+        let portal = self
+            .portals
+            .iter()
+            .find(|&&[a, b]| a == b || blocked(a) || blocked(b));
+        if let Some(&[pos, _]) = portal {
+            return Err(LevelError::PortalBlocked(pos));
+        }
+
+        let snake = self.snake();
+        let ahead = grid.advance(self.start, self.direction);
+        let ahead = self.portal_exit(ahead).unwrap_or(ahead);
+        let on_portal = snake.iter().any(|&pos| self.is_portal(pos));
+        if snake.iter().any(|&pos| blocked(pos))
+            || on_portal
+            || blocked(ahead)
+            || snake.contains(&ahead)
+        {
+            return Err(LevelError::StartBlocked);
+        }
+        let food = self
+            .food
+            .iter()
+            .find(|&&pos| blocked(pos) || self.is_portal(pos));
+        if let Some(&pos) = food {
+            return Err(LevelError::FoodBlocked(pos));
+        }
+
+        for hazard in &self.hazards {
+            let cells = hazard.cells();
+            if let Some(&pos) = cells
+                .iter()
+                .find(|&&pos| blocked(pos) || self.is_portal(pos))
+            {
+                return Err(LevelError::HazardBlocked(pos));
+            }
+            let start = hazard.position();
+            let too_close = match hazard {
+                Hazard::Hunter(pos) => {
+                    (pos.x - self.start.x).abs() + (pos.y - self.start.y).abs() < HUNTER_CLEARANCE
+                }
+                _ => cells.iter().any(|pos| snake.contains(pos) || *pos == ahead),
+            };
+            if too_close {
+                return Err(LevelError::HazardTooClose(start));
+            }
+        }
+
+        let reachable = self.reachable(&grid);
+        let unreachable = if self.food.is_empty() {
+            (0..self.size)
+                .flat_map(|y| (0..self.size).map(move |x| Position::new(x, y)))
+                .find(|&pos| {
+                    !self.is_wall(pos) && !self.is_portal(pos) && !reachable.contains(&pos)
+                })
+        } else {
+            self.food
+                .iter()
+                .copied()
+                .find(|pos| !reachable.contains(pos))
+        };
+        match unreachable {
+            Some(pos) => Err(LevelError::Unreachable(pos)),
+            None => Ok(()),
+        }
+    }
+
+    //  NOTE: a free spawn, or any free cell if they're all taken or there are none
+    pub fn food_position(
+        &self,
+        occupied: &HashSet<Position>,
+        rng: &mut impl Rng,
+    ) -> Option<Position> {
+        let free = |pos: &Position| {
+            !occupied.contains(pos) && !self.is_wall(*pos) && !self.is_portal(*pos)
+        };
+        self.food
+            .iter()
+            .copied()
+            .filter(free)
+            .choose(rng)
+            .or_else(|| {
+                (0..self.size)
+                    .flat_map(|y| (0..self.size).map(move |x| Position::new(x, y)))
+                    .filter(free)
+                    .choose(rng)
+            })
+    }
+
+    //  NOTE: flood fill from the head, the snake's own body moves out of the way. Portals are
+    //  passed through, the head never stops on the end it moves onto
+    fn reachable(&self, grid: &Grid) -> HashSet<Position> {
+        let mut seen = HashSet::from([self.start]);
+        let mut queue = VecDeque::from([self.start]);
+        while let Some(pos) = queue.pop_front() {
+            for direction in Direction::ALL {
+                let Some(next) = grid.step(pos, direction) else {
+                    continue;
+                };
+                let next = self.portal_exit(next).unwrap_or(next);
+                if !self.is_wall(next) && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        seen
+    }
+
+    pub fn load(name: &str) -> Result<Self, LevelError> {
+        let contents = storage::read(&file_name(name)?)?;
+        Ok(ron::from_str(&contents)?)
+    }
+
+    pub fn save(&self) -> Result<(), LevelError> {
+        let contents = ron::ser::to_string_pretty(self, Default::default())?;
+        storage::write(&file_name(&self.name)?, &contents)?;
+        Ok(())
+    }
+
+    //  NOTE: names of the saved levels, sorted
+    pub fn saved() -> Vec<String> {
+        let mut names = storage::list();
+        names.sort();
+        names
+    }
+}
+
+//  NOTE: names end up in file names, keep them to something every platform accepts
+fn file_name(name: &str) -> Result<String, LevelError> {
+    let valid = !name.trim().is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_'));
+    if valid {
+        Ok(name.trim().to_string())
+    } else {
+        Err(LevelError::Name)
+    }
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Serialize(ron::Error),
+    Name,
+    Size(i32),
+    StartBlocked,
+    FoodBlocked(Position),
+    PortalBlocked(Position),
+    HazardBlocked(Position),
+    HazardTooClose(Position),
+    Unreachable(Position),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read or write the level: {err}"),
+            Self::Ron(err) => write!(f, "could not parse the level: {err}"),
+            Self::Serialize(err) => write!(f, "could not write the level: {err}"),
+            Self::Name => write!(f, "level names take letters, digits, spaces, - and _"),
+            Self::Size(size) => write!(
+                f,
+                "the board must be {MIN_BOARD_SIZE} to {MAX_BOARD_SIZE} cells, not {size}"
+            ),
+            Self::StartBlocked => write!(f, "the snake's start is blocked"),
+            Self::FoodBlocked(pos) => {
+                write!(
+                    f,
+                    "the food at ({}, {}) is on a wall or portal",
+                    pos.x, pos.y
+                )
+            }
+            Self::PortalBlocked(pos) => {
+                write!(f, "the portal at ({}, {}) is blocked", pos.x, pos.y)
+            }
+            Self::HazardBlocked(pos) => {
+                write!(f, "the hazard at ({}, {}) runs into a wall", pos.x, pos.y)
+            }
+            Self::HazardTooClose(pos) => {
+                write!(
+                    f,
+                    "the hazard at ({}, {}) is too close to the start",
+                    pos.x, pos.y
+                )
+            }
+            Self::Unreachable(pos) => {
+                write!(f, "({}, {}) can't be reached from the start", pos.x, pos.y)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<std::io::Error> for LevelError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for LevelError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Ron(err)
+    }
+}
+
+impl From<ron::Error> for LevelError {
+    fn from(err: ron::Error) -> Self {
+        Self::Serialize(err)
+    }
+}
+
+//  NOTE: the level games are played on, `None` for the open board from the settings
+#[derive(Resource, Debug, Default)]
+pub struct CurrentLevel(pub Option<Level>);
+
+impl CurrentLevel {
+    pub fn is_wall(&self, pos: Position) -> bool {
+        self.0.as_ref().is_some_and(|level| level.is_wall(pos))
+    }
+
+    pub fn portal_exit(&self, pos: Position) -> Option<Position> {
+        self.0.as_ref().and_then(|level| level.portal_exit(pos))
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Wall;
+
+#[derive(Component, Debug)]
+pub struct Portal;
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentLevel>()
+            .add_systems(OnEnter(GameState::NewGame), spawn_level);
+    }
+}
+
+fn spawn_level(
+    mut commands: Commands,
+    level: Res<CurrentLevel>,
+    board: Res<Board>,
+    theme: Res<CurrentTheme>,
+) {
+    let Some(level) = &level.0 else {
+        return;
+    };
+    for &pos in &level.walls {
+        commands.spawn((wall_sprite(&board, &theme, pos), Wall, pos));
+    }
+    for (i, pair) in level.portals.iter().enumerate() {
+        for &pos in pair {
+            commands.spawn((portal_sprite(&board, i, pos), Portal, pos));
+        }
+    }
+}
+
+//  NOTE: both ends of a pair share a colour, the next pair's is well around the colour wheel
+pub fn portal_sprite(board: &Board, pair: usize, pos: Position) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::hsl((pair as f32 * 137.5) % 360.0, 0.8, 0.55),
+            custom_size: Some(Vec2::splat(TILE_SIZE * 0.8)),
+            ..default()
+        },
+        transform: Transform::from_xyz(
+            board.position_translate(pos.x),
+            board.position_translate(pos.y),
+            0.8,
+        ),
+        ..default()
+    }
+}
+
+//  NOTE: walls take the panel colour, it stands out from the tiles in every theme
+pub fn wall_sprite(board: &Board, theme: &CurrentTheme, pos: Position) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: theme.panel,
+            custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+            ..default()
+        },
+        transform: Transform::from_xyz(
+            board.position_translate(pos.x),
+            board.position_translate(pos.y),
+            1.0,
+        ),
+        ..default()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::{fs, io, path::PathBuf};
+
+    const LEVELS_DIR: &str = "levels";
+    const EXTENSION: &str = ".level.ron";
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(LEVELS_DIR).join(format!("{name}{EXTENSION}"))
+    }
+
+    pub fn read(name: &str) -> io::Result<String> {
+        fs::read_to_string(path(name))
+    }
+
+    pub fn write(name: &str, contents: &str) -> io::Result<()> {
+        fs::create_dir_all(LEVELS_DIR)?;
+        fs::write(path(name), contents)
+    }
+
+    pub fn list() -> Vec<String> {
+        let Ok(entries) = fs::read_dir(LEVELS_DIR) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| {
+                let file_name = entry.ok()?.file_name().into_string().ok()?;
+                file_name.strip_suffix(EXTENSION).map(str::to_string)
+            })
+            .collect()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use std::io;
+
+    const LEVEL_KEY: &str = "snake.level.";
+
+    fn local_storage() -> io::Result<web_sys::Storage> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "no local storage"))
+    }
+
+    pub fn read(name: &str) -> io::Result<String> {
+        local_storage()?
+            .get_item(&format!("{LEVEL_KEY}{name}"))
+            .ok()
+            .flatten()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such level"))
+    }
+
+    pub fn write(name: &str, contents: &str) -> io::Result<()> {
+        local_storage()?
+            .set_item(&format!("{LEVEL_KEY}{name}"), contents)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "local storage is full"))
+    }
+
+    pub fn list() -> Vec<String> {
+        let Ok(storage) = local_storage() else {
+            return Vec::new();
+        };
+        let count = storage.length().unwrap_or(0);
+        (0..count)
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter_map(|key| key.strip_prefix(LEVEL_KEY).map(str::to_string))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //  NOTE: the open board starts at (5, 10) heading right, the body trails to (3, 10)
+    fn level() -> Level {
+        Level::new("test", 20)
+    }
+
+    //  NOTE: walls on every side of the given cells
+    fn enclose(level: &mut Level, cells: &[Position]) {
+        for &pos in cells {
+            for (x, y) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let next = Position::new(pos.x + x, pos.y + y);
+                if !cells.contains(&next) {
+                    level.set_wall(next, true);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn open_boards_are_playable() {
+        assert!(level().validate(WallsMode::Solid).is_ok());
+        assert!(level().validate(WallsMode::Wrap).is_ok());
+    }
+
+    #[test]
+    fn starts_need_room_for_the_snake_and_its_first_move() {
+        for pos in [Position::new(6, 10), Position::new(4, 10)] {
+            let mut level = level();
+            level.set_wall(pos, true);
+            let result = level.validate(WallsMode::Solid);
+            assert!(matches!(result, Err(LevelError::StartBlocked)), "{pos:?}");
+        }
+
+        let mut level = level();
+        level.add_portal(Position::new(3, 10), Position::new(15, 15));
+        let result = level.validate(WallsMode::Solid);
+        assert!(matches!(result, Err(LevelError::StartBlocked)));
+    }
+
+    #[test]
+    fn food_can_not_spawn_on_walls_or_portals() {
+        let wall = Position::new(15, 5);
+        let mut level = level();
+        level.set_wall(wall, true);
+        level.food.push(wall);
+        let result = level.validate(WallsMode::Solid);
+        assert!(matches!(result, Err(LevelError::FoodBlocked(pos)) if pos == wall));
+
+        let portal = Position::new(15, 15);
+        let mut level = self::level();
+        level.add_portal(Position::new(15, 5), portal);
+        level.food.push(portal);
+        let result = level.validate(WallsMode::Solid);
+        assert!(matches!(result, Err(LevelError::FoodBlocked(pos)) if pos == portal));
+    }
+
+    #[test]
+    fn walled_in_food_is_unreachable() {
+        let food = Position::new(15, 15);
+        let mut level = level();
+        enclose(&mut level, &[food]);
+        level.set_food(food, true);
+        let result = level.validate(WallsMode::Solid);
+        assert!(matches!(result, Err(LevelError::Unreachable(pos)) if pos == food));
+    }
+
+    #[test]
+    fn portals_reach_into_walled_in_rooms() {
+        let (portal, food) = (Position::new(15, 15), Position::new(16, 15));
+        let mut level = level();
+        enclose(&mut level, &[portal, food]);
+        level.add_portal(Position::new(8, 5), portal);
+        level.set_food(food, true);
+        assert!(level.validate(WallsMode::Solid).is_ok());
+    }
+
+    #[test]
+    fn open_boards_need_every_free_cell_reachable() {
+        let mut level = level();
+        enclose(&mut level, &[Position::new(15, 15)]);
+        let result = level.validate(WallsMode::Solid);
+        assert!(
+            matches!(result, Err(LevelError::Unreachable(pos)) if pos == Position::new(15, 15))
+        );
+
+        //  NOTE: a wall across the board cuts it in two, unless the snake can wrap around it
+        let mut level = self::level();
+        for y in 0..level.size {
+            level.set_wall(Position::new(10, y), true);
+        }
+        let result = level.validate(WallsMode::Solid);
+        assert!(matches!(result, Err(LevelError::Unreachable(pos)) if pos == Position::new(11, 0)));
+        assert!(level.validate(WallsMode::Wrap).is_ok());
+    }
+}
@@ -0,0 +1,44 @@
+//  NOTE: the game modules live in the library so the headless tools in `examples/` and
+//  `src/bin/` can share them
+pub mod accessibility;
+pub mod arena;
+pub mod asset_loader;
+pub mod audio;
+pub mod autopilot;
+pub mod battle_royale;
+pub mod board;
+pub mod bot;
+pub mod camera;
+pub mod collision_detection;
+pub mod colors;
+pub mod death;
+pub mod despawn;
+pub mod dpad;
+pub mod editor;
+pub mod food;
+pub mod gym;
+pub mod hamiltonian;
+pub mod hazard;
+pub mod juice;
+pub mod level;
+pub mod loading;
+pub mod mode;
+pub mod neuro;
+pub mod online;
+pub mod pause;
+pub mod protocol;
+pub mod rollback;
+pub mod royale;
+pub mod schedule;
+pub mod score;
+pub mod settings;
+pub mod sim;
+pub mod snake;
+pub mod spectate;
+pub mod sprite_pack;
+pub mod state;
+pub mod stream;
+pub mod theme;
+pub mod ui;
+pub mod util;
+pub mod versus;
@@ -0,0 +1,122 @@
+use bevy::{asset::LoadState, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    asset_loader::REQUIRED_ASSETS,
+    state::AppState,
+    theme::{color32, CurrentTheme},
+};
+
+pub struct LoadingScreenPlugin;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_loading_screen.run_if(in_state(AppState::Loading)),
+        )
+        .add_systems(
+            Update,
+            update_load_failed_screen.run_if(in_state(AppState::LoadFailed)),
+        );
+    }
+}
+
+fn loading_progress(asset_server: &AssetServer) -> f32 {
+    let loaded = REQUIRED_ASSETS
+        .iter()
+        .filter_map(|path| asset_server.get_path_id(*path))
+        .filter(|id| asset_server.get_load_state(*id) == Some(LoadState::Loaded))
+        .count();
+    loaded as f32 / REQUIRED_ASSETS.len() as f32
+}
+
+fn update_loading_screen(
+    mut contexts: EguiContexts,
+    asset_server: Res<AssetServer>,
+    theme: Res<CurrentTheme>,
+) {
+    let progress = loading_progress(&asset_server);
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none().fill(color32(theme.background)))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 2.0 - 40.0);
+                ui.label(
+                    egui::RichText::new("Loading")
+                        .color(color32(theme.text))
+                        .font(egui::FontId::monospace(24.0)),
+                );
+                ui.add_space(10.0);
+                ui.add(
+                    egui::ProgressBar::new(progress)
+                        .desired_width(240.0)
+                        .fill(color32(theme.button))
+                        .show_percentage(),
+                );
+            });
+        });
+}
+
+fn update_load_failed_screen(
+    mut contexts: EguiContexts,
+    asset_server: Res<AssetServer>,
+    theme: Res<CurrentTheme>,
+) {
+    let failed: Vec<&str> = REQUIRED_ASSETS
+        .iter()
+        .copied()
+        .filter(|path| {
+            asset_server
+                .get_path_id(*path)
+                .is_some_and(|id| asset_server.get_load_state(id) == Some(LoadState::Failed))
+        })
+        .collect();
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none().fill(color32(theme.background)))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 2.0 - 80.0);
+                ui.label(
+                    egui::RichText::new("Could not load the game")
+                        .color(color32(theme.text))
+                        .font(egui::FontId::monospace(24.0)),
+                );
+                ui.add_space(10.0);
+                ui.label(
+                    egui::RichText::new("Check your connection and try again.")
+                        .color(color32(theme.text)),
+                );
+                for path in failed {
+                    ui.label(
+                        egui::RichText::new(format!("missing: {path}"))
+                            .color(color32(theme.text))
+                            .small(),
+                    );
+                }
+
+                #[cfg(target_arch = "wasm32")]
+                {
+                    ui.add_space(10.0);
+                    if ui
+                        .add(
+                            egui::Button::new(
+                                egui::RichText::new("Reload")
+                                    .color(color32(theme.text))
+                                    .font(egui::FontId::monospace(20.0)),
+                            )
+                            .rounding(8.0)
+                            .fill(color32(theme.button)),
+                        )
+                        .clicked()
+                    {
+                        if let Some(window) = web_sys::window() {
+                            let _ = window.location().reload();
+                        }
+                    }
+                }
+            });
+        });
+}
@@ -1,34 +1,20 @@
-mod asset_loader;
-mod board;
-mod camera;
-mod collision_detection;
-mod colors;
-mod despawn;
-mod food;
-mod schedule;
-mod score;
-mod snake;
-mod state;
-mod ui;
-mod util;
-
-use asset_loader::AssetLoaderPlugin;
 use bevy::{asset::AssetMetaCheck, prelude::*};
-use board::BoardPlugin;
-use camera::CameraPlugin;
-use collision_detection::CollisionDetectionPlugin;
-use despawn::DespawnPlugin;
-use food::FoodPlugin;
-use schedule::SchedulePlugin;
-use score::ScorePlugin;
-use snake::SnakePlugin;
-use state::StatePlugin;
-use ui::GameUiPlugin;
+use snake::{
+    accessibility::AccessibilityPlugin, asset_loader::AssetLoaderPlugin, audio::GameAudioPlugin,
+    autopilot::AutopilotPlugin, battle_royale::BattleRoyalePlugin, board::BoardPlugin,
+    camera::CameraPlugin, collision_detection::CollisionDetectionPlugin, colors::COLORS,
+    death::DeathPlugin, despawn::DespawnPlugin, dpad::DpadPlugin, editor::EditorPlugin,
+    food::FoodPlugin, hazard::HazardPlugin, juice::JuicePlugin, level::LevelPlugin,
+    loading::LoadingScreenPlugin, mode::GameModePlugin, online::OnlinePlugin, pause::PausePlugin,
+    schedule::SchedulePlugin, score::ScorePlugin, settings::SettingsPlugin, snake::SnakePlugin,
+    spectate::SpectatePlugin, sprite_pack::SpritePackPlugin, state::StatePlugin,
+    theme::ThemePlugin, ui::GameUiPlugin,
+};
 
 fn main() {
     App::new()
         .insert_resource(AssetMetaCheck::Never)
-        .insert_resource(ClearColor(Color::hex("#578a34").unwrap()))
+        .insert_resource(ClearColor(COLORS.background))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 canvas: Some("#snake-canvas".into()),
@@ -37,16 +23,34 @@ fn main() {
             }),
             ..Default::default()
         }))
+        .add_plugins(StatePlugin)
+        .add_plugins(SettingsPlugin)
+        .add_plugins(ThemePlugin)
         .add_plugins(GameUiPlugin)
+        .add_plugins(DpadPlugin)
+        .add_plugins(PausePlugin)
         .add_plugins(CameraPlugin)
         .add_plugins(AssetLoaderPlugin)
+        .add_plugins(LoadingScreenPlugin)
+        .add_plugins(SpritePackPlugin)
         .add_plugins(BoardPlugin)
+        .add_plugins(LevelPlugin)
+        .add_plugins(HazardPlugin)
+        .add_plugins(EditorPlugin)
         .add_plugins(ScorePlugin)
         .add_plugins(FoodPlugin)
+        .add_plugins(GameModePlugin)
         .add_plugins(SnakePlugin)
+        .add_plugins(AutopilotPlugin)
+        .add_plugins(OnlinePlugin)
+        .add_plugins(SpectatePlugin)
+        .add_plugins(BattleRoyalePlugin)
         .add_plugins(CollisionDetectionPlugin)
+        .add_plugins(DeathPlugin)
+        .add_plugins(AccessibilityPlugin)
+        .add_plugins(GameAudioPlugin)
+        .add_plugins(JuicePlugin)
         .add_plugins(DespawnPlugin)
         .add_plugins(SchedulePlugin)
-        .add_plugins(StatePlugin)
         .run();
 }
@@ -0,0 +1,146 @@
+use std::{collections::HashSet, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    battle_royale::BattleRoyale,
+    board::Board,
+    food::{Food, FoodEvent},
+    level::CurrentLevel,
+    online::Online,
+    schedule::InGameSet,
+    settings::{GameMode, Settings},
+    sim::place_food,
+    snake::{Position, SnakeSegment},
+    spectate::Spectator,
+    state::GameState,
+};
+
+//  NOTE: what sets the game modes apart from classic games. Time attack games end when the clock
+//  runs out, survival games when the snake goes too long without eating, and zen games never do,
+//  see `collision_detection`. Every mode keeps its own high score in `Score`
+
+//  NOTE: seconds a time attack game lasts
+pub const TIME_ATTACK_LENGTH: f32 = 60.0;
+//  NOTE: seconds the snake lasts without eating in survival
+pub const STARVE_AFTER: f32 = 10.0;
+//  NOTE: seconds food lasts in survival before it goes off and turns up somewhere else
+pub const FOOD_LIFETIME: f32 = 6.0;
+//  NOTE: how see-through food is just before it goes off
+const STALE_FOOD_ALPHA: f32 = 0.3;
+
+//  NOTE: the time attack clock or the survival hunger, shown in the top bar. `None` in the other
+//  modes and in games the local rules don't run
+#[derive(Resource, Debug, Default)]
+pub struct ModeClock(Option<Timer>);
+
+impl ModeClock {
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0.as_ref().map(Timer::remaining)
+    }
+}
+
+pub struct GameModePlugin;
+
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ModeClock>()
+            //  NOTE: once to show a full clock while waiting, and again when the game starts in
+            //  case another mode was picked in the meantime
+            .add_systems(OnEnter(GameState::NewGame), start_clock)
+            .add_systems(OnExit(GameState::NewGame), start_clock)
+            .add_systems(
+                Update,
+                (tick_clock, expire_food).in_set(InGameSet::EntityUpdates),
+            );
+    }
+}
+
+fn start_clock(
+    mut clock: ResMut<ModeClock>,
+    settings: Res<Settings>,
+    online: Res<Online>,
+    spectator: Res<Spectator>,
+    royale: Res<BattleRoyale>,
+) {
+    let local = !online.in_match() && !spectator.watching() && !royale.in_match();
+    let seconds = match settings.gameplay.mode {
+        GameMode::TimeAttack if local => Some(TIME_ATTACK_LENGTH),
+        GameMode::Survival if local => Some(STARVE_AFTER),
+        _ => None,
+    };
+    clock.0 = seconds.map(|seconds| Timer::from_seconds(seconds, TimerMode::Once));
+}
+
+fn tick_clock(
+    mut clock: ResMut<ModeClock>,
+    mut food_event_reader: EventReader<FoodEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+) {
+    let Some(timer) = &mut clock.0 else {
+        return;
+    };
+
+    let mode = settings.gameplay.mode;
+    //  NOTE: eating fills the snake back up
+    if food_event_reader.read().count() > 0 && mode == GameMode::Survival {
+        timer.reset();
+    }
+
+    timer.tick(time.delta());
+    if timer.just_finished() {
+        match mode {
+            //  NOTE: time's up, the score counts like any other game
+            GameMode::TimeAttack => next_state.set(GameState::GameOver),
+            //  NOTE: starving, the snake dies like it crashed
+            GameMode::Survival => next_state.set(GameState::Dying),
+            _ => {}
+        }
+    }
+}
+
+//  NOTE: food fades as it ages and moves somewhere else once it goes off. Freshly spawned food
+//  starts the clock again
+#[allow(clippy::too_many_arguments)]
+fn expire_food(
+    mut food_query: Query<(&mut Position, &mut Transform, &mut Sprite), With<Food>>,
+    added_query: Query<(), Added<Food>>,
+    snake_query: Query<&Position, (With<SnakeSegment>, Without<Food>)>,
+    board: Res<Board>,
+    level: Res<CurrentLevel>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut age: Local<Duration>,
+) {
+    if settings.gameplay.mode != GameMode::Survival {
+        return;
+    }
+    let Ok((mut pos, mut transform, mut sprite)) = food_query.get_single_mut() else {
+        return;
+    };
+
+    let lifetime = Duration::from_secs_f32(FOOD_LIFETIME);
+    *age = if added_query.is_empty() {
+        *age + time.delta()
+    } else {
+        Duration::ZERO
+    };
+    if *age >= lifetime {
+        let occupied: HashSet<Position> = snake_query.iter().chain([&*pos]).copied().collect();
+        let grid = board.grid(settings.gameplay.walls());
+        let fresh = place_food(&grid, level.0.as_ref(), &occupied, &mut rand::thread_rng());
+        if let Some(fresh) = fresh {
+            *pos = fresh;
+            transform.translation.x = board.position_translate(fresh.x);
+            transform.translation.y = board.position_translate(fresh.y);
+        }
+        *age = Duration::ZERO;
+    }
+
+    let fresh = 1.0 - age.as_secs_f32() / lifetime.as_secs_f32();
+    sprite
+        .color
+        .set_a(STALE_FOOD_ALPHA + (1.0 - STALE_FOOD_ALPHA) * fresh);
+}
@@ -0,0 +1,509 @@
+use std::{collections::HashSet, fmt, num::NonZeroUsize, path::Path, thread};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    settings::WallsMode,
+    sim::{Game, Grid, Rules},
+    snake::{Direction, Position},
+};
+
+//  NOTE: where `snake-evolve` saves the best genome and the "Neural network" AI looks for it
+pub const GENOME_FILE: &str = "genome.ron";
+
+//  NOTE: the eight rays the snake looks along, straight and diagonal
+const RAYS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+//  NOTE: wall, body and food for every ray plus the heading one hot as in `Direction::ALL`
+pub const INPUTS: usize = RAYS.len() * 3 + 4;
+pub const OUTPUTS: usize = Direction::ALL.len();
+pub const DEFAULT_HIDDEN: [usize; 2] = [16, 16];
+
+//  NOTE: what the network sees, along each ray the inverse distance to the wall (0 when the walls
+//  wrap), the inverse distance to the closest body segment (0 when there is none) and whether the
+//  food is on it. On levels `obstacles`, see `Game::obstacles`, count as walls
+pub fn vision(
+    grid: &Grid,
+    snake: &[Position],
+    food: &[Position],
+    obstacles: &HashSet<Position>,
+    heading: Direction,
+) -> Vec<f32> {
+    let mut inputs = Vec::with_capacity(INPUTS);
+    let Some(&head) = snake.first() else {
+        return vec![0.0; INPUTS];
+    };
+
+    for (dx, dy) in RAYS {
+        let (mut wall, mut body, mut seen_food) = (0.0, 0.0, 0.0);
+        for distance in 1..=grid.size {
+            let mut pos = Position::new(head.x + dx * distance, head.y + dy * distance);
+            if !grid.contains(pos) {
+                match grid.walls {
+                    WallsMode::Solid => {
+                        wall = 1.0 / distance as f32;
+                        break;
+                    }
+                    WallsMode::Wrap => {
+                        pos =
+                            Position::new(pos.x.rem_euclid(grid.size), pos.y.rem_euclid(grid.size));
+                    }
+                }
+            }
+            if pos == head {
+                break;
+            }
+            if obstacles.contains(&pos) {
+                wall = 1.0 / distance as f32;
+                break;
+            }
+            if body == 0.0 && snake[1..].contains(&pos) {
+                body = 1.0 / distance as f32;
+            }
+            if food.contains(&pos) {
+                seen_food = 1.0;
+            }
+        }
+        inputs.extend([wall, body, seen_food]);
+    }
+    inputs.extend(Direction::ALL.map(|direction| if direction == heading { 1.0 } else { 0.0 }));
+    inputs
+}
+
+//  NOTE: a small feed-forward net, the genome the trainer evolves. `weights` holds every layer one
+//  after the other, each output neuron's weights followed by its bias
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Network {
+    layers: Vec<usize>,
+    weights: Vec<f32>,
+}
+
+impl Network {
+    pub fn random(hidden: &[usize], rng: &mut impl Rng) -> Self {
+        let layers: Vec<usize> = std::iter::once(INPUTS)
+            .chain(hidden.iter().copied())
+            .chain(std::iter::once(OUTPUTS))
+            .collect();
+        let weights = (0..weight_count(&layers))
+            .map(|_| rng.gen_range(-1.0..1.0))
+            .collect();
+        Self { layers, weights }
+    }
+
+    pub fn layers(&self) -> &[usize] {
+        &self.layers
+    }
+
+    pub fn forward(&self, inputs: &[f32]) -> Vec<f32> {
+        let mut activations = inputs.to_vec();
+        let mut weights = self.weights.as_slice();
+        let last = self.layers.len() - 2;
+
+        for (layer, pair) in self.layers.windows(2).enumerate() {
+            let (fan_in, fan_out) = (pair[0], pair[1]);
+            let (layer_weights, rest) = weights.split_at((fan_in + 1) * fan_out);
+            weights = rest;
+
+            activations = layer_weights
+                .chunks(fan_in + 1)
+                .map(|neuron| {
+                    let sum = neuron[fan_in]
+                        + neuron[..fan_in]
+                            .iter()
+                            .zip(&activations)
+                            .map(|(weight, input)| weight * input)
+                            .sum::<f32>();
+                    //  NOTE: relu on the hidden layers, the outputs are only compared
+                    if layer == last {
+                        sum
+                    } else {
+                        sum.max(0.0)
+                    }
+                })
+                .collect();
+        }
+        activations
+    }
+
+    //  NOTE: the strongest output, turning back into the neck is never picked
+    pub fn decide(&self, inputs: &[f32], heading: Direction) -> Direction {
+        let outputs = self.forward(inputs);
+        Direction::ALL
+            .into_iter()
+            .zip(outputs)
+            .filter(|&(direction, _)| direction != heading.opposite())
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(heading, |(direction, _)| direction)
+    }
+
+    pub fn plan_direction(
+        &self,
+        grid: &Grid,
+        snake: &[Position],
+        food: &[Position],
+        obstacles: &HashSet<Position>,
+        heading: Direction,
+    ) -> Direction {
+        self.decide(&vision(grid, snake, food, obstacles, heading), heading)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GenomeError> {
+        let contents = std::fs::read_to_string(path)?;
+        let network: Network = ron::from_str(&contents)?;
+
+        let shape_ok = network.layers.len() >= 2
+            && network.layers.first() == Some(&INPUTS)
+            && network.layers.last() == Some(&OUTPUTS)
+            && network.weights.len() == weight_count(&network.layers);
+        if !shape_ok {
+            return Err(GenomeError::Shape {
+                layers: network.layers,
+                weights: network.weights.len(),
+            });
+        }
+        Ok(network)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GenomeError> {
+        let contents = ron::ser::to_string_pretty(self, Default::default())?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    //  NOTE: each weight picked from either parent
+    fn crossover(&self, other: &Network, rng: &mut impl Rng) -> Network {
+        Network {
+            layers: self.layers.clone(),
+            weights: self
+                .weights
+                .iter()
+                .zip(&other.weights)
+                .map(|(&a, &b)| if rng.gen_bool(0.5) { a } else { b })
+                .collect(),
+        }
+    }
+
+    fn mutate(&mut self, rate: f64, strength: f32, rng: &mut impl Rng) {
+        for weight in &mut self.weights {
+            if rng.gen_bool(rate) {
+                *weight += rng.gen_range(-strength..strength);
+            }
+        }
+    }
+}
+
+fn weight_count(layers: &[usize]) -> usize {
+    layers.windows(2).map(|pair| (pair[0] + 1) * pair[1]).sum()
+}
+
+#[derive(Debug)]
+pub enum GenomeError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Serialize(ron::Error),
+    Shape { layers: Vec<usize>, weights: usize },
+}
+
+impl fmt::Display for GenomeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access genome: {err}"),
+            Self::Ron(err) => write!(f, "could not parse genome: {err}"),
+            Self::Serialize(err) => write!(f, "could not serialize genome: {err}"),
+            Self::Shape { layers, weights } => write!(
+                f,
+                "genome with layers {layers:?} and {weights} weights doesn't fit \
+                 {INPUTS} inputs and {OUTPUTS} outputs"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GenomeError {}
+
+impl From<std::io::Error> for GenomeError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for GenomeError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Ron(err)
+    }
+}
+
+impl From<ron::Error> for GenomeError {
+    fn from(err: ron::Error) -> Self {
+        Self::Serialize(err)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrainerConfig {
+    pub rules: Rules,
+    pub hidden: Vec<usize>,
+    pub population: usize,
+    //  NOTE: games every genome plays per generation, all genomes get the same seeds
+    pub games: usize,
+    //  NOTE: best genomes copied over unchanged
+    pub elite: usize,
+    pub tournament: usize,
+    pub mutation_rate: f64,
+    pub mutation_strength: f32,
+}
+
+impl Default for TrainerConfig {
+    fn default() -> Self {
+        Self {
+            rules: Rules::default(),
+            hidden: DEFAULT_HIDDEN.to_vec(),
+            population: 200,
+            games: 3,
+            elite: 10,
+            tournament: 5,
+            mutation_rate: 0.05,
+            mutation_strength: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerationStats {
+    pub generation: u32,
+    pub best_fitness: f64,
+    pub mean_fitness: f64,
+    //  NOTE: mean score of the generation's best genome over its games
+    pub best_score: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Evaluation {
+    fitness: f64,
+    score: f64,
+}
+
+//  NOTE: genetic algorithm over `Network`s, every generation is played headlessly on all cores
+pub struct Trainer {
+    config: TrainerConfig,
+    population: Vec<Network>,
+    rng: StdRng,
+    generation: u32,
+    best: Option<(Network, f64)>,
+}
+
+impl Trainer {
+    pub fn new(config: TrainerConfig, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let population = (0..config.population.max(2))
+            .map(|_| Network::random(&config.hidden, &mut rng))
+            .collect();
+        Self {
+            config,
+            population,
+            rng,
+            generation: 0,
+            best: None,
+        }
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    //  NOTE: the fittest genome seen in any generation so far
+    pub fn best(&self) -> Option<&Network> {
+        self.best.as_ref().map(|(network, _)| network)
+    }
+
+    pub fn evolve(&mut self) -> GenerationStats {
+        let evaluations = self.evaluate();
+        let mut ranked: Vec<usize> = (0..self.population.len()).collect();
+        ranked.sort_by(|&a, &b| evaluations[b].fitness.total_cmp(&evaluations[a].fitness));
+
+        let champion = ranked[0];
+        let stats = GenerationStats {
+            generation: self.generation,
+            best_fitness: evaluations[champion].fitness,
+            mean_fitness: evaluations.iter().map(|e| e.fitness).sum::<f64>()
+                / evaluations.len() as f64,
+            best_score: evaluations[champion].score,
+        };
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|(_, fitness)| stats.best_fitness > *fitness)
+        {
+            self.best = Some((self.population[champion].clone(), stats.best_fitness));
+        }
+
+        let mut next: Vec<Network> = ranked
+            .iter()
+            .take(self.config.elite.min(self.population.len()))
+            .map(|&index| self.population[index].clone())
+            .collect();
+        while next.len() < self.population.len() {
+            let a = self.select(&evaluations);
+            let b = self.select(&evaluations);
+            let mut child = self.population[a].crossover(&self.population[b], &mut self.rng);
+            child.mutate(
+                self.config.mutation_rate,
+                self.config.mutation_strength,
+                &mut self.rng,
+            );
+            next.push(child);
+        }
+
+        self.population = next;
+        self.generation += 1;
+        stats
+    }
+
+    fn select(&mut self, evaluations: &[Evaluation]) -> usize {
+        (0..self.config.tournament.max(1))
+            .map(|_| self.rng.gen_range(0..evaluations.len()))
+            .max_by(|&a, &b| evaluations[a].fitness.total_cmp(&evaluations[b].fitness))
+            .unwrap()
+    }
+
+    fn evaluate(&self) -> Vec<Evaluation> {
+        let games = self.config.games.max(1);
+        let first_seed = self.generation as u64 * games as u64;
+        let seeds: Vec<u64> = (first_seed..first_seed + games as u64).collect();
+        let rules = &self.config.rules;
+
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk = self.population.len().div_ceil(threads).max(1);
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .population
+                .chunks(chunk)
+                .map(|networks| {
+                    let seeds = &seeds;
+                    scope.spawn(move || {
+                        networks
+                            .iter()
+                            .map(|network| evaluate(network, rules, seeds))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+}
+
+//  NOTE: food is what counts, surviving only breaks ties and is capped below the worth of one
+//  food so circling until starvation doesn't pay
+fn evaluate(network: &Network, rules: &Rules, seeds: &[u64]) -> Evaluation {
+    let cells = rules.grid.cells() as f64;
+    let (mut fitness, mut score) = (0.0, 0.0);
+
+    for &seed in seeds {
+        let game = play(network, rules, seed);
+        fitness += game.score() as f64 * 100.0 + (game.moves() as f64).min(cells) / cells * 50.0;
+        score += game.score() as f64;
+    }
+
+    Evaluation {
+        fitness: fitness / seeds.len() as f64,
+        score: score / seeds.len() as f64,
+    }
+}
+
+pub fn play(network: &Network, rules: &Rules, seed: u64) -> Game {
+    let mut game = Game::new(rules.clone(), seed);
+    while game.outcome().is_none() {
+        let snake: Vec<Position> = game.snake().iter().copied().collect();
+        let food: Vec<Position> = game.food().into_iter().collect();
+        let obstacles = game.obstacles();
+        game.step(network.plan_direction(game.grid(), &snake, &food, &obstacles, game.direction()));
+    }
+    game
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    //  NOTE: one file per test, they run in parallel
+    fn genome_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("snake-{}-{name}.ron", std::process::id()))
+    }
+
+    #[test]
+    fn saved_genomes_load_back() {
+        let network = Network::random(&DEFAULT_HIDDEN, &mut StdRng::seed_from_u64(0));
+        let path = genome_path("round-trip");
+        network.save(&path).unwrap();
+        let loaded = Network::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), network);
+    }
+
+    #[test]
+    fn genomes_of_the_wrong_shape_are_rejected() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut wrong_inputs = Network::random(&[8], &mut rng);
+        wrong_inputs.layers[0] = INPUTS + 1;
+        let mut missing_weights = Network::random(&[8], &mut rng);
+        missing_weights.weights.pop();
+
+        for (name, network) in [("inputs", wrong_inputs), ("weights", missing_weights)] {
+            let path = genome_path(name);
+            network.save(&path).unwrap();
+            let loaded = Network::load(&path);
+            std::fs::remove_file(&path).unwrap();
+
+            assert!(
+                matches!(loaded, Err(GenomeError::Shape { .. })),
+                "{name}: {loaded:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn never_turns_back_into_the_neck() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..50 {
+            let network = Network::random(&DEFAULT_HIDDEN, &mut rng);
+            let inputs: Vec<f32> = (0..INPUTS).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            for heading in Direction::ALL {
+                assert_ne!(network.decide(&inputs, heading), heading.opposite());
+            }
+        }
+    }
+
+    #[test]
+    fn evolution_is_deterministic_for_a_seed() {
+        let config = TrainerConfig {
+            population: 20,
+            games: 2,
+            elite: 2,
+            ..TrainerConfig::default()
+        };
+        let run = || {
+            let mut trainer = Trainer::new(config.clone(), 7);
+            let stats: Vec<GenerationStats> = (0..3).map(|_| trainer.evolve()).collect();
+            (stats, trainer.best().cloned())
+        };
+
+        assert_eq!(run(), run());
+    }
+}
@@ -0,0 +1,704 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    asset_loader::SpritePart,
+    autopilot::Autopilot,
+    board::{Board, TILE_SIZE},
+    protocol::{
+        ClientMessage, EndReason, MatchMode, MatchRules, PeerEvent, ServerMessage, MAX_INPUT_DELAY,
+    },
+    rollback::{BufferedTransport, RollbackSession},
+    schedule::SteerSet,
+    settings::Settings,
+    sim::Grid,
+    snake::{segment_part, Direction, Position, SteerEvent},
+    sprite_pack::CurrentSpritePack,
+    state::{GameState, MenuState},
+    theme::{color32, CurrentTheme},
+    versus::{VersusGame, VersusOutcome, VersusState},
+};
+
+use transport::Connection;
+
+//  NOTE: how often the latency is measured
+const PING_INTERVAL: f64 = 1.0;
+//  NOTE: the opponent is tinted so both snakes can share the sprite sheet
+const OPPONENT_TINT: Color = Color::rgb(0.55, 0.7, 1.0);
+//  NOTE: rollback matches have no server to hold the first move back, same wait as `snake-server`
+const ROLLBACK_START_DELAY: f64 = 2.0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OnlineStatus {
+    #[default]
+    Offline,
+    //  NOTE: the menu's "Play online" was pressed, opening the socket
+    Connecting,
+    //  NOTE: connected, nobody to play against yet
+    Waiting,
+    //  NOTE: from the server's `Start` until the next `NewGame`, local game systems are off
+    Playing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchResult {
+    Won,
+    Lost,
+    Draw,
+    OpponentLeft,
+    Disconnected,
+    Unreachable,
+    Desync,
+}
+
+impl MatchResult {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Won => "You won!",
+            Self::Lost => "You lost",
+            Self::Draw => "Draw",
+            Self::OpponentLeft => "Your opponent left, you win",
+            Self::Disconnected => "Connection lost",
+            Self::Unreachable => "Could not reach the server",
+            Self::Desync => "Out of sync with your opponent",
+        }
+    }
+
+    fn of(winner: Option<usize>, player: usize) -> Self {
+        match winner {
+            Some(winner) if winner == player => Self::Won,
+            Some(_) => Self::Lost,
+            None => Self::Draw,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Online {
+    status: OnlineStatus,
+    //  NOTE: the outcome of the last match, shown in the menu
+    last_result: Option<MatchResult>,
+    player: usize,
+    rules: Option<MatchRules>,
+    state: Option<VersusState>,
+    //  NOTE: round trip to the server in seconds
+    rtt: Option<f64>,
+    //  NOTE: the last turn sent and the tick it was scheduled for
+    last_turn: Option<(u32, Direction)>,
+    //  NOTE: rollback matches run here, `state` mirrors the session's
+    session: Option<RollbackSession<BufferedTransport>>,
+    //  NOTE: seconds not yet played as frames, starts negative to wait `ROLLBACK_START_DELAY`
+    clock: f64,
+}
+
+impl Online {
+    pub fn status(&self) -> OnlineStatus {
+        self.status
+    }
+
+    pub fn last_result(&self) -> Option<MatchResult> {
+        self.last_result
+    }
+
+    pub fn connect(&mut self) {
+        self.status = OnlineStatus::Connecting;
+        self.last_result = None;
+    }
+
+    pub fn cancel(&mut self) {
+        if self.searching() {
+            self.status = OnlineStatus::Offline;
+        }
+    }
+
+    pub fn searching(&self) -> bool {
+        matches!(
+            self.status,
+            OnlineStatus::Connecting | OnlineStatus::Waiting
+        )
+    }
+
+    pub fn in_match(&self) -> bool {
+        self.status == OnlineStatus::Playing
+    }
+
+    fn finish(&mut self, result: MatchResult) {
+        self.last_result = Some(result);
+        self.rtt = None;
+        self.last_turn = None;
+        self.session = None;
+    }
+}
+
+pub fn in_online_match(online: Res<Online>) -> bool {
+    online.in_match()
+}
+
+//  NOTE: the socket is `!Send` in the browser so it lives in a non-send resource
+#[derive(Default)]
+struct OnlineConnection(Option<Connection>);
+
+#[derive(Component, Debug)]
+struct OnlineSprite;
+
+pub struct OnlinePlugin;
+
+impl Plugin for OnlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Online>()
+            .insert_non_send_resource(OnlineConnection::default())
+            .add_systems(OnEnter(GameState::NewGame), leave_finished_match)
+            .add_systems(
+                Update,
+                (
+                    open_connection,
+                    receive_messages,
+                    advance_rollback.run_if(in_online_match),
+                    send_pings,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                online_controls
+                    .after(receive_messages)
+                    .after(SteerSet)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_online_match),
+            )
+            .add_systems(
+                Update,
+                (render_online_match, update_online_hud)
+                    .after(receive_messages)
+                    .run_if(in_online_match),
+            );
+    }
+}
+
+fn leave_finished_match(mut online: ResMut<Online>) {
+    if online.in_match() {
+        online.status = OnlineStatus::Offline;
+        online.state = None;
+    }
+}
+
+fn open_connection(
+    online: Res<Online>,
+    settings: Res<Settings>,
+    board: Res<Board>,
+    mut connection: NonSendMut<OnlineConnection>,
+) {
+    match (online.searching(), connection.0.is_some()) {
+        (true, false) => {
+            let socket = Connection::open(&settings.gameplay.server);
+            //  NOTE: the board that's already up, the server pairs players asking for the same one
+            socket.send(ClientMessage::Hello(MatchRules {
+                board_size: board.size,
+                walls: settings.gameplay.walls,
+                speed: settings.gameplay.speed,
+            }));
+            connection.0 = Some(socket);
+        }
+        //  NOTE: cancelled from the menu
+        (false, true) if !online.in_match() => connection.0 = None,
+        _ => (),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn receive_messages(
+    mut commands: Commands,
+    mut online: ResMut<Online>,
+    mut connection: NonSendMut<OnlineConnection>,
+    mut autopilot: ResMut<Autopilot>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    time: Res<Time<Real>>,
+    local_query: Query<Entity, (With<Position>, Without<OnlineSprite>)>,
+) {
+    let Some(socket) = &connection.0 else {
+        return;
+    };
+
+    for event in socket.poll() {
+        match event {
+            PeerEvent::Message(ServerMessage::Waiting) => online.status = OnlineStatus::Waiting,
+            PeerEvent::Message(ServerMessage::Start {
+                player,
+                rules,
+                seed,
+                mode,
+            }) => {
+                online.status = OnlineStatus::Playing;
+                online.player = player;
+                online.rules = Some(rules);
+                online.state = None;
+                online.last_turn = None;
+                online.session = None;
+                if mode == MatchMode::Rollback {
+                    let grid = Grid {
+                        size: rules.board_size,
+                        walls: rules.walls,
+                    };
+                    let game = VersusGame::new(grid, 2, seed);
+                    let session = RollbackSession::new(game, player, BufferedTransport::default());
+                    online.state = Some(session.state().clone());
+                    online.session = Some(session);
+                    online.clock = -ROLLBACK_START_DELAY;
+                }
+
+                //  NOTE: clear the local board, it comes back with the next `NewGame`
+                for entity in local_query.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                autopilot.enabled = false;
+                next_menu_state.set(MenuState::Off);
+                next_state.set(GameState::Playing);
+            }
+            PeerEvent::Message(ServerMessage::Tick(state)) => online.state = Some(state),
+            PeerEvent::Message(ServerMessage::Relay(message)) => {
+                if let Some(session) = &mut online.session {
+                    session.transport_mut().incoming.push(message);
+                }
+            }
+            PeerEvent::Message(ServerMessage::Pong { sent }) => {
+                online.rtt = Some(time.elapsed_seconds_f64() - sent);
+            }
+            //  NOTE: only spectators get these, see `spectate.rs`
+            PeerEvent::Message(ServerMessage::Stream(_) | ServerMessage::StreamEnded) => {}
+            PeerEvent::Message(ServerMessage::GameOver { winner, reason }) => {
+                //  NOTE: a rollback peer leaves once it knows the outcome, its last inputs came
+                //  in right before this
+                let settled = online.session.as_mut().and_then(|session| session.settle());
+                let result = match (reason, settled) {
+                    (_, Some(VersusOutcome::Over { winner })) => {
+                        MatchResult::of(winner, online.player)
+                    }
+                    (EndReason::OpponentLeft, _) => MatchResult::OpponentLeft,
+                    (EndReason::Finished, _) => MatchResult::of(winner, online.player),
+                };
+                online.finish(result);
+                next_state.set(GameState::GameOver);
+                connection.0 = None;
+                return;
+            }
+            PeerEvent::Closed(reason) => {
+                warn!("online connection closed: {reason}");
+                if online.in_match() {
+                    online.finish(MatchResult::Disconnected);
+                    next_state.set(GameState::GameOver);
+                } else {
+                    let result = match online.status {
+                        OnlineStatus::Connecting => MatchResult::Unreachable,
+                        _ => MatchResult::Disconnected,
+                    };
+                    online.status = OnlineStatus::Offline;
+                    online.finish(result);
+                }
+                connection.0 = None;
+                return;
+            }
+        }
+    }
+}
+
+//  NOTE: real time, the opponent keeps playing whatever happens to this window
+fn advance_rollback(
+    mut online: ResMut<Online>,
+    mut connection: NonSendMut<OnlineConnection>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time<Real>>,
+) {
+    let (Some(socket), Some(rules)) = (&connection.0, online.rules) else {
+        return;
+    };
+    let step = rules.speed.step().as_secs_f64();
+
+    //  NOTE: only redraw when a frame was played
+    let online_ref = online.bypass_change_detection();
+    let Some(session) = &mut online_ref.session else {
+        return;
+    };
+    online_ref.clock += time.delta_seconds_f64();
+    let mut stepped = false;
+    while online_ref.clock >= step {
+        online_ref.clock -= step;
+        session.advance();
+        stepped = true;
+    }
+
+    for message in session.transport_mut().outgoing.drain(..) {
+        socket.send(ClientMessage::Relay(message));
+    }
+    let result = match (session.desync(), session.confirmed_outcome()) {
+        (Some(frame), _) => {
+            warn!("rollback match out of sync at frame {frame}");
+            Some(MatchResult::Desync)
+        }
+        (None, Some(VersusOutcome::Over { winner })) => {
+            Some(MatchResult::of(winner, online_ref.player))
+        }
+        _ => None,
+    };
+    if stepped {
+        online_ref.state = Some(session.state().clone());
+        online.set_changed();
+    }
+
+    if let Some(result) = result {
+        online.finish(result);
+        next_state.set(GameState::GameOver);
+        //  NOTE: hanging up tells the opponent, see `relay_match`
+        connection.0 = None;
+    }
+}
+
+//  NOTE: real time, the round trip doesn't stop for anything on this end
+fn send_pings(
+    online: Res<Online>,
+    connection: NonSend<OnlineConnection>,
+    time: Res<Time<Real>>,
+    mut last_ping: Local<Option<f64>>,
+) {
+    let Some(socket) = &connection.0 else {
+        *last_ping = None;
+        return;
+    };
+    if online.status == OnlineStatus::Connecting {
+        return;
+    }
+
+    let now = time.elapsed_seconds_f64();
+    if last_ping.is_none_or(|last| now - last >= PING_INTERVAL) {
+        socket.send(ClientMessage::Ping { sent: now });
+        *last_ping = Some(now);
+    }
+}
+
+fn online_controls(
+    mut steer_event_reader: EventReader<SteerEvent>,
+    mut online: ResMut<Online>,
+    connection: NonSend<OnlineConnection>,
+) {
+    let turns: Vec<Direction> = steer_event_reader
+        .read()
+        .map(|&SteerEvent { direction, .. }| direction)
+        .collect();
+    if turns.is_empty() {
+        return;
+    }
+
+    //  NOTE: the session plays local turns itself, a few frames late
+    if let Some(session) = &mut online.bypass_change_detection().session {
+        for direction in turns {
+            session.add_turn(direction);
+        }
+        return;
+    }
+
+    let (Some(socket), Some(state), Some(rules)) = (&connection.0, &online.state, online.rules)
+    else {
+        return;
+    };
+    let Some(snake) = state.snakes.get(online.player) else {
+        return;
+    };
+
+    //  NOTE: half the round trip in ticks, so the turn lands when the player meant it to
+    let step = rules.speed.step().as_secs_f64();
+    let delay = online
+        .rtt
+        .map_or(0, |rtt| (rtt / 2.0 / step).ceil() as u32)
+        .min(MAX_INPUT_DELAY);
+    let mut tick = state.tick + 1 + delay;
+    let mut heading = snake.direction;
+    if let Some((last_tick, last_direction)) = online.last_turn {
+        if last_tick > state.tick {
+            heading = last_direction;
+            tick = tick.max(last_tick + 1);
+        }
+    }
+
+    let mut last_turn = online.last_turn;
+    for direction in turns {
+        //  NOTE: same rule as `queue_direction`, no repeats and no turning back into the neck
+        if direction == heading || direction == heading.opposite() {
+            continue;
+        }
+
+        socket.send(ClientMessage::Input { tick, direction });
+        last_turn = Some((tick, direction));
+        heading = direction;
+        tick += 1;
+    }
+    online.last_turn = last_turn;
+}
+
+fn render_online_match(
+    mut commands: Commands,
+    online: Res<Online>,
+    board: Res<Board>,
+    sprites: Res<CurrentSpritePack>,
+    theme: Res<CurrentTheme>,
+    query: Query<Entity, With<OnlineSprite>>,
+) {
+    if !online.is_changed() {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let (Some(state), Some(rules)) = (&online.state, online.rules) else {
+        return;
+    };
+    let grid = board.grid(rules.walls);
+
+    let mut spawn = |pos: Position, part: SpritePart, color: Color, z: f32| {
+        commands.spawn((
+            SpriteSheetBundle {
+                atlas: TextureAtlas {
+                    layout: sprites.layout.clone(),
+                    index: sprites.index(part),
+                },
+                texture: sprites.texture.clone(),
+                transform: Transform::from_xyz(
+                    board.position_translate(pos.x),
+                    board.position_translate(pos.y),
+                    z,
+                ),
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+            OnlineSprite,
+            pos,
+        ));
+    };
+
+    if let Some(food) = state.food {
+        spawn(food, SpritePart::Apple, theme.food, 1.0);
+    }
+
+    for (player, snake) in state.snakes.iter().enumerate() {
+        let mut color = if player == online.player {
+            theme.snake
+        } else {
+            OPPONENT_TINT
+        };
+        if !snake.alive {
+            color.set_a(0.4);
+        }
+
+        let directions = snake.directions(&grid);
+        for (i, &pos) in snake.body.iter().enumerate() {
+            //  NOTE: a head that crashed into the wall is outside of the board
+            if grid.contains(pos) {
+                let z = if i == 0 { 3.0 } else { 2.0 };
+                spawn(pos, segment_part(&directions, i), color, z);
+            }
+        }
+    }
+}
+
+fn update_online_hud(mut contexts: EguiContexts, online: Res<Online>, theme: Res<CurrentTheme>) {
+    let Some(state) = &online.state else {
+        return;
+    };
+
+    let score = |player: usize| state.snakes.get(player).map_or(0, |snake| snake.score);
+    let mut text = format!(
+        "You {}  ·  {} Them",
+        score(online.player),
+        score(1 - online.player.min(1))
+    );
+    if let Some(rtt) = online.rtt {
+        text.push_str(&format!("  ·  {:.0} ms", rtt * 1000.0));
+    }
+    if let Some(session) = &online.session {
+        text.push_str(&format!("  ·  {} rolled back", session.resimulated()));
+    }
+    if state.tick == 0 {
+        text.push_str("  ·  get ready");
+    }
+
+    egui::Area::new(egui::Id::new("online-hud"))
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 70.0))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                egui::RichText::new(text)
+                    .color(color32(theme.text))
+                    .font(egui::FontId::monospace(18.0)),
+            );
+        });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod transport {
+    use std::{
+        sync::mpsc::{self, Receiver, Sender},
+        thread,
+    };
+
+    use tungstenite::stream::MaybeTlsStream;
+
+    use crate::protocol::{
+        socket::{pump, POLL},
+        ClientMessage, PeerEvent, ServerMessage,
+    };
+
+    pub struct Connection {
+        outgoing: Sender<ClientMessage>,
+        incoming: Receiver<PeerEvent<ServerMessage>>,
+    }
+
+    impl Connection {
+        //  NOTE: connects on a thread of its own, failures come back through `poll`
+        pub fn open(url: &str) -> Self {
+            let (outgoing_tx, outgoing_rx) = mpsc::channel();
+            let (incoming_tx, incoming_rx) = mpsc::channel();
+            let url = url.to_string();
+
+            thread::spawn(move || match tungstenite::connect(url.as_str()) {
+                Ok((socket, _)) => {
+                    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+                        let _ = stream.set_read_timeout(Some(POLL));
+                        let _ = stream.set_nodelay(true);
+                    }
+                    pump(socket, outgoing_rx, incoming_tx);
+                }
+                Err(err) => {
+                    let _ = incoming_tx.send(PeerEvent::Closed(err.to_string()));
+                }
+            });
+
+            Self {
+                outgoing: outgoing_tx,
+                incoming: incoming_rx,
+            }
+        }
+
+        pub fn send(&self, message: ClientMessage) {
+            let _ = self.outgoing.send(message);
+        }
+
+        pub fn poll(&self) -> Vec<PeerEvent<ServerMessage>> {
+            self.incoming.try_iter().collect()
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) mod transport {
+    use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use web_sys::{CloseEvent, MessageEvent, WebSocket};
+
+    use crate::protocol::{decode, encode, ClientMessage, PeerEvent, ServerMessage};
+
+    type Events = Rc<RefCell<VecDeque<PeerEvent<ServerMessage>>>>;
+
+    pub struct Connection {
+        socket: Option<WebSocket>,
+        events: Events,
+        //  NOTE: messages sent before the socket finished opening
+        queued: Rc<RefCell<Vec<String>>>,
+        _callbacks: Vec<Closure<dyn FnMut(JsValue)>>,
+    }
+
+    impl Connection {
+        pub fn open(url: &str) -> Self {
+            let events: Events = Default::default();
+            let queued: Rc<RefCell<Vec<String>>> = Default::default();
+
+            let socket = match WebSocket::new(url) {
+                Ok(socket) => socket,
+                Err(err) => {
+                    events
+                        .borrow_mut()
+                        .push_back(PeerEvent::Closed(format!("{err:?}")));
+                    return Self {
+                        socket: None,
+                        events,
+                        queued,
+                        _callbacks: Vec::new(),
+                    };
+                }
+            };
+
+            let on_open = {
+                let socket = socket.clone();
+                let queued = queued.clone();
+                Closure::<dyn FnMut(JsValue)>::new(move |_| {
+                    for text in queued.borrow_mut().drain(..) {
+                        let _ = socket.send_with_str(&text);
+                    }
+                })
+            };
+            let on_message = {
+                let events = events.clone();
+                Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+                    let text = event
+                        .dyn_into::<MessageEvent>()
+                        .ok()
+                        .and_then(|event| event.data().as_string());
+                    if let Some(message) = text.as_deref().and_then(decode) {
+                        events.borrow_mut().push_back(PeerEvent::Message(message));
+                    }
+                })
+            };
+            let on_close = {
+                let events = events.clone();
+                Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+                    let reason = event
+                        .dyn_into::<CloseEvent>()
+                        .ok()
+                        .map(|event| event.reason())
+                        .filter(|reason| !reason.is_empty())
+                        .unwrap_or_else(|| "connection closed".to_string());
+                    events.borrow_mut().push_back(PeerEvent::Closed(reason));
+                })
+            };
+            socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+            Self {
+                socket: Some(socket),
+                events,
+                queued,
+                _callbacks: vec![on_open, on_message, on_close],
+            }
+        }
+
+        pub fn send(&self, message: ClientMessage) {
+            let text = encode(&message);
+            match &self.socket {
+                Some(socket) if socket.ready_state() == WebSocket::OPEN => {
+                    let _ = socket.send_with_str(&text);
+                }
+                Some(_) => self.queued.borrow_mut().push(text),
+                None => (),
+            }
+        }
+
+        pub fn poll(&self) -> Vec<PeerEvent<ServerMessage>> {
+            self.events.borrow_mut().drain(..).collect()
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            if let Some(socket) = &self.socket {
+                socket.set_onopen(None);
+                socket.set_onmessage(None);
+                socket.set_onclose(None);
+                let _ = socket.close();
+            }
+        }
+    }
+}
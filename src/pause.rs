@@ -0,0 +1,211 @@
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowFocused},
+};
+use bevy_egui::{
+    egui::{self, epaint::Shadow},
+    EguiContexts,
+};
+
+use crate::{
+    online::Online,
+    spectate::Spectator,
+    state::{GameState, MenuState},
+    theme::{color32, CurrentTheme, Theme},
+};
+
+const COUNTDOWN_SECONDS: f32 = 3.0;
+
+#[derive(Resource, Debug)]
+struct ResumeCountdown {
+    timer: Timer,
+}
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ResumeCountdown {
+            timer: Timer::from_seconds(COUNTDOWN_SECONDS, TimerMode::Once),
+        })
+        .add_systems(OnEnter(GameState::Paused), freeze_time)
+        .add_systems(OnExit(GameState::Paused), unfreeze_time)
+        .add_systems(OnEnter(GameState::Resuming), start_countdown)
+        .add_systems(
+            Update,
+            update_pause_menu.run_if(in_state(MenuState::Paused)),
+        )
+        .add_systems(
+            Update,
+            update_countdown.run_if(in_state(GameState::Resuming)),
+        )
+        .add_systems(Update, pause_on_focus_lost);
+
+        #[cfg(target_arch = "wasm32")]
+        app.add_systems(Update, pause_on_hidden_tab);
+    }
+}
+
+//  NOTE: virtual time drives the movement timer, stopping it keeps the snake exactly where it was
+fn freeze_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn unfreeze_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn start_countdown(mut countdown: ResMut<ResumeCountdown>) {
+    countdown.timer.reset();
+}
+
+//  NOTE: online matches and streams keep going on the other end, freezing time here would only
+//  leave the snake behind
+pub fn can_pause(online: &Online, spectator: &Spectator) -> bool {
+    !online.in_match() && !spectator.watching()
+}
+
+fn pause(
+    game_state: &State<GameState>,
+    next_state: &mut NextState<GameState>,
+    next_menu_state: &mut NextState<MenuState>,
+) {
+    if matches!(game_state.get(), GameState::Playing | GameState::Resuming) {
+        next_state.set(GameState::Paused);
+        next_menu_state.set(MenuState::Paused);
+    }
+}
+
+fn pause_on_focus_lost(
+    mut focus_events: EventReader<WindowFocused>,
+    game_state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    online: Res<Online>,
+    spectator: Res<Spectator>,
+) {
+    if focus_events.read().any(|ev| !ev.focused) && can_pause(&online, &spectator) {
+        pause(&game_state, &mut next_state, &mut next_menu_state);
+    }
+}
+
+//  NOTE: switching browser tabs doesn't always blur the canvas, so also watch the page visibility
+#[cfg(target_arch = "wasm32")]
+fn pause_on_hidden_tab(
+    game_state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    online: Res<Online>,
+    spectator: Res<Spectator>,
+) {
+    let hidden = web_sys::window()
+        .and_then(|window| window.document())
+        .is_some_and(|document| document.hidden());
+    if hidden && can_pause(&online, &spectator) {
+        pause(&game_state, &mut next_state, &mut next_menu_state);
+    }
+}
+
+fn update_countdown(
+    mut contexts: EguiContexts,
+    mut countdown: ResMut<ResumeCountdown>,
+    time: Res<Time<Real>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    theme: Res<CurrentTheme>,
+) {
+    //  NOTE: real time, virtual time is what the pause froze
+    countdown.timer.tick(time.delta());
+    if countdown.timer.finished() {
+        next_state.set(GameState::Playing);
+        return;
+    }
+
+    let remaining = countdown.timer.remaining_secs().ceil();
+    egui::Area::new(egui::Id::new("resume-countdown"))
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                egui::RichText::new(format!("{}", remaining))
+                    .color(color32(theme.text))
+                    .font(egui::FontId::monospace(96.0)),
+            );
+        });
+}
+
+fn update_pause_menu(
+    mut contexts: EguiContexts,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    theme: Res<CurrentTheme>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+
+    //  NOTE: egui coordinates, they differ from the window size when the UI is scaled
+    let screen = contexts.ctx_mut().screen_rect();
+    egui::Window::new("pause")
+        .title_bar(false)
+        .fixed_size(egui::vec2(300.0, 0.0))
+        .current_pos(egui::pos2(
+            (screen.width() - 300.0) / 2.0,
+            (screen.height() - 300.0) / 2.0,
+        ))
+        .movable(false)
+        .collapsible(false)
+        .resizable(false)
+        .frame(egui::Frame {
+            fill: color32(theme.menu),
+            rounding: egui::Rounding::same(10.0),
+            inner_margin: egui::Margin::same(15.0),
+            shadow: Shadow {
+                color: egui::Color32::from_rgba_premultiplied(0, 0, 0, 150),
+                spread: window.width(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .show(contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(
+                    egui::RichText::new("Paused")
+                        .color(color32(theme.text))
+                        .font(egui::FontId::monospace(28.0)),
+                );
+                ui.add_space(15.0);
+                ui.style_mut().spacing.button_padding = egui::Vec2::new(15., 10.);
+
+                if pause_button(ui, &theme, "Resume").clicked() {
+                    next_state.set(GameState::Resuming);
+                    next_menu_state.set(MenuState::Off);
+                }
+                if pause_button(ui, &theme, "Restart").clicked() {
+                    next_state.set(GameState::Resetting);
+                    next_menu_state.set(MenuState::Off);
+                }
+                if pause_button(ui, &theme, "Settings").clicked() {
+                    next_menu_state.set(MenuState::Settings);
+                }
+                if pause_button(ui, &theme, "Main Menu").clicked() {
+                    next_state.set(GameState::Resetting);
+                    next_menu_state.set(MenuState::On);
+                }
+            });
+        });
+}
+
+fn pause_button(ui: &mut egui::Ui, theme: &Theme, label: &str) -> egui::Response {
+    ui.add_space(5.0);
+    ui.add(
+        egui::Button::new(
+            egui::RichText::new(label)
+                .color(color32(theme.text))
+                .font(egui::FontId::monospace(20.0)),
+        )
+        .min_size(egui::vec2(270., 0.))
+        .rounding(8.0)
+        .fill(color32(theme.button)),
+    )
+}
@@ -0,0 +1,167 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    rollback::PeerMessage,
+    settings::{Speed, WallsMode},
+    snake::Direction,
+    stream::StreamEvent,
+    versus::VersusState,
+};
+
+//  NOTE: messages between `snake-server` and the game, JSON in WebSocket text frames so the wasm
+//  build can talk to it through the browser
+
+pub const DEFAULT_SERVER: &str = "ws://127.0.0.1:7878";
+//  NOTE: inputs are scheduled at most this many ticks ahead to make up for latency
+pub const MAX_INPUT_DELAY: u32 = 5;
+
+//  NOTE: players are only matched with someone asking for the same board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MatchRules {
+    pub board_size: i32,
+    pub walls: WallsMode,
+    pub speed: Speed,
+}
+
+//  NOTE: who runs the match, the server or both players with rollback
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchMode {
+    #[default]
+    Server,
+    //  NOTE: the server only passes `PeerMessage`s along, see `rollback`
+    Rollback,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    Hello(MatchRules),
+    //  NOTE: turn when the server moves to `tick`, see `MAX_INPUT_DELAY`
+    Input { tick: u32, direction: Direction },
+    Ping { sent: f64 },
+    //  NOTE: for the opponent, rollback matches only
+    Relay(PeerMessage),
+    //  NOTE: instead of `Hello`, to stream a game or watch one, see `stream.rs`
+    Broadcast { name: String },
+    Watch { name: String },
+    Stream(StreamEvent),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ServerMessage {
+    //  NOTE: connected, nobody to play against yet
+    Waiting,
+    Start {
+        player: usize,
+        rules: MatchRules,
+        //  NOTE: rollback peers build the same `VersusGame` from it
+        seed: u64,
+        mode: MatchMode,
+    },
+    Tick(VersusState),
+    Pong {
+        sent: f64,
+    },
+    Relay(PeerMessage),
+    //  NOTE: starts with the latest snapshot when joining a stream that's already running
+    Stream(StreamEvent),
+    //  NOTE: the broadcaster left
+    StreamEnded,
+    GameOver {
+        winner: Option<usize>,
+        reason: EndReason,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndReason {
+    Finished,
+    OpponentLeft,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerEvent<T> {
+    Message(T),
+    Closed(String),
+}
+
+pub fn encode(message: &impl Serialize) -> String {
+    serde_json::to_string(message).expect("protocol messages always serialize")
+}
+
+pub fn decode<T: DeserializeOwned>(text: &str) -> Option<T> {
+    serde_json::from_str(text).ok()
+}
+
+//  NOTE: blocking sockets for the server and the native game, each connection gets a thread that
+//  shuttles messages between channels and the socket
+#[cfg(not(target_arch = "wasm32"))]
+pub mod socket {
+    use std::{
+        io::{self, Read, Write},
+        sync::mpsc::{Receiver, Sender, TryRecvError},
+        time::Duration,
+    };
+
+    use serde::{de::DeserializeOwned, Serialize};
+    use tungstenite::{Error, Message, WebSocket};
+
+    use super::{decode, encode, PeerEvent};
+
+    //  NOTE: how long a read waits before checking for outgoing messages again
+    pub const POLL: Duration = Duration::from_millis(2);
+
+    //  NOTE: runs until either side hangs up, the underlying stream needs a read timeout of `POLL`
+    pub fn pump<S: Read + Write, Out: Serialize, In: DeserializeOwned>(
+        mut socket: WebSocket<S>,
+        outgoing: Receiver<Out>,
+        incoming: Sender<PeerEvent<In>>,
+    ) {
+        loop {
+            loop {
+                match outgoing.try_recv() {
+                    Ok(message) => {
+                        if let Err(err) = socket.send(Message::Text(encode(&message))) {
+                            let _ = incoming.send(PeerEvent::Closed(err.to_string()));
+                            return;
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    //  NOTE: our side dropped the connection
+                    Err(TryRecvError::Disconnected) => {
+                        let _ = socket.close(None);
+                        let _ = socket.flush();
+                        return;
+                    }
+                }
+            }
+
+            let closed = match socket.read() {
+                Ok(Message::Text(text)) => {
+                    if let Some(message) = decode(&text) {
+                        if incoming.send(PeerEvent::Message(message)).is_err() {
+                            let _ = socket.close(None);
+                            let _ = socket.flush();
+                            return;
+                        }
+                    }
+                    None
+                }
+                Ok(Message::Close(_)) => Some("connection closed".to_string()),
+                Ok(_) => None,
+                Err(Error::Io(err))
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    None
+                }
+                Err(err) => Some(err.to_string()),
+            };
+            if let Some(reason) = closed {
+                let _ = incoming.send(PeerEvent::Closed(reason));
+                return;
+            }
+        }
+    }
+}
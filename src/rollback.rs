@@ -0,0 +1,617 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    snake::Direction,
+    versus::{VersusGame, VersusOutcome, VersusState},
+};
+
+//  NOTE: peer-to-peer alternative to the authoritative server. Every peer runs the whole match,
+//  sends its own turns and guesses the others' ("no turn") until they arrive. When a guess was
+//  wrong the game is restored from the snapshot of that frame and played forward again. A
+//  `VersusGame` clone is a full snapshot, RNG included, so both peers end up in the same state
+
+//  NOTE: local turns are played this many frames late, hides most rollbacks on a decent connection
+pub const INPUT_DELAY: u32 = 2;
+//  NOTE: how far ahead of the slowest peer we may guess before waiting for it
+pub const MAX_PREDICTION: u32 = 8;
+//  NOTE: how often peers compare their confirmed states
+const CHECKSUM_INTERVAL: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerMessage {
+    //  NOTE: sent for every frame, `None` when the player didn't turn
+    Input {
+        player: usize,
+        frame: u32,
+        turn: Option<Direction>,
+    },
+    //  NOTE: of the state at the start of `frame`, once every input before it is known
+    Checksum {
+        player: usize,
+        frame: u32,
+        checksum: u64,
+    },
+}
+
+//  NOTE: delivers messages in order, rollback relies on inputs never overtaking each other
+pub trait Transport {
+    fn send(&mut self, message: PeerMessage);
+    fn receive(&mut self) -> Vec<PeerMessage>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advance {
+    Stepped,
+    //  NOTE: too far ahead of a peer, try again next tick
+    Stalled,
+    //  NOTE: the game is over as far as we know, it can still come back after a rollback
+    Over,
+}
+
+#[derive(Debug)]
+pub struct RollbackSession<T> {
+    transport: T,
+    local: usize,
+    game: VersusGame,
+    //  NOTE: `snapshots[i]` is the game at the start of frame `base + i`, kept from the first
+    //  frame that isn't confirmed on
+    base: u32,
+    snapshots: VecDeque<VersusGame>,
+    //  NOTE: `inputs[frame][player]`, `None` until that player's input arrived
+    inputs: Vec<Vec<Option<Option<Direction>>>>,
+    //  NOTE: frames with every player's input known
+    confirmed: u32,
+    turns: VecDeque<Direction>,
+    checksums: HashMap<u32, u64>,
+    remote_checksums: Vec<(u32, u64)>,
+    desync: Option<u32>,
+    resimulated: u32,
+}
+
+impl<T: Transport> RollbackSession<T> {
+    pub fn new(game: VersusGame, local: usize, transport: T) -> Self {
+        let mut session = Self {
+            transport,
+            local,
+            game,
+            base: 0,
+            snapshots: VecDeque::new(),
+            inputs: Vec::new(),
+            confirmed: 0,
+            turns: VecDeque::new(),
+            checksums: HashMap::new(),
+            remote_checksums: Vec::new(),
+            desync: None,
+            resimulated: 0,
+        };
+        //  NOTE: nobody can turn during the first frames, the delay hasn't passed yet
+        for frame in 0..INPUT_DELAY {
+            session.set_local(frame, None);
+        }
+        session
+    }
+
+    pub fn state(&self) -> &VersusState {
+        self.game.state()
+    }
+
+    pub fn local(&self) -> usize {
+        self.local
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.game.state().tick
+    }
+
+    pub fn confirmed_frame(&self) -> u32 {
+        self.confirmed
+    }
+
+    //  NOTE: frames played again after a wrong guess, a rough measure of how much we predicted
+    pub fn resimulated(&self) -> u32 {
+        self.resimulated
+    }
+
+    //  NOTE: the first frame both sides disagreed on, only a bug in the rules can cause this
+    pub fn desync(&self) -> Option<u32> {
+        self.desync
+    }
+
+    //  NOTE: every player's input for each confirmed frame, replaying them gives the same game
+    pub fn confirmed_inputs(&self) -> impl Iterator<Item = Vec<Option<Direction>>> + '_ {
+        self.inputs
+            .iter()
+            .take(self.confirmed as usize)
+            .map(|slot| slot.iter().map(|input| input.flatten()).collect())
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    //  NOTE: the outcome once no late input can change it anymore
+    pub fn confirmed_outcome(&self) -> Option<VersusOutcome> {
+        let outcome = self.game.outcome();
+        (outcome != VersusOutcome::Running && self.confirmed >= self.frame()).then_some(outcome)
+    }
+
+    //  NOTE: same rule as `queue_direction`, no repeats and no turning back into the neck.
+    //  Turns are used one per frame
+    pub fn add_turn(&mut self, direction: Direction) -> bool {
+        let heading = self.heading();
+        if direction == heading || direction == heading.opposite() {
+            return false;
+        }
+        self.turns.push_back(direction);
+        true
+    }
+
+    fn heading(&self) -> Direction {
+        if let Some(&turn) = self.turns.back() {
+            return turn;
+        }
+        let frame = self.frame() as usize;
+        let scheduled = self
+            .inputs
+            .iter()
+            .skip(frame)
+            .filter_map(|slot| slot[self.local].flatten())
+            .last();
+        scheduled.unwrap_or(self.game.state().snakes[self.local].direction)
+    }
+
+    pub fn advance(&mut self) -> Advance {
+        self.receive();
+        if self.game.outcome() != VersusOutcome::Running {
+            return Advance::Over;
+        }
+
+        let frame = self.frame();
+        if frame >= self.confirmed + MAX_PREDICTION {
+            return Advance::Stalled;
+        }
+
+        //  NOTE: a rollback can end the game earlier than it was, the frames after it already had
+        //  their inputs sent and the peers must never see two different ones
+        let (scheduled, local) = (frame + INPUT_DELAY, self.local);
+        if self.slot(scheduled)[local].is_none() {
+            let turn = self.turns.pop_front();
+            self.set_local(scheduled, turn);
+        }
+        self.step();
+        self.confirm();
+        Advance::Stepped
+    }
+
+    //  NOTE: play on with what has arrived until the outcome is confirmed or we'd have to guess
+    //  too far, used when a peer leaves right after the last frame
+    pub fn settle(&mut self) -> Option<VersusOutcome> {
+        while self.advance() == Advance::Stepped {}
+        self.confirmed_outcome()
+    }
+
+    fn players(&self) -> usize {
+        self.game.state().snakes.len()
+    }
+
+    fn slot(&mut self, frame: u32) -> &mut Vec<Option<Option<Direction>>> {
+        let players = self.players();
+        let frame = frame as usize;
+        if self.inputs.len() <= frame {
+            self.inputs.resize(frame + 1, vec![None; players]);
+        }
+        &mut self.inputs[frame]
+    }
+
+    fn set_local(&mut self, frame: u32, turn: Option<Direction>) {
+        let local = self.local;
+        self.slot(frame)[local] = Some(turn);
+        self.transport.send(PeerMessage::Input {
+            player: local,
+            frame,
+            turn,
+        });
+    }
+
+    //  NOTE: missing inputs are guessed as "no turn", snakes mostly go straight
+    fn step(&mut self) {
+        let frame = self.frame();
+        let inputs: Vec<Option<Direction>> = self
+            .slot(frame)
+            .iter()
+            .map(|input| input.flatten())
+            .collect();
+        self.snapshots.push_back(self.game.clone());
+        self.game.step(&inputs);
+    }
+
+    fn receive(&mut self) {
+        let mut rollback_to: Option<u32> = None;
+        for message in self.transport.receive() {
+            match message {
+                PeerMessage::Input {
+                    player,
+                    frame,
+                    turn,
+                } if player != self.local && player < self.players() => {
+                    //  NOTE: confirmed frames are settled and an input never changes once it
+                    //  arrived, duplicates and misbehaving peers can't rewrite either
+                    if frame < self.base || self.slot(frame)[player].is_some() {
+                        continue;
+                    }
+                    self.slot(frame)[player] = Some(turn);
+                    //  NOTE: the guess for a frame already played was "no turn"
+                    if frame < self.frame() && turn.is_some() {
+                        rollback_to =
+                            Some(rollback_to.map_or(frame, |earliest| earliest.min(frame)));
+                    }
+                }
+                PeerMessage::Checksum {
+                    player,
+                    frame,
+                    checksum,
+                } if player != self.local => self.remote_checksums.push((frame, checksum)),
+                _ => (),
+            }
+        }
+
+        if let Some(frame) = rollback_to {
+            self.rollback(frame);
+        }
+        self.confirm();
+    }
+
+    fn rollback(&mut self, frame: u32) {
+        let current = self.frame();
+        let index = (frame - self.base) as usize;
+        self.game = self.snapshots[index].clone();
+        self.snapshots.truncate(index);
+
+        while self.frame() < current && self.game.outcome() == VersusOutcome::Running {
+            self.step();
+            self.resimulated += 1;
+        }
+    }
+
+    fn confirm(&mut self) {
+        while self.confirmed < self.frame()
+            && self
+                .inputs
+                .get(self.confirmed as usize)
+                .is_some_and(|slot| slot.iter().all(Option::is_some))
+        {
+            self.confirmed += 1;
+            if self.confirmed.is_multiple_of(CHECKSUM_INTERVAL) {
+                self.send_checksum(self.confirmed);
+            }
+        }
+
+        //  NOTE: nothing before the confirmed frame can be rolled back anymore
+        while self.base < self.confirmed && !self.snapshots.is_empty() {
+            self.snapshots.pop_front();
+            self.base += 1;
+        }
+        self.check_desync();
+    }
+
+    fn send_checksum(&mut self, frame: u32) {
+        let state = match frame.checked_sub(self.base) {
+            Some(index) if (index as usize) < self.snapshots.len() => {
+                self.snapshots[index as usize].state()
+            }
+            _ => self.game.state(),
+        };
+        let checksum = checksum(state);
+        self.checksums.insert(frame, checksum);
+        self.transport.send(PeerMessage::Checksum {
+            player: self.local,
+            frame,
+            checksum,
+        });
+    }
+
+    fn check_desync(&mut self) {
+        let checksums = &self.checksums;
+        let mut desync = self.desync;
+        self.remote_checksums
+            .retain(|&(frame, remote)| match checksums.get(&frame) {
+                Some(&local) => {
+                    if local != remote && desync.is_none() {
+                        desync = Some(frame);
+                    }
+                    false
+                }
+                None => true,
+            });
+        self.desync = desync;
+    }
+}
+
+//  NOTE: FNV-1a over the fields, written out by hand so native and wasm peers agree
+pub fn checksum(state: &VersusState) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |value: u32| {
+        for byte in value.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+
+    feed(state.tick);
+    match state.food {
+        Some(food) => {
+            feed(1);
+            feed(food.x as u32);
+            feed(food.y as u32);
+        }
+        None => feed(0),
+    }
+    for snake in &state.snakes {
+        feed(snake.alive as u32);
+        feed(snake.score);
+        feed(snake.direction as u32);
+        feed(snake.body.len() as u32);
+        for cell in &snake.body {
+            feed(cell.x as u32);
+            feed(cell.y as u32);
+        }
+    }
+    hash
+}
+
+//  NOTE: hands messages to whoever moves them, the game shuttles them through the relay server
+#[derive(Debug, Default)]
+pub struct BufferedTransport {
+    pub incoming: Vec<PeerMessage>,
+    pub outgoing: Vec<PeerMessage>,
+}
+
+impl Transport for BufferedTransport {
+    fn send(&mut self, message: PeerMessage) {
+        self.outgoing.push(message);
+    }
+
+    fn receive(&mut self) -> Vec<PeerMessage> {
+        std::mem::take(&mut self.incoming)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Link {
+    //  NOTE: per direction, the message and the receive call it can be picked up on
+    queues: [VecDeque<(u64, PeerMessage)>; 2],
+}
+
+//  NOTE: two ends of an in-memory connection that holds every message back for `latency`
+//  receive calls, one per frame, for trying rollback without a network
+#[derive(Debug, Clone)]
+pub struct LocalTransport {
+    link: Arc<Mutex<Link>>,
+    side: usize,
+    latency: u64,
+    clock: u64,
+}
+
+impl LocalTransport {
+    pub fn pair(latency: u32) -> (Self, Self) {
+        let link = Arc::new(Mutex::new(Link::default()));
+        let end = |side| Self {
+            link: link.clone(),
+            side,
+            latency: latency as u64,
+            clock: 0,
+        };
+        (end(0), end(1))
+    }
+}
+
+impl Transport for LocalTransport {
+    fn send(&mut self, message: PeerMessage) {
+        let mut link = self.link.lock().unwrap();
+        link.queues[1 - self.side].push_back((self.clock + self.latency, message));
+    }
+
+    fn receive(&mut self) -> Vec<PeerMessage> {
+        self.clock += 1;
+        let mut link = self.link.lock().unwrap();
+        let queue = &mut link.queues[self.side];
+        let mut messages = Vec::new();
+        while queue.front().is_some_and(|&(due, _)| due <= self.clock) {
+            messages.extend(queue.pop_front().map(|(_, message)| message));
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{settings::WallsMode, sim::Grid, snake::Position};
+
+    const GRID: Grid = Grid {
+        size: 20,
+        walls: WallsMode::Solid,
+    };
+    //  NOTE: loop iterations before a match counts as stuck
+    const MAX_ITERATIONS: u32 = 20_000;
+
+    //  NOTE: two bots over `LocalTransport`, the second one skips a frame now and then like a
+    //  slower machine so the first one has to wait for it too. Both peers must end where a
+    //  straight replay of their confirmed inputs does
+    fn play(seed: u64, latency: u32) {
+        let game = VersusGame::new(GRID, 2, seed);
+        let (left, right) = LocalTransport::pair(latency);
+        let mut peers = [
+            RollbackSession::new(game.clone(), 0, left),
+            RollbackSession::new(game.clone(), 1, right),
+        ];
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let mut iterations = 0;
+        while peers.iter().any(|peer| peer.confirmed_outcome().is_none()) {
+            iterations += 1;
+            assert!(
+                iterations <= MAX_ITERATIONS,
+                "latency {latency}, seed {seed}: the match never settled"
+            );
+            for (player, peer) in peers.iter_mut().enumerate() {
+                if player == 1 && rng.gen_bool(0.1) {
+                    continue;
+                }
+                if let Some(direction) = bot_turn(peer.state(), player, &mut rng) {
+                    peer.add_turn(direction);
+                }
+                peer.advance();
+            }
+        }
+
+        for peer in &peers {
+            assert_eq!(
+                peer.desync(),
+                None,
+                "latency {latency}, seed {seed}: checksums differed"
+            );
+            let mut replay = game.clone();
+            for inputs in peer.confirmed_inputs() {
+                replay.step(&inputs);
+            }
+            assert_eq!(
+                replay.state(),
+                peer.state(),
+                "latency {latency}, seed {seed}: player {} differs from the replay",
+                peer.local()
+            );
+        }
+        assert_eq!(peers[0].state(), peers[1].state());
+    }
+
+    //  NOTE: keeps going unless that crashes soon, with a random turn now and then to make
+    //  rollbacks happen. Turns land `INPUT_DELAY` frames late so it looks that far ahead
+    fn bot_turn(state: &VersusState, player: usize, rng: &mut StdRng) -> Option<Direction> {
+        let snake = state.snakes.get(player).filter(|snake| snake.alive)?;
+        let occupied = |pos: Position| {
+            state
+                .snakes
+                .iter()
+                .any(|other| other.alive && other.body.contains(&pos))
+        };
+        let free = |direction: Direction| {
+            let mut pos = snake.body[0];
+            (0..=INPUT_DELAY + 1).all(|_| match GRID.step(pos, direction) {
+                Some(next) if !occupied(next) => {
+                    pos = next;
+                    true
+                }
+                _ => false,
+            })
+        };
+
+        if free(snake.direction) && rng.gen_bool(0.85) {
+            return None;
+        }
+        let options: Vec<Direction> = Direction::ALL
+            .into_iter()
+            .filter(|&direction| direction != snake.direction.opposite() && free(direction))
+            .collect();
+        options.choose(rng).copied()
+    }
+
+    #[test]
+    fn peers_match_the_replay_at_any_latency() {
+        for latency in [0, 1, 2, 4, 8, 15] {
+            for seed in 0..5 {
+                play(seed, latency);
+            }
+        }
+    }
+
+    fn remote_input(frame: u32, turn: Option<Direction>) -> PeerMessage {
+        PeerMessage::Input {
+            player: 1,
+            frame,
+            turn,
+        }
+    }
+
+    #[test]
+    fn stale_and_conflicting_inputs_are_ignored() {
+        let game = VersusGame::new(GRID, 2, 0);
+        let mut session = RollbackSession::new(game.clone(), 0, BufferedTransport::default());
+        session.transport_mut().incoming = (0..6).map(|frame| remote_input(frame, None)).collect();
+        for _ in 0..4 {
+            session.advance();
+        }
+        assert_eq!(session.confirmed_frame(), 4);
+
+        //  NOTE: a repeat of a confirmed frame used to roll back past the oldest snapshot, and a
+        //  turn for a frame that already had one used to rewrite it
+        session.transport_mut().incoming = vec![
+            remote_input(0, Some(Direction::Up)),
+            remote_input(3, Some(Direction::Up)),
+            remote_input(5, Some(Direction::Down)),
+        ];
+        session.advance();
+        session.advance();
+
+        let mut replay = game;
+        for _ in 0..session.frame() {
+            replay.step(&[None, None]);
+        }
+        assert_eq!(session.state(), replay.state());
+        assert!(session
+            .confirmed_inputs()
+            .all(|inputs| inputs == [None, None]));
+    }
+
+    #[test]
+    fn a_peer_that_stops_stalls_the_other_until_it_comes_back() {
+        //  NOTE: on separate rows of a wrapping board the snakes go straight forever
+        let grid = Grid {
+            size: 40,
+            walls: WallsMode::Wrap,
+        };
+        let game = VersusGame::new(grid, 2, 0);
+        let (left, right) = LocalTransport::pair(1);
+        let mut peers = [
+            RollbackSession::new(game.clone(), 0, left),
+            RollbackSession::new(game, 1, right),
+        ];
+        for _ in 0..10 {
+            for peer in &mut peers {
+                assert_eq!(peer.advance(), Advance::Stepped);
+            }
+        }
+
+        //  NOTE: the second peer stops, the first one guesses as far as it may and then waits
+        let mut stalled = 0;
+        for _ in 0..MAX_PREDICTION * 2 {
+            if peers[0].advance() == Advance::Stalled {
+                stalled += 1;
+            }
+        }
+        assert!(stalled > 0);
+        let frame = peers[0].frame();
+        assert_eq!(frame, peers[0].confirmed_frame() + MAX_PREDICTION);
+        assert_eq!(peers[0].advance(), Advance::Stalled);
+        assert_eq!(peers[0].frame(), frame);
+
+        //  NOTE: back again, the second peer's inputs confirm the guesses and both play on
+        for _ in 0..MAX_PREDICTION * 4 {
+            for peer in &mut peers {
+                peer.advance();
+            }
+        }
+        assert!(peers[0].confirmed_frame() > frame);
+        assert!(peers[0].frame() > frame);
+        for peer in &peers {
+            assert_eq!(peer.desync(), None);
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    f32::consts::TAU,
+};
+
+use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    autopilot::plan_direction,
+    settings::WallsMode,
+    sim::Grid,
+    snake::{Direction, Position},
+    versus::{advance, crashed, VersusOutcome, VersusSnake},
+};
+
+//  NOTE: battle royale, many snakes on a large board that closes in on them. Moves and crashes
+//  like `versus::VersusGame`, with food everywhere: snakes that die turn into food and the edge cells
+//  become walls every `SHRINK_INTERVAL` moves
+
+pub const ROYALE_BOARD_SIZE: i32 = 60;
+pub const MIN_ROYALE_SNAKES: usize = 8;
+pub const MAX_ROYALE_SNAKES: usize = 16;
+pub const DEFAULT_ROYALE_SNAKES: usize = 12;
+//  NOTE: one on WASD and one on the arrow keys, see `SteerEvent`
+pub const MAX_ROYALE_HUMANS: usize = 2;
+//  NOTE: moves between two shrinks, the arena loses one cell on every side
+pub const SHRINK_INTERVAL: u32 = 40;
+//  NOTE: the arena stops shrinking at this size
+const MIN_ARENA_SIZE: i32 = 12;
+//  NOTE: random food is topped up to this much, on top of what dead snakes leave behind
+const SPAWNED_FOOD: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoyaleState {
+    pub tick: u32,
+    pub snakes: Vec<VersusSnake>,
+    pub food: Vec<Position>,
+    //  NOTE: cells closed off on every side of the board
+    pub margin: i32,
+    //  NOTE: 1 for the winner, `None` while still alive
+    pub places: Vec<Option<usize>>,
+}
+
+impl RoyaleState {
+    pub fn alive(&self) -> usize {
+        self.snakes.iter().filter(|snake| snake.alive).count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RoyaleGame {
+    grid: Grid,
+    state: RoyaleState,
+    rng: StdRng,
+    outcome: VersusOutcome,
+}
+
+impl RoyaleGame {
+    pub fn new(players: usize, seed: u64) -> Self {
+        let grid = Grid {
+            size: ROYALE_BOARD_SIZE,
+            walls: WallsMode::Solid,
+        };
+        let snakes = (0..players)
+            .map(|player| spawn(grid.size, player, players))
+            .collect();
+        let mut game = Self {
+            grid,
+            state: RoyaleState {
+                tick: 0,
+                snakes,
+                food: Vec::new(),
+                margin: 0,
+                places: vec![None; players],
+            },
+            rng: StdRng::seed_from_u64(seed),
+            outcome: VersusOutcome::Running,
+        };
+        game.spawn_food();
+        game
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn state(&self) -> &RoyaleState {
+        &self.state
+    }
+
+    pub fn outcome(&self) -> VersusOutcome {
+        self.outcome
+    }
+
+    //  NOTE: whether `pos` is still inside the walls
+    pub fn in_arena(&self, pos: Position) -> bool {
+        inside(self.grid.size, self.state.margin, pos)
+    }
+
+    //  NOTE: `None` once the arena is as small as it gets
+    pub fn moves_to_shrink(&self) -> Option<u32> {
+        (self.grid.size - 2 * self.state.margin > MIN_ARENA_SIZE)
+            .then(|| SHRINK_INTERVAL - self.state.tick % SHRINK_INTERVAL)
+    }
+
+    //  NOTE: one move for every snake still alive, `None` keeps a snake going the way it was
+    pub fn step(&mut self, directions: &[Option<Direction>]) -> VersusOutcome {
+        if self.outcome != VersusOutcome::Running {
+            return self.outcome;
+        }
+
+        let vacated = advance(&self.grid, &mut self.state.snakes, directions);
+
+        self.state.tick += 1;
+        if self.moves_to_shrink() == Some(SHRINK_INTERVAL) {
+            self.state.margin += 1;
+        }
+
+        //  NOTE: everybody moves at once, and a snake caught by the closing walls is crushed
+        let dead: Vec<usize> = (0..self.state.snakes.len())
+            .filter(|&i| self.state.snakes[i].alive && (self.crashed(i) || self.crushed(i)))
+            .collect();
+        let remaining = self.state.alive() - dead.len();
+        for &i in &dead {
+            self.state.snakes[i].alive = false;
+            //  NOTE: snakes dying on the same move share the place
+            self.state.places[i] = Some(remaining + 1);
+        }
+        for &i in &dead {
+            let remains: Vec<Position> = self.state.snakes[i].body.iter().copied().collect();
+            for cell in remains {
+                if self.in_arena(cell) && !self.state.food.contains(&cell) {
+                    self.state.food.push(cell);
+                }
+            }
+        }
+
+        for (snake, vacated) in self.state.snakes.iter_mut().zip(vacated) {
+            if !snake.alive {
+                continue;
+            }
+            if let Some(eaten) = self
+                .state
+                .food
+                .iter()
+                .position(|&food| food == snake.body[0])
+            {
+                self.state.food.swap_remove(eaten);
+                //  NOTE: the new segment takes the cell the tail just left
+                snake.body.extend(vacated);
+                snake.score += 1;
+            }
+        }
+        let (size, margin) = (self.grid.size, self.state.margin);
+        self.state.food.retain(|&food| inside(size, margin, food));
+        self.spawn_food();
+
+        let alive: Vec<usize> = (0..self.state.snakes.len())
+            .filter(|&i| self.state.snakes[i].alive)
+            .collect();
+        if alive.len() <= 1 {
+            if let Some(&winner) = alive.first() {
+                self.state.places[winner] = Some(1);
+            }
+            self.outcome = VersusOutcome::Over {
+                winner: alive.first().copied(),
+            };
+        }
+        self.outcome
+    }
+
+    //  NOTE: the built-in bots, `autopilot::plan_direction` with the walls, the other snakes and
+    //  the cells right next to their heads in the way
+    pub fn plan(&self, player: usize) -> Option<Direction> {
+        let snake = self.state.snakes.get(player).filter(|snake| snake.alive)?;
+        let mut obstacles: HashSet<Position> = (0..self.grid.size)
+            .flat_map(|y| (0..self.grid.size).map(move |x| Position::new(x, y)))
+            .filter(|&pos| !self.in_arena(pos))
+            .collect();
+        for (i, other) in self.state.snakes.iter().enumerate() {
+            if i == player || !other.alive {
+                continue;
+            }
+            obstacles.extend(other.body.iter().copied());
+            obstacles.extend(
+                Direction::ALL
+                    .into_iter()
+                    .map(|direction| self.grid.advance(other.body[0], direction)),
+            );
+        }
+
+        let body: Vec<Position> = snake.body.iter().copied().collect();
+        //  NOTE: a snake the next shrink would catch heads for the middle instead
+        let caught = body
+            .iter()
+            .any(|&cell| !inside(self.grid.size, self.state.margin + 1, cell));
+        let food = match self.moves_to_shrink() {
+            Some(moves) if caught && moves as usize <= body.len() + 2 => {
+                vec![Position::new(self.grid.size / 2, self.grid.size / 2)]
+            }
+            _ => self.state.food.clone(),
+        };
+        plan_direction(&self.grid, &body, &obstacles, &food)
+    }
+
+    //  NOTE: the closed off cells are walls too
+    fn crashed(&self, i: usize) -> bool {
+        !self.in_arena(self.state.snakes[i].body[0]) || crashed(&self.grid, &self.state.snakes, i)
+    }
+
+    fn crushed(&self, i: usize) -> bool {
+        self.state.snakes[i]
+            .body
+            .iter()
+            .any(|&cell| !self.in_arena(cell))
+    }
+
+    fn spawn_food(&mut self) {
+        let spawned = self.state.food.len();
+        if spawned >= SPAWNED_FOOD {
+            return;
+        }
+
+        let occupied: HashSet<Position> = self
+            .state
+            .snakes
+            .iter()
+            .filter(|snake| snake.alive)
+            .flat_map(|snake| snake.body.iter().copied())
+            .chain(self.state.food.iter().copied())
+            .collect();
+        let (size, margin) = (self.grid.size, self.state.margin);
+        let free = (0..size)
+            .flat_map(|y| (0..size).map(move |x| Position::new(x, y)))
+            .filter(|&pos| inside(size, margin, pos) && !occupied.contains(&pos));
+        let picked = free.choose_multiple(&mut self.rng, SPAWNED_FOOD - spawned);
+        self.state.food.extend(picked);
+    }
+}
+
+fn inside(size: i32, margin: i32, pos: Position) -> bool {
+    let open = margin..size - margin;
+    open.contains(&pos.x) && open.contains(&pos.y)
+}
+
+//  NOTE: snakes start three long, evenly spread on a circle around the middle and heading around
+//  it so nobody starts out facing somebody else
+fn spawn(size: i32, player: usize, players: usize) -> VersusSnake {
+    let angle = TAU * player as f32 / players as f32;
+    let radius = size as f32 * 0.35;
+    let center = size as f32 / 2.0;
+    let head = Position::new(
+        (center + radius * angle.cos()).round() as i32,
+        (center + radius * angle.sin()).round() as i32,
+    );
+    //  NOTE: counter-clockwise along the circle, snapped to the closest direction
+    let (dx, dy) = (-angle.sin(), angle.cos());
+    let direction = if dx.abs() > dy.abs() {
+        if dx > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        }
+    } else if dy > 0.0 {
+        Direction::Up
+    } else {
+        Direction::Down
+    };
+    let behind = Grid {
+        size,
+        walls: WallsMode::Solid,
+    };
+
+    let mut body = VecDeque::from([head]);
+    for _ in 1..3 {
+        let last = *body.back().expect("just pushed");
+        body.push_back(behind.advance(last, direction.opposite()));
+    }
+    VersusSnake {
+        body,
+        direction,
+        alive: true,
+        score: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //  NOTE: the arena stops shrinking long before this, bots that loop forever count as stuck
+    const MAX_MOVES: u32 = 20_000;
+    const SEEDS: u64 = 4;
+
+    //  NOTE: the built-in bots play it out, no living snake may ever sit in the walls
+    fn play(snakes: usize, seed: u64) -> RoyaleGame {
+        let mut game = RoyaleGame::new(snakes, seed);
+        while game.outcome() == VersusOutcome::Running {
+            assert!(
+                game.state().tick < MAX_MOVES,
+                "{snakes} snakes, seed {seed}: still running after {MAX_MOVES} moves"
+            );
+            let turns: Vec<_> = (0..snakes).map(|player| game.plan(player)).collect();
+            game.step(&turns);
+
+            let state = game.state();
+            for snake in state.snakes.iter().filter(|snake| snake.alive) {
+                assert!(
+                    snake.body.iter().all(|&cell| game.in_arena(cell)),
+                    "{snakes} snakes, seed {seed}: a living snake is in the walls"
+                );
+            }
+        }
+        game
+    }
+
+    #[test]
+    fn bots_finish_with_one_winner_or_a_shared_draw() {
+        for snakes in [MIN_ROYALE_SNAKES, MAX_ROYALE_SNAKES] {
+            for seed in 0..SEEDS {
+                let game = play(snakes, seed);
+                let state = game.state();
+                assert!(
+                    state.places.iter().all(Option::is_some),
+                    "{snakes} snakes, seed {seed}: a snake finished without a place"
+                );
+
+                let firsts = state
+                    .places
+                    .iter()
+                    .filter(|&&place| place == Some(1))
+                    .count();
+                match game.outcome() {
+                    VersusOutcome::Over {
+                        winner: Some(winner),
+                    } => {
+                        assert_eq!(firsts, 1, "{snakes} snakes, seed {seed}");
+                        assert_eq!(state.places[winner], Some(1));
+                    }
+                    VersusOutcome::Over { winner: None } => {
+                        assert!(
+                            firsts > 1,
+                            "{snakes} snakes, seed {seed}: a draw for nobody"
+                        );
+                    }
+                    VersusOutcome::Running => unreachable!("`play` runs until the game is over"),
+                }
+            }
+        }
+    }
+}
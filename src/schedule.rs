@@ -1,16 +1,25 @@
 use bevy::prelude::*;
 
-use crate::state::GameState;
+use crate::{
+    battle_royale::in_battle_royale, online::in_online_match, spectate::spectating,
+    state::GameState,
+};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 pub enum InGameSet {
     DespawnEntities,
     UserInput,
     PositionUpdates,
+    //  NOTE: enemies move after the snake, see `hazard.rs`
+    HazardUpdates,
     CollisionDetection,
     EntityUpdates,
 }
 
+//  NOTE: the systems sending `SteerEvent`s, anything steering a snake runs after them
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub struct SteerSet;
+
 pub struct SchedulePlugin;
 
 impl Plugin for SchedulePlugin {
@@ -21,11 +30,19 @@ impl Plugin for SchedulePlugin {
                 InGameSet::DespawnEntities,
                 InGameSet::UserInput,
                 InGameSet::PositionUpdates,
+                InGameSet::HazardUpdates,
                 InGameSet::CollisionDetection,
                 InGameSet::EntityUpdates,
             )
                 .chain()
-                .run_if(in_state(GameState::Playing)),
-        );
+                .run_if(in_state(GameState::Playing))
+                //  NOTE: the server runs online matches, see `online.rs`
+                .run_if(not(in_online_match))
+                //  NOTE: and spectated games come from the stream, see `spectate.rs`
+                .run_if(not(spectating))
+                //  NOTE: and battle royales run their own rules, see `battle_royale.rs`
+                .run_if(not(in_battle_royale)),
+        )
+        .configure_sets(Update, SteerSet.before(InGameSet::UserInput));
     }
 }
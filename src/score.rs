@@ -1,6 +1,15 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
 
-use crate::state::GameState;
+use crate::{
+    autopilot::Autopilot,
+    battle_royale::BattleRoyale,
+    online::Online,
+    settings::{GameMode, Settings},
+    spectate::Spectator,
+    state::GameState,
+};
 
 pub struct ScorePlugin;
 
@@ -8,7 +17,8 @@ pub struct ScorePlugin;
 pub struct Score {
     pub value: i32,
     pub previous: i32,
-    pub highest: i32,
+    //  NOTE: every game mode keeps its own record
+    highest: HashMap<GameMode, i32>,
 }
 
 impl Default for Score {
@@ -16,36 +26,153 @@ impl Default for Score {
         Self {
             value: 0,
             previous: 0,
-            highest: 0,
+            highest: HashMap::new(),
         }
     }
 }
 
 impl Score {
-    pub fn game_over(&mut self) {
-        if self.value > self.highest {
-            self.highest = self.value;
+    pub fn highest(&self, mode: GameMode) -> i32 {
+        self.highest.get(&mode).copied().unwrap_or(0)
+    }
+
+    pub fn game_over(&mut self, mode: GameMode) {
+        let highest = self.highest.entry(mode).or_insert(0);
+        if self.value > *highest {
+            *highest = self.value;
         }
         self.previous = self.value;
         self.value = 0;
     }
+
+    //  NOTE: autopilot, online, spectated and battle royale games show their score but never count
+    //  as a record
+    pub fn demo_over(&mut self) {
+        self.previous = self.value;
+        self.value = 0;
+    }
+
+    pub fn reset(&mut self) {
+        self.value = 0;
+    }
 }
 
 impl Plugin for ScorePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Score>()
-            .add_systems(OnEnter(GameState::GameOver), update_score);
+            .add_systems(OnEnter(GameState::GameOver), update_score)
+            .add_systems(OnEnter(GameState::Resetting), reset_score);
     }
 }
 
-fn update_score(mut game_score: ResMut<Score>) {
-    game_score.game_over();
+fn update_score(
+    mut game_score: ResMut<Score>,
+    autopilot: Res<Autopilot>,
+    online: Res<Online>,
+    spectator: Res<Spectator>,
+    royale: Res<BattleRoyale>,
+    settings: Res<Settings>,
+) {
+    if is_demo(&autopilot, &online, &spectator, &royale) {
+        game_score.demo_over();
+    } else {
+        game_score.game_over(settings.gameplay.mode);
+    }
 }
 
-// pub struct ScoreEvent {
-//
-// }
+//  NOTE: zen games never end on their own, starting over is how they finish
+fn reset_score(
+    mut game_score: ResMut<Score>,
+    autopilot: Res<Autopilot>,
+    online: Res<Online>,
+    spectator: Res<Spectator>,
+    royale: Res<BattleRoyale>,
+    settings: Res<Settings>,
+) {
+    let zen = settings.gameplay.mode == GameMode::Zen;
+    if zen && game_score.value > 0 && !is_demo(&autopilot, &online, &spectator, &royale) {
+        game_score.game_over(GameMode::Zen);
+    } else {
+        game_score.reset();
+    }
+}
+
+fn is_demo(
+    autopilot: &Autopilot,
+    online: &Online,
+    spectator: &Spectator,
+    royale: &BattleRoyale,
+) -> bool {
+    autopilot.enabled || online.in_match() || spectator.watching() || royale.in_match()
+}
 
 //  NOTE: Scoreboard
 //  1. when the snake eats, increase the score
 //  2. since it's a monolithic state, let's keep it as a resource?
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    //  NOTE: the resources the score systems read, nothing in a demo
+    fn world(mode: GameMode, value: i32) -> World {
+        let mut world = World::new();
+        let mut settings = Settings::default();
+        settings.gameplay.mode = mode;
+        world.insert_resource(settings);
+        world.insert_resource(Score { value, ..default() });
+        world.init_resource::<Autopilot>();
+        world.init_resource::<Online>();
+        world.init_resource::<Spectator>();
+        world.init_resource::<BattleRoyale>();
+        world
+    }
+
+    #[test]
+    fn records_are_kept_per_mode() {
+        let mut score = Score::default();
+        score.value = 5;
+        score.game_over(GameMode::Classic);
+        score.value = 3;
+        score.game_over(GameMode::TimeAttack);
+        score.value = 4;
+        score.game_over(GameMode::Classic);
+
+        assert_eq!(score.highest(GameMode::Classic), 5);
+        assert_eq!(score.highest(GameMode::TimeAttack), 3);
+        assert_eq!(score.highest(GameMode::Survival), 0);
+        assert_eq!((score.value, score.previous), (0, 4));
+    }
+
+    #[test]
+    fn demos_never_set_a_record() {
+        let mut score = Score::default();
+        score.value = 7;
+        score.demo_over();
+        assert_eq!((score.value, score.previous), (0, 7));
+        assert_eq!(score.highest(GameMode::Classic), 0);
+
+        let mut world = world(GameMode::Classic, 7);
+        world.resource_mut::<Autopilot>().enabled = true;
+        world.run_system_once(update_score);
+        assert_eq!(world.resource::<Score>().highest(GameMode::Classic), 0);
+    }
+
+    #[test]
+    fn zen_games_record_when_they_start_over() {
+        let mut world = self::world(GameMode::Zen, 4);
+        world.run_system_once(reset_score);
+        let score = world.resource::<Score>();
+        assert_eq!(score.highest(GameMode::Zen), 4);
+        assert_eq!(score.value, 0);
+
+        //  NOTE: other modes record at game over, starting over just clears the score
+        let mut world = self::world(GameMode::Classic, 4);
+        world.run_system_once(reset_score);
+        let score = world.resource::<Score>();
+        assert_eq!(score.highest(GameMode::Classic), 0);
+        assert_eq!(score.value, 0);
+    }
+}
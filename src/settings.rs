@@ -0,0 +1,499 @@
+use std::time::Duration;
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    protocol::DEFAULT_SERVER, royale::DEFAULT_ROYALE_SNAKES, sprite_pack::DEFAULT_SPRITE_PACK,
+    stream::DEFAULT_STREAM_NAME, theme::DEFAULT_THEME,
+};
+
+pub const DEFAULT_BOARD_SIZE: i32 = 20;
+//  NOTE: the snake spawns 5 cells left of the center and the first food 5 cells right of it
+pub const MIN_BOARD_SIZE: i32 = 14;
+pub const MAX_BOARD_SIZE: i32 = 40;
+pub const MIN_UI_SCALE: f32 = 0.75;
+pub const MAX_UI_SCALE: f32 = 2.0;
+
+#[derive(Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub gameplay: GameplaySettings,
+    pub video: VideoSettings,
+    pub controls: ControlSettings,
+    pub audio: AudioSettings,
+    pub accessibility: AccessibilitySettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameplaySettings {
+    //  NOTE: picked in the menu, see `mode.rs`
+    pub mode: GameMode,
+    //  NOTE: applied on the next `NewGame`
+    pub board_size: i32,
+    //  NOTE: applied on the next `NewGame`
+    pub speed: Speed,
+    pub walls: WallsMode,
+    //  NOTE: the bot behind the "Watch AI" menu entry
+    pub ai: AiPlayer,
+    //  NOTE: `snake-server` address for "Play online"
+    pub server: String,
+    //  NOTE: where local games are streamed to, see `spectate.rs`
+    pub broadcast: BroadcastTarget,
+    //  NOTE: the stream to broadcast on and to watch with "Spectate"
+    pub stream_name: String,
+    //  NOTE: snakes in a battle royale, the player included
+    pub royale_snakes: usize,
+    //  NOTE: players sharing the keyboard in a battle royale, the first snakes are theirs
+    pub royale_humans: usize,
+    //  NOTE: how many obstacles "Random arena" puts on the board, see `arena.rs`
+    pub arena: ArenaDifficulty,
+    //  NOTE: replays the arena with this seed, a new one every time when empty
+    pub arena_seed: String,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::default(),
+            board_size: DEFAULT_BOARD_SIZE,
+            speed: Speed::default(),
+            walls: WallsMode::default(),
+            ai: AiPlayer::default(),
+            server: DEFAULT_SERVER.to_string(),
+            broadcast: BroadcastTarget::default(),
+            stream_name: DEFAULT_STREAM_NAME.to_string(),
+            royale_snakes: DEFAULT_ROYALE_SNAKES,
+            royale_humans: 1,
+            arena: ArenaDifficulty::default(),
+            arena_seed: String::new(),
+        }
+    }
+}
+
+impl GameplaySettings {
+    //  NOTE: the walls games are played with, zen games always wrap around
+    pub fn walls(&self) -> WallsMode {
+        match self.mode {
+            GameMode::Zen => WallsMode::Wrap,
+            _ => self.walls,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub enum GameMode {
+    //  NOTE: endless, until the snake crashes
+    #[default]
+    Classic,
+    //  NOTE: as many apples as possible before the clock runs out
+    TimeAttack,
+    //  NOTE: food goes off after a while and the snake starves if it goes too long without eating
+    Survival,
+    //  NOTE: nothing kills the snake, it just grows
+    Zen,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::Classic,
+        GameMode::TimeAttack,
+        GameMode::Survival,
+        GameMode::Zen,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Classic => "Classic",
+            Self::TimeAttack => "Time attack",
+            Self::Survival => "Survival",
+            Self::Zen => "Zen",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub enum Speed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+impl Speed {
+    pub const ALL: [Speed; 3] = [Speed::Slow, Speed::Normal, Speed::Fast];
+
+    //  NOTE: time between two snake moves
+    pub fn step(&self) -> Duration {
+        match self {
+            Self::Slow => Duration::from_millis(150),
+            Self::Normal => Duration::from_millis(100),
+            Self::Fast => Duration::from_millis(65),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Slow => "Slow",
+            Self::Normal => "Normal",
+            Self::Fast => "Fast",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub enum WallsMode {
+    //  NOTE: hitting a wall ends the game
+    #[default]
+    Solid,
+    //  NOTE: leaving the board on one side enters it on the opposite side
+    Wrap,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AiPlayer {
+    //  NOTE: shortest safe path to the food, see `autopilot.rs`
+    #[default]
+    Pathfinding,
+    //  NOTE: follows a cycle through every cell, slow but finishes the board, see `hamiltonian.rs`
+    Hamiltonian,
+    //  NOTE: a genome trained by `snake-evolve`, see `neuro.rs`
+    Neural,
+}
+
+impl AiPlayer {
+    pub const ALL: [AiPlayer; 3] = [
+        AiPlayer::Pathfinding,
+        AiPlayer::Hamiltonian,
+        AiPlayer::Neural,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Pathfinding => "Pathfinding",
+            Self::Hamiltonian => "Hamiltonian",
+            Self::Neural => "Neural network",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BroadcastTarget {
+    #[default]
+    Off,
+    //  NOTE: to spectators through the online server
+    Server,
+    //  NOTE: to `STREAM_FILE`, native builds only
+    File,
+}
+
+impl BroadcastTarget {
+    pub const ALL: [BroadcastTarget; 3] = [
+        BroadcastTarget::Off,
+        BroadcastTarget::Server,
+        BroadcastTarget::File,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Server => "Server",
+            Self::File => "File",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArenaDifficulty {
+    //  NOTE: a few short bars
+    Easy,
+    #[default]
+    Normal,
+    //  NOTE: a maze of walls between evenly spaced posts
+    Hard,
+}
+
+impl ArenaDifficulty {
+    pub const ALL: [ArenaDifficulty; 3] = [
+        ArenaDifficulty::Easy,
+        ArenaDifficulty::Normal,
+        ArenaDifficulty::Hard,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Easy => "Easy",
+            Self::Normal => "Normal",
+            Self::Hard => "Hard",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CameraMode {
+    //  NOTE: follow the snake on boards too large to show whole, like battle royales
+    #[default]
+    Auto,
+    //  NOTE: always show the whole board
+    Fit,
+    //  NOTE: always follow the snake, zoomed in as far as the scroll wheel or a pinch says
+    Follow,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 3] = [CameraMode::Auto, CameraMode::Fit, CameraMode::Follow];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Auto => "Auto",
+            Self::Fit => "Whole board",
+            Self::Follow => "Follow",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoSettings {
+    //  NOTE: `name` of one of the files in `assets/themes`
+    pub theme: String,
+    //  NOTE: `name` of one of the manifests in `assets/sprite_packs`
+    pub sprite_pack: String,
+    pub grid_lines: bool,
+    //  NOTE: glide between cells instead of jumping once per movement tick
+    pub interpolation: bool,
+    pub camera: CameraMode,
+    //  NOTE: seconds the snake takes to disappear after crashing, 0 skips straight to game over
+    pub death_animation: f32,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            theme: DEFAULT_THEME.to_string(),
+            sprite_pack: DEFAULT_SPRITE_PACK.to_string(),
+            grid_lines: false,
+            interpolation: true,
+            camera: CameraMode::default(),
+            death_animation: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlSettings {
+    //  NOTE: swipe anywhere on the screen to turn the snake
+    pub swipe: bool,
+    //  NOTE: on-screen directional pad drawn below the board
+    pub dpad: bool,
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        Self {
+            swipe: true,
+            dpad: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub sfx: f32,
+    pub music: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 0.8,
+            sfx: 1.0,
+            music: 0.5,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn sfx_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master * self.sfx
+        }
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master * self.music
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    //  NOTE: high-visibility backplate behind the snake head and the food
+    pub outlines: bool,
+    //  NOTE: shape overlay on food so it doesn't rely on colour alone
+    pub food_patterns: bool,
+    //  NOTE: no gliding between cells and no screen effects
+    pub reduced_motion: bool,
+    //  NOTE: egui scale factor, independent of the camera breakpoints
+    pub ui_scale: f32,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            outlines: false,
+            food_patterns: false,
+            reduced_motion: false,
+            ui_scale: 1.0,
+        }
+    }
+}
+
+impl Settings {
+    //  NOTE: the settings file can be edited by hand, keep what's loaded to what the menus offer
+    fn clamped(mut self) -> Self {
+        let gameplay = &mut self.gameplay;
+        gameplay.board_size = gameplay.board_size.clamp(MIN_BOARD_SIZE, MAX_BOARD_SIZE);
+        let scale = self.accessibility.ui_scale;
+        self.accessibility.ui_scale = if scale.is_nan() {
+            AccessibilitySettings::default().ui_scale
+        } else {
+            scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+        };
+        self
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(storage::load().map(Settings::clamped).unwrap_or_default())
+            .add_systems(Last, persist_settings);
+    }
+}
+
+//  NOTE: how long the settings have to stay put before they're written, a slider drag changes
+//  them every frame
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+struct PendingSave {
+    //  NOTE: what's on disk, `None` until the first frame
+    saved: Option<Settings>,
+    //  NOTE: the latest edit and when it was made
+    edited: Option<(Settings, Duration)>,
+}
+
+//  NOTE: egui hands out `&mut` to the settings every frame the menu is open, so change detection
+//  is always tripped. Compare against what was last written instead. Real time, the pause menu
+//  stops the virtual clock.
+fn persist_settings(
+    settings: Res<Settings>,
+    time: Res<Time<Real>>,
+    mut exit_events: EventReader<AppExit>,
+    mut pending: Local<PendingSave>,
+) {
+    let exiting = exit_events.read().count() > 0;
+    if pending.saved.is_none() {
+        pending.saved = Some(settings.clone());
+    }
+    if pending.saved.as_ref() == Some(&*settings) {
+        pending.edited = None;
+        return;
+    }
+
+    let now = time.elapsed();
+    let edited_at = match &pending.edited {
+        Some((edited, at)) if edited == &*settings => *at,
+        _ => {
+            pending.edited = Some((settings.clone(), now));
+            now
+        }
+    };
+    if exiting || now - edited_at >= SAVE_DELAY {
+        storage::save(&settings);
+        pending.saved = Some(settings.clone());
+        pending.edited = None;
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use super::Settings;
+
+    const SETTINGS_FILE: &str = "settings.ron";
+
+    pub fn load() -> Option<Settings> {
+        let contents = std::fs::read_to_string(SETTINGS_FILE).ok()?;
+        ron::from_str(&contents).ok()
+    }
+
+    pub fn save(settings: &Settings) {
+        let Ok(contents) = ron::ser::to_string_pretty(settings, Default::default()) else {
+            return;
+        };
+        if let Err(err) = std::fs::write(SETTINGS_FILE, contents) {
+            bevy::log::warn!("could not save settings: {err}");
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use super::Settings;
+
+    const SETTINGS_KEY: &str = "snake.settings";
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn load() -> Option<Settings> {
+        let contents = local_storage()?.get_item(SETTINGS_KEY).ok()??;
+        ron::from_str(&contents).ok()
+    }
+
+    pub fn save(settings: &Settings) {
+        let Ok(contents) = ron::to_string(settings) else {
+            return;
+        };
+        if let Some(storage) = local_storage() {
+            let _ = storage.set_item(SETTINGS_KEY, &contents);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hand_edited_values_are_clamped() {
+        let loaded: Settings =
+            ron::from_str("(gameplay: (board_size: 1000), accessibility: (ui_scale: 0.01))")
+                .unwrap();
+        let settings = loaded.clamped();
+        assert_eq!(settings.gameplay.board_size, MAX_BOARD_SIZE);
+        assert_eq!(settings.accessibility.ui_scale, MIN_UI_SCALE);
+
+        let mut loaded = Settings::default();
+        loaded.gameplay.board_size = -3;
+        loaded.accessibility.ui_scale = f32::NAN;
+        let settings = loaded.clamped();
+        assert_eq!(settings.gameplay.board_size, MIN_BOARD_SIZE);
+        assert_eq!(settings.accessibility.ui_scale, 1.0);
+    }
+}
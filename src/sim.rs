@@ -0,0 +1,547 @@
+use std::collections::{HashSet, VecDeque};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    hazard::{move_enemies, Enemy},
+    level::Level,
+    mode::{FOOD_LIFETIME, STARVE_AFTER, TIME_ATTACK_LENGTH},
+    settings::{GameMode, Speed, WallsMode, DEFAULT_BOARD_SIZE},
+    snake::{Direction, Position},
+    util::{food_position, snake_starting_position},
+};
+
+//  NOTE: the board as the rules see it, shared by the bevy systems, the bots and the headless games
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid {
+    pub size: i32,
+    pub walls: WallsMode,
+}
+
+impl Grid {
+    pub fn contains(&self, pos: Position) -> bool {
+        (0..self.size).contains(&pos.x) && (0..self.size).contains(&pos.y)
+    }
+
+    //  NOTE: where the head lands moving one cell in `direction`, with wrapping walls it re-enters
+    //  the board on the opposite side, with solid walls it can end up outside of it
+    pub fn advance(&self, pos: Position, direction: Direction) -> Position {
+        let next = match direction {
+            Direction::Up => Position::new(pos.x, pos.y + 1),
+            Direction::Down => Position::new(pos.x, pos.y - 1),
+            Direction::Left => Position::new(pos.x - 1, pos.y),
+            Direction::Right => Position::new(pos.x + 1, pos.y),
+        };
+
+        match self.walls {
+            WallsMode::Wrap => {
+                Position::new(next.x.rem_euclid(self.size), next.y.rem_euclid(self.size))
+            }
+            WallsMode::Solid => next,
+        }
+    }
+
+    //  NOTE: like `advance` but `None` when the move would leave the board
+    pub fn step(&self, pos: Position, direction: Direction) -> Option<Position> {
+        let next = self.advance(pos, direction);
+        self.contains(next).then_some(next)
+    }
+
+    pub fn cells(&self) -> usize {
+        (self.size * self.size) as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    pub grid: Grid,
+    //  NOTE: end the game after this many moves without eating, keeps looping bots from running forever
+    pub starvation: u32,
+    //  NOTE: played instead of the open board, with its walls, portals and hazards
+    pub level: Option<Level>,
+    pub mode: GameMode,
+    //  NOTE: the mode clocks and the spikes run on seconds, counted in moves at this speed
+    pub speed: Speed,
+}
+
+impl Rules {
+    pub fn new(board_size: i32, walls: WallsMode) -> Self {
+        let grid = Grid {
+            size: board_size,
+            walls,
+        };
+        Self {
+            grid,
+            starvation: grid.cells() as u32 * 4,
+            level: None,
+            mode: GameMode::default(),
+            speed: Speed::default(),
+        }
+    }
+
+    //  NOTE: the board takes the level's size
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.grid.size = level.size;
+        self.starvation = self.grid.cells() as u32 * 4;
+        self.level = Some(level);
+        self
+    }
+
+    //  NOTE: zen games wrap around the board, like `GameplaySettings::walls`
+    pub fn with_mode(mut self, mode: GameMode) -> Self {
+        if mode == GameMode::Zen {
+            self.grid.walls = WallsMode::Wrap;
+        }
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: Speed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    //  NOTE: how many moves the snake makes in `seconds`
+    pub fn moves(&self, seconds: f32) -> u32 {
+        (seconds / self.speed.step().as_secs_f32()).round() as u32
+    }
+
+    fn level(&self) -> Option<&Level> {
+        self.level.as_ref()
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::new(DEFAULT_BOARD_SIZE, WallsMode::default())
+    }
+}
+
+//  NOTE: the rules of a single move. `Game` plays by these, and so do `update_position`,
+//  `collision_detection`, `apply_eat_food` and the versus and royale games
+
+//  NOTE: turning back into the neck is ignored, like `queue_direction` does
+pub fn turn(heading: Direction, wanted: Direction) -> Direction {
+    if wanted == heading.opposite() {
+        heading
+    } else {
+        wanted
+    }
+}
+
+//  NOTE: where the head lands moving in `direction`. Moving onto a portal jumps to its other end,
+//  keeping the direction, the segments just take the cell in front of them and follow through
+pub fn next_head(
+    grid: &Grid,
+    level: Option<&Level>,
+    head: Position,
+    direction: Direction,
+) -> Position {
+    let next = grid.advance(head, direction);
+    level
+        .and_then(|level| level.portal_exit(next))
+        .unwrap_or(next)
+}
+
+//  NOTE: moves the snake's head to `head` and every segment into the cell in front of it, returns
+//  the cell the tail left. That's where the new segment goes when the snake eats
+pub fn slide(body: &mut VecDeque<Position>, head: Position) -> Position {
+    body.push_front(head);
+    body.pop_back().expect("the head was just pushed")
+}
+
+//  NOTE: what the head ran into, checked in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crash {
+    //  NOTE: left the board through a solid wall
+    Edge,
+    Wall,
+    //  NOTE: index into the enemies or the segments that were checked
+    Enemy(usize),
+    Body(usize),
+}
+
+//  NOTE: whether the head that just moved from `head_prev` to `head` crashed into anything.
+//  `segments` are the ones behind the head, its own and other snakes' alike
+pub fn crash<'a>(
+    grid: &Grid,
+    level: Option<&Level>,
+    head: Position,
+    head_prev: Position,
+    segments: impl IntoIterator<Item = &'a Position>,
+    enemies: impl IntoIterator<Item = &'a Enemy>,
+) -> Option<Crash> {
+    if !grid.contains(head) {
+        return Some(Crash::Edge);
+    }
+    if level.is_some_and(|level| level.is_wall(head)) {
+        return Some(Crash::Wall);
+    }
+    if let Some(enemy) = enemies
+        .into_iter()
+        .position(|enemy| enemy.hits(head, head_prev))
+    {
+        return Some(Crash::Enemy(enemy));
+    }
+    segments
+        .into_iter()
+        .position(|&segment| segment == head)
+        .map(Crash::Body)
+}
+
+//  NOTE: where the next food goes, one of the level's spawns when it has free ones. `None` when
+//  the snake covers the whole board
+pub fn place_food(
+    grid: &Grid,
+    level: Option<&Level>,
+    occupied: &HashSet<Position>,
+    rng: &mut impl Rng,
+) -> Option<Position> {
+    match level {
+        Some(level) => level.food_position(occupied, rng),
+        None => food_position(grid.size, occupied, rng),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    Wall,
+    Snake,
+    Enemy,
+    Starvation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Moved,
+    Ate,
+    //  NOTE: the snake fills the whole board, there is nowhere left to spawn food
+    Won,
+    //  NOTE: the time attack clock ran out
+    TimeUp,
+    Died(DeathCause),
+}
+
+impl Outcome {
+    pub fn is_over(&self) -> bool {
+        matches!(self, Self::Won | Self::TimeUp | Self::Died(_))
+    }
+}
+
+//  NOTE: one game of snake without bevy, levels and modes included. It plays by the same rules
+//  as `update_position`, the hazards, `collision_detection` and `apply_eat_food`, in that order,
+//  so headless runs play like the real thing
+#[derive(Debug, Clone)]
+pub struct Game {
+    rules: Rules,
+    //  NOTE: head first
+    snake: VecDeque<Position>,
+    direction: Direction,
+    food: Option<Position>,
+    enemies: Vec<Enemy>,
+    rng: StdRng,
+    score: u32,
+    moves: u32,
+    moves_since_food: u32,
+    //  NOTE: moves since the food was placed, it goes off in survival games
+    food_age: u32,
+    outcome: Option<Outcome>,
+}
+
+impl Game {
+    pub fn new(rules: Rules, seed: u64) -> Self {
+        let size = rules.grid.size;
+        let mut rng = StdRng::seed_from_u64(seed);
+        //  NOTE: same spots as `spawn_snake` and `spawn_food`
+        let (snake, direction, food, enemies) = match rules.level() {
+            Some(level) => {
+                let snake = level.snake();
+                let occupied = snake.iter().copied().collect();
+                let food = level.food_position(&occupied, &mut rng);
+                let enemies = level.hazards.iter().map(Enemy::new).collect();
+                (snake, level.direction, food, enemies)
+            }
+            None => (
+                snake_starting_position(size),
+                Direction::Right,
+                Some(Position::new(size / 2 + 5, size / 2)),
+                Vec::new(),
+            ),
+        };
+        Self {
+            rules,
+            snake: snake.into(),
+            direction,
+            food,
+            enemies,
+            rng,
+            score: 0,
+            moves: 0,
+            moves_since_food: 0,
+            food_age: 0,
+            outcome: None,
+        }
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.rules.grid
+    }
+
+    pub fn snake(&self) -> &VecDeque<Position> {
+        &self.snake
+    }
+
+    pub fn head(&self) -> Position {
+        self.snake[0]
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn food(&self) -> Option<Position> {
+        self.food
+    }
+
+    pub fn enemies(&self) -> &[Enemy] {
+        &self.enemies
+    }
+
+    //  NOTE: the level's walls and portals and where the enemies are, what `autopilot_controls`
+    //  steers around
+    pub fn obstacles(&self) -> HashSet<Position> {
+        let level = self.rules.level();
+        level
+            .map(|level| level.walls.iter().chain(level.portals.iter().flatten()))
+            .into_iter()
+            .flatten()
+            .copied()
+            .chain(self.enemies.iter().map(Enemy::position))
+            .collect()
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn moves(&self) -> u32 {
+        self.moves
+    }
+
+    //  NOTE: `None` while the game is still running
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn step(&mut self, direction: Direction) -> Outcome {
+        if let Some(outcome) = self.outcome {
+            return outcome;
+        }
+
+        let grid = self.rules.grid;
+        let level = self.rules.level();
+        self.direction = turn(self.direction, direction);
+        let head_prev = self.head();
+        let head = next_head(&grid, level, head_prev, self.direction);
+        let vacated = slide(&mut self.snake, head);
+        self.moves += 1;
+        self.moves_since_food += 1;
+        self.food_age += 1;
+
+        let step = self.rules.speed.step();
+        let snake: &[Position] = self.snake.make_contiguous();
+        move_enemies(self.enemies.iter_mut(), &grid, level, snake);
+        for enemy in &mut self.enemies {
+            enemy.tick(step);
+        }
+
+        //  NOTE: nothing kills the snake in zen games
+        let crashed = match self.rules.mode {
+            GameMode::Zen => None,
+            _ => crash(
+                &grid,
+                level,
+                head,
+                head_prev,
+                self.snake.iter().skip(1),
+                &self.enemies,
+            ),
+        };
+        let mut outcome = match crashed {
+            Some(Crash::Edge | Crash::Wall) => Outcome::Died(DeathCause::Wall),
+            Some(Crash::Enemy(_)) => Outcome::Died(DeathCause::Enemy),
+            Some(Crash::Body(_)) => Outcome::Died(DeathCause::Snake),
+            None if self.food == Some(head) => self.eat(vacated),
+            None => Outcome::Moved,
+        };
+
+        if !outcome.is_over() {
+            if self.moves_since_food >= self.starvation() {
+                outcome = Outcome::Died(DeathCause::Starvation);
+            } else if self.rules.mode == GameMode::TimeAttack
+                && self.moves >= self.rules.moves(TIME_ATTACK_LENGTH)
+            {
+                outcome = Outcome::TimeUp;
+            } else if self.rules.mode == GameMode::Survival
+                && self.food_age >= self.rules.moves(FOOD_LIFETIME)
+            {
+                //  NOTE: like `expire_food`, the food can't turn up where it was
+                let mut occupied: HashSet<Position> = self.snake.iter().copied().collect();
+                occupied.extend(self.food);
+                let level = self.rules.level();
+                if let Some(food) = place_food(&grid, level, &occupied, &mut self.rng) {
+                    self.food = Some(food);
+                }
+                self.food_age = 0;
+            }
+        }
+
+        if outcome.is_over() {
+            self.outcome = Some(outcome);
+        }
+        outcome
+    }
+
+    //  NOTE: survival games starve the snake well before the cut off for looping bots
+    fn starvation(&self) -> u32 {
+        match self.rules.mode {
+            GameMode::Survival => self.rules.starvation.min(self.rules.moves(STARVE_AFTER)),
+            _ => self.rules.starvation,
+        }
+    }
+
+    fn eat(&mut self, vacated: Position) -> Outcome {
+        //  NOTE: the new segment takes the cell the tail just left
+        self.snake.push_back(vacated);
+        self.score += 1;
+        self.moves_since_food = 0;
+        self.food_age = 0;
+
+        let occupied: HashSet<Position> = self.snake.iter().copied().collect();
+        self.food = place_food(
+            &self.rules.grid,
+            self.rules.level(),
+            &occupied,
+            &mut self.rng,
+        );
+        match self.food {
+            Some(_) => Outcome::Ate,
+            None => Outcome::Won,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Hazard;
+
+    //  NOTE: round and round a 2x2 square next to where the snake starts, away from the food
+    const CIRCLE: [Direction; 4] = [
+        Direction::Up,
+        Direction::Left,
+        Direction::Down,
+        Direction::Right,
+    ];
+
+    fn circle(game: &mut Game) -> Outcome {
+        for &direction in CIRCLE.iter().cycle() {
+            let outcome = game.step(direction);
+            if outcome.is_over() {
+                return outcome;
+            }
+        }
+        unreachable!("the directions cycle forever")
+    }
+
+    //  NOTE: an open level of the default size, the snake starts at (5, 10) heading right
+    fn level() -> Level {
+        Level::new("Test", DEFAULT_BOARD_SIZE)
+    }
+
+    #[test]
+    fn level_walls_kill() {
+        let mut level = level();
+        level.set_wall(Position::new(7, 10), true);
+        let mut game = Game::new(Rules::default().with_level(level), 0);
+
+        assert_eq!(game.step(Direction::Right), Outcome::Moved);
+        assert_eq!(game.step(Direction::Right), Outcome::Died(DeathCause::Wall));
+    }
+
+    #[test]
+    fn portals_carry_the_head() {
+        let mut level = level();
+        level.add_portal(Position::new(6, 10), Position::new(2, 15));
+        let mut game = Game::new(Rules::default().with_level(level), 0);
+
+        game.step(Direction::Right);
+        assert_eq!(game.head(), Position::new(2, 15));
+        game.step(Direction::Right);
+        assert_eq!(game.head(), Position::new(3, 15));
+        assert_eq!(game.snake()[1], Position::new(2, 15));
+    }
+
+    #[test]
+    fn hazards_kill() {
+        let mut level = level();
+        level.add_hazard(Hazard::Spikes(Position::new(6, 10)));
+        let mut game = Game::new(Rules::default().with_level(level.clone()), 0);
+        assert_eq!(
+            game.step(Direction::Right),
+            Outcome::Died(DeathCause::Enemy)
+        );
+
+        //  NOTE: walking towards each other, the patrol and the head swap cells
+        level.hazards = vec![Hazard::Patrol([Position::new(6, 10), Position::new(5, 10)])];
+        let mut game = Game::new(Rules::default().with_level(level), 0);
+        assert_eq!(
+            game.step(Direction::Right),
+            Outcome::Died(DeathCause::Enemy)
+        );
+    }
+
+    #[test]
+    fn time_attack_runs_out() {
+        let rules = Rules::default().with_mode(GameMode::TimeAttack);
+        let length = rules.moves(TIME_ATTACK_LENGTH);
+        let mut game = Game::new(rules, 0);
+
+        assert_eq!(circle(&mut game), Outcome::TimeUp);
+        assert_eq!(game.moves(), length);
+    }
+
+    #[test]
+    fn survival_starves() {
+        let rules = Rules::default()
+            .with_mode(GameMode::Survival)
+            .with_speed(Speed::Fast);
+        let hunger = rules.moves(STARVE_AFTER);
+        let mut game = Game::new(rules, 0);
+
+        assert_eq!(circle(&mut game), Outcome::Died(DeathCause::Starvation));
+        assert_eq!(game.moves(), hunger);
+    }
+
+    #[test]
+    fn zen_never_crashes() {
+        let mut level = level();
+        level.set_wall(Position::new(5, 12), true);
+        let rules = Rules::new(DEFAULT_BOARD_SIZE, WallsMode::Solid)
+            .with_level(level)
+            .with_mode(GameMode::Zen);
+        let mut game = Game::new(rules, 0);
+
+        //  NOTE: through the wall and round the board, then back into the body
+        for _ in 0..DEFAULT_BOARD_SIZE * 2 {
+            assert!(!game.step(Direction::Up).is_over());
+        }
+        for &direction in &CIRCLE {
+            assert!(!game.step(direction).is_over());
+        }
+    }
+}
@@ -1,10 +1,17 @@
 use bevy::prelude::*;
-use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, time::Duration};
 
 use crate::{
-    asset_loader::{ImageAssets, SpritePart},
+    asset_loader::SpritePart,
+    autopilot::AiControlled,
     board::{Board, TILE_SIZE},
-    schedule::InGameSet,
+    level::CurrentLevel,
+    schedule::{InGameSet, SteerSet},
+    settings::Settings,
+    sim::{next_head, slide, turn},
+    spectate::spectating,
+    sprite_pack::CurrentSpritePack,
     state::{GameState, MenuState},
     util::{direction_from_vec2, snake_starting_position},
 };
@@ -26,24 +33,39 @@ impl SnakeHeadDirection {
             self.directions.push(new_direction);
         }
     }
+
+    //  NOTE: the direction the head moved in on the last tick
+    pub fn current(&self) -> Direction {
+        self.current
+    }
+
+    pub fn has_queued(&self) -> bool {
+        !self.directions.is_empty()
+    }
 }
 
-impl Default for SnakeHeadDirection {
-    fn default() -> Self {
+impl SnakeHeadDirection {
+    pub fn new(direction: Direction) -> Self {
         SnakeHeadDirection {
-            current: Direction::default(),
-            directions: vec![Direction::Right],
+            current: direction,
+            directions: vec![direction],
         }
     }
 }
 
+impl Default for SnakeHeadDirection {
+    fn default() -> Self {
+        Self::new(Direction::Right)
+    }
+}
+
 #[derive(Component, Debug)]
 pub struct SnakeHead;
 
 #[derive(Component, Debug)]
 pub struct SnakeSegment;
 
-#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -55,12 +77,58 @@ impl Position {
     }
 }
 
+//  NOTE: where a segment was before the last movement tick, used to glide between cells
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+pub struct PreviousPosition(pub Position);
+
 #[derive(Resource, Debug, Default)]
 pub struct MovementTimer {
     timer: Timer,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+impl MovementTimer {
+    //  NOTE: time between two snake moves
+    pub fn step(&self) -> Duration {
+        self.timer.duration()
+    }
+
+    //  NOTE: how far along the current step is, from 0.0 to 1.0
+    pub fn fraction(&self) -> f32 {
+        self.timer.fraction()
+    }
+
+    //  NOTE: the snake moved this frame
+    pub fn just_moved(&self) -> bool {
+        self.timer.just_finished()
+    }
+
+    //  NOTE: for moves made elsewhere, like a spectated game, glide over one `step` and then
+    //  wait for the next move
+    pub fn follow(&mut self, step: Duration) {
+        self.timer = Timer::new(step, TimerMode::Once);
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct TurnEvent {
+    pub direction: Direction,
+}
+
+//  NOTE: a direction asked for with the keyboard, a swipe or the D-pad. The local snake, online
+//  matches and battle royales all steer by these, `TurnEvent` is sent once the snake turns
+#[derive(Event, Debug)]
+pub struct SteerEvent {
+    pub direction: Direction,
+    //  NOTE: tells two players on one keyboard apart. WASD, swipes and the D-pad are seat 0 and
+    //  the arrow keys seat 1, games with a single player take every seat
+    pub seat: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Down,
@@ -70,7 +138,9 @@ pub enum Direction {
 }
 
 impl Direction {
-    fn opposite(&self) -> Self {
+    pub const ALL: [Direction; 4] = [Self::Up, Self::Down, Self::Left, Self::Right];
+
+    pub fn opposite(&self) -> Self {
         match self {
             Self::Up => Self::Down,
             Self::Down => Self::Up,
@@ -90,46 +160,85 @@ pub struct SnakePlugin;
 impl Plugin for SnakePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<SnakeDirectionQueue>()
+            .add_event::<TurnEvent>()
+            .add_event::<SteerEvent>()
             .insert_resource(MovementTimer {
                 timer: Timer::from_seconds(0.1, TimerMode::Repeating),
             })
             .add_systems(OnEnter(GameState::NewGame), spawn_snake)
             .add_systems(OnEnter(GameState::NewGame), load_snake_direction_queue)
+            .add_systems(OnEnter(GameState::NewGame), reset_movement_timer)
             .add_systems(
                 Update,
-                (movement_controls, touch_movement_controls).in_set(InGameSet::UserInput),
+                (keyboard_steering, touch_movement_controls)
+                    .in_set(SteerSet)
+                    .run_if(in_state(GameState::Playing)),
             )
+            .add_systems(Update, movement_controls.in_set(InGameSet::UserInput))
             .add_systems(
                 Update,
                 (update_position, update_board_position, update_snake_sprite)
                     .chain()
                     .in_set(InGameSet::PositionUpdates),
+            )
+            //  NOTE: re-pick the atlas indices when another sprite pack is selected mid-game
+            .add_systems(
+                Update,
+                update_snake_sprite.run_if(resource_exists_and_changed::<CurrentSpritePack>),
+            )
+            //  NOTE: spectated games are drawn the same way, their positions come from the stream
+            .add_systems(
+                Update,
+                (update_board_position, update_snake_sprite)
+                    .chain()
+                    .run_if(spectating),
             );
     }
 }
 
-fn load_snake_direction_queue(mut snake_direction_queue: ResMut<SnakeDirectionQueue>) {
+fn load_snake_direction_queue(
+    mut snake_direction_queue: ResMut<SnakeDirectionQueue>,
+    level: Res<CurrentLevel>,
+) {
+    let direction = level
+        .0
+        .as_ref()
+        .map_or(Direction::Right, |level| level.direction);
     *snake_direction_queue = SnakeDirectionQueue {
-        directions: VecDeque::from([Direction::Right; 3]),
+        directions: VecDeque::from([direction; 3]),
     };
 }
 
-fn spawn_snake(mut commands: Commands, board: Res<Board>, assets: Res<ImageAssets>) {
-    let start_pos = snake_starting_position(board.size);
+fn reset_movement_timer(mut movement_timer: ResMut<MovementTimer>, settings: Res<Settings>) {
+    movement_timer.timer = Timer::new(settings.gameplay.speed.step(), TimerMode::Repeating);
+}
+
+fn spawn_snake(
+    mut commands: Commands,
+    board: Res<Board>,
+    sprites: Res<CurrentSpritePack>,
+    level: Res<CurrentLevel>,
+) {
+    //  NOTE: levels pick where the snake starts and which way it's heading
+    let (start_pos, direction) = match &level.0 {
+        Some(level) => (level.snake(), level.direction),
+        None => (snake_starting_position(board.size), Direction::Right),
+    };
+    let directions = VecDeque::from([direction; 3]);
 
     // NOTE: load snake head
     commands.spawn((
         SpriteSheetBundle {
             atlas: TextureAtlas {
-                layout: assets.sprite_sheet_layout.clone(),
-                index: SpritePart::HeadRight as usize,
+                layout: sprites.layout.clone(),
+                index: sprites.index(segment_part(&directions, 0)),
             },
             transform: Transform::from_xyz(
                 board.position_translate(start_pos[0].x.into()),
                 board.position_translate(start_pos[0].y.into()),
                 2.0,
             ),
-            texture: assets.sprite_sheet.clone(),
+            texture: sprites.texture.clone(),
             sprite: Sprite {
                 custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
                 ..Default::default()
@@ -139,7 +248,8 @@ fn spawn_snake(mut commands: Commands, board: Res<Board>, assets: Res<ImageAsset
         SnakeHead,
         SnakeSegment,
         Position::from(start_pos[0]),
-        SnakeHeadDirection::default(),
+        PreviousPosition(start_pos[0]),
+        SnakeHeadDirection::new(direction),
     ));
 
     commands.spawn((
@@ -149,19 +259,20 @@ fn spawn_snake(mut commands: Commands, board: Res<Board>, assets: Res<ImageAsset
                 board.position_translate(start_pos[1].y),
                 10.0,
             ),
-            texture: assets.sprite_sheet.clone(),
+            texture: sprites.texture.clone(),
             sprite: Sprite {
                 custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
                 ..default()
             },
             atlas: TextureAtlas {
-                layout: assets.sprite_sheet_layout.clone(),
-                index: SpritePart::BodyHorizontal as usize,
+                layout: sprites.layout.clone(),
+                index: sprites.index(segment_part(&directions, 1)),
             },
             ..default()
         },
         SnakeSegment,
         Position::new(start_pos[1].x, start_pos[1].y),
+        PreviousPosition(start_pos[1]),
     ));
 
     commands.spawn((
@@ -171,32 +282,47 @@ fn spawn_snake(mut commands: Commands, board: Res<Board>, assets: Res<ImageAsset
                 board.position_translate(start_pos[2].y),
                 10.0,
             ),
-            texture: assets.sprite_sheet.clone(),
+            texture: sprites.texture.clone(),
             sprite: Sprite {
                 custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
                 ..default()
             },
             atlas: TextureAtlas {
-                layout: assets.sprite_sheet_layout.clone(),
-                index: SpritePart::TailRight as usize,
+                layout: sprites.layout.clone(),
+                index: sprites.index(segment_part(&directions, 2)),
             },
             ..default()
         },
         SnakeSegment,
         Position::new(start_pos[2].x, start_pos[2].y),
+        PreviousPosition(start_pos[2]),
     ));
 }
 
 fn update_board_position(
     board: Res<Board>,
-    mut query: Query<(&mut Transform, &Position), With<SnakeSegment>>,
+    movement_timer: Res<MovementTimer>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Transform, &Position, &PreviousPosition), With<SnakeSegment>>,
 ) {
-    for (mut transform, pos) in query.iter_mut() {
-        transform.translation = Vec3::new(
+    for (mut transform, pos, PreviousPosition(prev)) in query.iter_mut() {
+        let current = Vec2::new(
             board.position_translate(pos.x),
             board.position_translate(pos.y),
-            1.0,
         );
+        //  NOTE: don't glide across the board when wrapping around a wall or through a portal
+        let adjacent = (pos.x - prev.x).abs() + (pos.y - prev.y).abs() <= 1;
+        let interpolate = settings.video.interpolation && !settings.accessibility.reduced_motion;
+        let translation = if interpolate && adjacent {
+            let previous = Vec2::new(
+                board.position_translate(prev.x),
+                board.position_translate(prev.y),
+            );
+            previous.lerp(current, movement_timer.fraction())
+        } else {
+            current
+        };
+        transform.translation = translation.extend(1.0);
     }
 }
 
@@ -204,16 +330,23 @@ fn update_board_position(
 pub struct TouchMap(pub(crate) Option<u64>);
 
 fn touch_movement_controls(
-    mut snake_head_query: Query<&mut SnakeHeadDirection, With<SnakeHead>>,
+    mut steer_event_writer: EventWriter<SteerEvent>,
     touches: Res<Touches>,
     mut touch_map: Local<TouchMap>,
+    settings: Res<Settings>,
     // mut next_state: ResMut<NextState<GameState>>,
     // game_state: Res<State<GameState>>,
     // menu_state: Res<State<MenuState>>,
 ) {
-    let Ok(mut snake_direction) = snake_head_query.get_single_mut() else {
+    if !settings.controls.swipe {
         return;
-    };
+    }
+
+    //  NOTE: two fingers pinch to zoom, see `camera.rs`
+    if touches.iter().count() > 1 {
+        touch_map.0 = None;
+        return;
+    }
 
     //  NOTE: Check if the touchmap should be cleared.
     if let Some(tm) = touch_map.0 {
@@ -248,8 +381,8 @@ fn touch_movement_controls(
                     //  the abs value of x is greater than the abs value of y, if it is, the
                     //  direction is horizontal.
                     let input = direction_from_vec2(direction);
-                    for dir in input.iter() {
-                        snake_direction.queue_direction(*dir);
+                    for &direction in input.iter() {
+                        steer_event_writer.send(SteerEvent { direction, seat: 0 });
                     }
                 }
             }
@@ -262,63 +395,81 @@ fn touch_movement_controls(
     }
 }
 
-fn movement_controls(
+fn keyboard_steering(
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut snake_head_query: Query<&mut SnakeHeadDirection, With<SnakeHead>>,
+    mut steer_event_writer: EventWriter<SteerEvent>,
+) {
+    for key in keyboard_input.get_just_pressed() {
+        let (direction, seat) = match key {
+            KeyCode::KeyW => (Direction::Up, 0),
+            KeyCode::KeyS => (Direction::Down, 0),
+            KeyCode::KeyA => (Direction::Left, 0),
+            KeyCode::KeyD => (Direction::Right, 0),
+            KeyCode::ArrowUp => (Direction::Up, 1),
+            KeyCode::ArrowDown => (Direction::Down, 1),
+            KeyCode::ArrowLeft => (Direction::Left, 1),
+            KeyCode::ArrowRight => (Direction::Right, 1),
+            _ => continue,
+        };
+        steer_event_writer.send(SteerEvent { direction, seat });
+    }
+}
+
+fn movement_controls(
+    mut steer_event_reader: EventReader<SteerEvent>,
+    mut snake_head_query: Query<&mut SnakeHeadDirection, (With<SnakeHead>, Without<AiControlled>)>,
 ) {
     let Ok(mut snake_direction) = snake_head_query.get_single_mut() else {
+        steer_event_reader.clear();
         return;
     };
 
-    let keys_pressed: Vec<KeyCode> = keyboard_input
-        .get_just_pressed()
-        .filter_map(|key| match key {
-            KeyCode::ArrowUp
-            | KeyCode::ArrowDown
-            | KeyCode::ArrowLeft
-            | KeyCode::ArrowRight
-            | KeyCode::KeyW
-            | KeyCode::KeyA
-            | KeyCode::KeyS
-            | KeyCode::KeyD => Some(*key),
-            _ => None,
-        })
-        .collect();
-
-    //  NOTE: Iterate through the collected keys and queue valid directions
-    for key in keys_pressed {
-        let direction = match key {
-            KeyCode::ArrowUp | KeyCode::KeyW => Direction::Up,
-            KeyCode::ArrowDown | KeyCode::KeyS => Direction::Down,
-            KeyCode::ArrowLeft | KeyCode::KeyA => Direction::Left,
-            KeyCode::ArrowRight | KeyCode::KeyD => Direction::Right,
-            _ => continue,
-        };
-        //  NOTE: new head direction to be queued
+    //  NOTE: new head directions to be queued
+    for &SteerEvent { direction, .. } in steer_event_reader.read() {
         snake_direction.queue_direction(direction);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_position(
     mut movement_timer: ResMut<MovementTimer>,
     time: Res<Time>,
-    mut snake_head_query: Query<(&mut SnakeHeadDirection, &mut Position), With<SnakeHead>>,
-    mut snake_body_query: Query<(&mut Position, &SnakeSegment), Without<SnakeHead>>,
+    board: Res<Board>,
+    settings: Res<Settings>,
+    mut snake_head_query: Query<
+        (
+            &mut SnakeHeadDirection,
+            &mut Position,
+            &mut PreviousPosition,
+        ),
+        With<SnakeHead>,
+    >,
+    mut snake_body_query: Query<
+        (&mut Position, &mut PreviousPosition, &SnakeSegment),
+        Without<SnakeHead>,
+    >,
     mut snake_direction_queue: ResMut<SnakeDirectionQueue>,
+    mut turn_event_writer: EventWriter<TurnEvent>,
+    level: Res<CurrentLevel>,
 ) {
     movement_timer.timer.tick(time.delta());
     if !movement_timer.timer.just_finished() {
         return;
     }
 
-    let Ok((mut snake_head_direction_input, mut head_pos)) = snake_head_query.get_single_mut()
+    let Ok((mut snake_head_direction_input, mut head_pos, mut head_prev_pos)) =
+        snake_head_query.get_single_mut()
     else {
         return;
     };
 
     //  NOTE: check if there's a queued direction and update the current direction also dequeue the first direction
-    if let Some(new_direction) = snake_head_direction_input.directions.get(0) {
-        snake_head_direction_input.current = *new_direction;
+    if let Some(&new_direction) = snake_head_direction_input.directions.first() {
+        let turned = turn(snake_head_direction_input.current, new_direction);
+        if snake_head_direction_input.current != turned {
+            turn_event_writer.send(TurnEvent { direction: turned });
+        }
+        snake_head_direction_input.current = turned;
         snake_head_direction_input.directions.remove(0);
     }
 
@@ -327,74 +478,89 @@ fn update_position(
         .push_front(snake_head_direction_input.current);
     snake_direction_queue.directions.pop_back();
 
-    let mut prev_pos = head_pos.clone();
-
-    match snake_head_direction_input.current {
-        Direction::Up => head_pos.y += 1,
-        Direction::Down => head_pos.y -= 1,
-        Direction::Left => head_pos.x -= 1,
-        Direction::Right => head_pos.x += 1,
-    };
-
-    for (mut segment_pos, _) in snake_body_query.iter_mut() {
-        let temp = *segment_pos;
-        *segment_pos = prev_pos;
-        prev_pos = temp;
+    //  NOTE: the move itself is `sim::next_head` and `sim::slide`, same order as the segments
+    //  are queried, head first and tail last
+    let mut body: VecDeque<Position> = std::iter::once(*head_pos)
+        .chain(snake_body_query.iter().map(|(pos, _, _)| *pos))
+        .collect();
+    let grid = board.grid(settings.gameplay.walls());
+    let head = next_head(
+        &grid,
+        level.0.as_ref(),
+        body[0],
+        snake_head_direction_input.current,
+    );
+    slide(&mut body, head);
+
+    head_prev_pos.0 = *head_pos;
+    *head_pos = body[0];
+    for ((mut segment_pos, mut segment_prev_pos, _), &moved) in
+        snake_body_query.iter_mut().zip(body.iter().skip(1))
+    {
+        segment_prev_pos.0 = *segment_pos;
+        *segment_pos = moved;
     }
 }
 
 fn update_snake_sprite(
     mut snake_query: Query<(&Position, &mut TextureAtlas, Entity), With<SnakeSegment>>,
     direction_queue: Res<SnakeDirectionQueue>,
+    sprites: Res<CurrentSpritePack>,
 ) {
-    //  NOTE: zip an immutable iter from directions and a mutable for the snake_query which contains the sprite.
-    for (i, (direction, (_, mut sprite, _))) in direction_queue
-        .directions
-        .iter()
-        .zip(snake_query.iter_mut())
+    //  NOTE: the queue holds one direction per segment, in the same order as the query. Portals
+    //  keep the direction, so segments on either side of one are drawn like they're next to each
+    //  other
+    for (i, (_, mut sprite, _)) in snake_query
+        .iter_mut()
+        .take(direction_queue.directions.len())
         .enumerate()
     {
-        sprite.index = if i == 0 {
-            match direction {
-                Direction::Up => SpritePart::HeadUp as usize,
-                Direction::Down => SpritePart::HeadDown as usize,
-                Direction::Left => SpritePart::HeadLeft as usize,
-                Direction::Right => SpritePart::HeadRight as usize,
+        sprite.index = sprites.index(segment_part(&direction_queue.directions, i));
+    }
+}
+
+//  NOTE: the sprite for segment `i` of a snake, `directions` holds the direction each segment last
+//  moved in, head first
+pub fn segment_part(directions: &VecDeque<Direction>, i: usize) -> SpritePart {
+    let direction = directions[i];
+    if i == 0 {
+        match direction {
+            Direction::Up => SpritePart::HeadUp,
+            Direction::Down => SpritePart::HeadDown,
+            Direction::Left => SpritePart::HeadLeft,
+            Direction::Right => SpritePart::HeadRight,
+        }
+    } else if i == directions.len() - 1 {
+        //  NOTE: use the segment of the snake that's previous to the snake tail to decide the tails direction
+        match directions[i - 1] {
+            Direction::Up => SpritePart::TailUp,
+            Direction::Down => SpritePart::TailDown,
+            Direction::Left => SpritePart::TailLeft,
+            Direction::Right => SpritePart::TailRight,
+        }
+    } else {
+        match (direction, directions[i - 1]) {
+            (Direction::Up, Direction::Up) | (Direction::Down, Direction::Down) => {
+                SpritePart::BodyVertical
             }
-        } else if i == direction_queue.directions.len() - 1 {
-            //  NOTE: use the segment of the snake that's previous to the snake tail to decide the tails direction
-            let prev_direction = direction_queue.directions[i - 1];
-            match prev_direction {
-                Direction::Up => SpritePart::TailUp as usize,
-                Direction::Down => SpritePart::TailDown as usize,
-                Direction::Left => SpritePart::TailLeft as usize,
-                Direction::Right => SpritePart::TailRight as usize,
+            (Direction::Left, Direction::Left) | (Direction::Right, Direction::Right) => {
+                SpritePart::BodyHorizontal
             }
-        } else {
-            let prev_direction = direction_queue.directions[i - 1];
-            match (direction, prev_direction) {
-                (Direction::Up, Direction::Up) | (Direction::Down, Direction::Down) => {
-                    SpritePart::BodyVertical as usize
-                }
-                (Direction::Left, Direction::Left) | (Direction::Right, Direction::Right) => {
-                    SpritePart::BodyHorizontal as usize
-                }
-                (Direction::Up, Direction::Right) | (Direction::Left, Direction::Down) => {
-                    SpritePart::BodyTopRight as usize
-                }
-                (Direction::Up, Direction::Left) | (Direction::Right, Direction::Down) => {
-                    SpritePart::BodyTopLeft as usize
-                }
-                (Direction::Down, Direction::Right) | (Direction::Left, Direction::Up) => {
-                    SpritePart::BodyBottomRight as usize
-                }
-                (Direction::Down, Direction::Left) | (Direction::Right, Direction::Up) => {
-                    SpritePart::BodyBottomLeft as usize
-                }
-                _ => {
-                    println!("No match");
-                    panic!("No match")
-                }
+            (Direction::Up, Direction::Right) | (Direction::Left, Direction::Down) => {
+                SpritePart::BodyTopRight
+            }
+            (Direction::Up, Direction::Left) | (Direction::Right, Direction::Down) => {
+                SpritePart::BodyTopLeft
+            }
+            (Direction::Down, Direction::Right) | (Direction::Left, Direction::Up) => {
+                SpritePart::BodyBottomRight
+            }
+            (Direction::Down, Direction::Left) | (Direction::Right, Direction::Up) => {
+                SpritePart::BodyBottomLeft
+            }
+            _ => {
+                println!("No match");
+                panic!("No match")
             }
         }
     }
@@ -0,0 +1,634 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    asset_loader::SpritePart,
+    autopilot::Autopilot,
+    board::{Board, LoadBoard, TILE_SIZE},
+    food::Food,
+    online::{transport::Connection, Online},
+    protocol::{ClientMessage, PeerEvent, ServerMessage},
+    score::Score,
+    settings::{BroadcastTarget, Settings},
+    snake::{
+        MovementTimer, Position, PreviousPosition, SnakeDirectionQueue, SnakeHead,
+        SnakeHeadDirection, SnakeSegment,
+    },
+    sprite_pack::CurrentSpritePack,
+    state::{GameState, MenuState},
+    stream::{StreamEvent, StreamSnapshot},
+    theme::{color32, CurrentTheme},
+};
+
+//  NOTE: streams local games as they're played and watches streamed ones read-only, drawn by the
+//  same systems as a local game, see `stream.rs` for what's sent
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpectatorStatus {
+    #[default]
+    Off,
+    //  NOTE: "Spectate" was pressed, opening the socket
+    Connecting,
+    //  NOTE: connected, the stream hasn't started yet
+    Waiting,
+    //  NOTE: from the first snapshot until the next `NewGame`, local game systems are off
+    Watching,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    #[default]
+    Server,
+    //  NOTE: a `STREAM_FILE` played back at the pace it was recorded
+    Recording,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Spectator {
+    status: SpectatorStatus,
+    source: Source,
+    //  NOTE: why the last stream stopped, shown in the menu
+    last_message: Option<&'static str>,
+    //  NOTE: the game as far as the stream got
+    snapshot: Option<StreamSnapshot>,
+    //  NOTE: the last game ended, the next one starts with a snapshot
+    over: bool,
+    //  NOTE: a snapshot replaced the game, the entities are rebuilt instead of moved
+    rebuild: bool,
+    //  NOTE: the snapshot changed since the entities were last updated
+    dirty: bool,
+    recording: VecDeque<StreamEvent>,
+}
+
+impl Spectator {
+    pub fn status(&self) -> SpectatorStatus {
+        self.status
+    }
+
+    pub fn last_message(&self) -> Option<&'static str> {
+        self.last_message
+    }
+
+    pub fn watch(&mut self) {
+        self.reset(SpectatorStatus::Connecting, Source::Server);
+    }
+
+    //  NOTE: plays `STREAM_FILE` back, native builds only
+    pub fn watch_recording(&mut self) {
+        self.reset(SpectatorStatus::Connecting, Source::Recording);
+        match recording::load() {
+            Ok(events) if !events.is_empty() => self.recording = events.into(),
+            Ok(_) => self.stop("The recording is empty"),
+            Err(err) => {
+                warn!("could not read the recording: {err}");
+                self.stop("No recording found");
+            }
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        if self.searching() {
+            self.status = SpectatorStatus::Off;
+        }
+    }
+
+    pub fn searching(&self) -> bool {
+        matches!(
+            self.status,
+            SpectatorStatus::Connecting | SpectatorStatus::Waiting
+        )
+    }
+
+    pub fn watching(&self) -> bool {
+        self.status == SpectatorStatus::Watching
+    }
+
+    fn reset(&mut self, status: SpectatorStatus, source: Source) {
+        *self = Self {
+            status,
+            source,
+            ..default()
+        };
+    }
+
+    fn stop(&mut self, message: &'static str) {
+        self.status = SpectatorStatus::Off;
+        self.last_message = Some(message);
+        self.snapshot = None;
+        self.recording.clear();
+    }
+
+    //  NOTE: `Err` when the stream can't be drawn
+    fn apply(&mut self, event: StreamEvent) -> Result<(), &'static str> {
+        match event {
+            StreamEvent::Snapshot(snapshot) => {
+                if !snapshot.is_valid() {
+                    return Err("The stream sent a broken game");
+                }
+                self.snapshot = Some(snapshot);
+                self.over = false;
+                self.rebuild = true;
+            }
+            StreamEvent::Tick(delta) => {
+                //  NOTE: joined between two games, wait for the next snapshot
+                let Some(snapshot) = &mut self.snapshot else {
+                    return Ok(());
+                };
+                snapshot.apply(&delta);
+                if !snapshot.is_valid() {
+                    return Err("The stream sent a broken game");
+                }
+            }
+            StreamEvent::GameOver => self.over = true,
+        }
+        self.dirty = true;
+        Ok(())
+    }
+}
+
+pub fn spectating(spectator: Res<Spectator>) -> bool {
+    spectator.watching()
+}
+
+//  NOTE: the sockets are `!Send` in the browser so they live in non-send resources
+#[derive(Default)]
+struct SpectatorConnection(Option<Connection>);
+
+#[derive(Default)]
+struct BroadcastSink {
+    //  NOTE: the settings the sink was opened for, it's reopened when they change
+    opened: Option<(BroadcastTarget, String, String)>,
+    connection: Option<Connection>,
+    #[cfg(not(target_arch = "wasm32"))]
+    file: Option<recording::Writer>,
+    //  NOTE: what was sent last, ticks are sent as the difference to it
+    last: Option<StreamSnapshot>,
+}
+
+impl BroadcastSink {
+    fn send(&mut self, event: StreamEvent) {
+        if let Some(connection) = &self.connection {
+            connection.send(ClientMessage::Stream(event.clone()));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(file) = &mut self.file {
+            if let Err(err) = file.write(&event) {
+                warn!("could not write the recording: {err}");
+                self.file = None;
+            }
+        }
+    }
+}
+
+pub struct SpectatePlugin;
+
+impl Plugin for SpectatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Spectator>()
+            .insert_non_send_resource(SpectatorConnection::default())
+            .insert_non_send_resource(BroadcastSink::default())
+            .add_systems(OnEnter(GameState::NewGame), leave_stream)
+            .add_systems(OnEnter(GameState::GameOver), broadcast_game_over)
+            .add_systems(
+                Update,
+                (
+                    open_spectator_connection,
+                    receive_stream,
+                    play_recording,
+                    update_spectated_game,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, update_spectator_hud.run_if(spectating))
+            //  NOTE: after the frame's moves, spawns and food have landed
+            .add_systems(PostUpdate, broadcast_game);
+    }
+}
+
+fn leave_stream(mut spectator: ResMut<Spectator>) {
+    if spectator.watching() {
+        spectator.status = SpectatorStatus::Off;
+        spectator.snapshot = None;
+        spectator.recording.clear();
+    }
+}
+
+fn open_spectator_connection(
+    spectator: Res<Spectator>,
+    settings: Res<Settings>,
+    mut connection: NonSendMut<SpectatorConnection>,
+) {
+    let wants_server =
+        spectator.source == Source::Server && (spectator.searching() || spectator.watching());
+    match (wants_server, connection.0.is_some()) {
+        (true, false) => {
+            let socket = Connection::open(&settings.gameplay.server);
+            socket.send(ClientMessage::Watch {
+                name: settings.gameplay.stream_name.clone(),
+            });
+            connection.0 = Some(socket);
+        }
+        (false, true) => connection.0 = None,
+        _ => (),
+    }
+}
+
+fn receive_stream(
+    mut spectator: ResMut<Spectator>,
+    mut connection: NonSendMut<SpectatorConnection>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(socket) = &connection.0 else {
+        return;
+    };
+
+    let mut stopped = None;
+    for event in socket.poll() {
+        match event {
+            PeerEvent::Message(ServerMessage::Waiting) => {
+                if spectator.searching() {
+                    spectator.status = SpectatorStatus::Waiting;
+                }
+            }
+            PeerEvent::Message(ServerMessage::Stream(event)) => {
+                if let Err(message) = spectator.apply(event) {
+                    stopped = Some(message);
+                    break;
+                }
+            }
+            PeerEvent::Message(ServerMessage::StreamEnded) => {
+                stopped = Some("The stream ended");
+                break;
+            }
+            PeerEvent::Message(_) => (),
+            PeerEvent::Closed(reason) => {
+                warn!("spectator connection closed: {reason}");
+                stopped = Some(match spectator.status {
+                    SpectatorStatus::Connecting => "Could not reach the server",
+                    _ => "Connection lost",
+                });
+                break;
+            }
+        }
+    }
+
+    if let Some(message) = stopped {
+        if spectator.watching() {
+            next_state.set(GameState::GameOver);
+        }
+        spectator.stop(message);
+        connection.0 = None;
+    }
+}
+
+//  NOTE: one tick per recorded step, snapshots and game overs go through right away
+fn play_recording(
+    mut spectator: ResMut<Spectator>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+    mut elapsed: Local<Duration>,
+) {
+    if spectator.source != Source::Recording || spectator.status == SpectatorStatus::Off {
+        *elapsed = Duration::ZERO;
+        return;
+    }
+
+    let step = spectator
+        .snapshot
+        .as_ref()
+        .map_or(Duration::ZERO, |snapshot| {
+            Duration::from_millis(snapshot.step_millis)
+        });
+    *elapsed += time.delta();
+    if *elapsed < step {
+        return;
+    }
+    *elapsed = Duration::ZERO;
+
+    let stopped = loop {
+        let Some(event) = spectator.recording.pop_front() else {
+            break Some("End of the recording");
+        };
+        let tick = matches!(event, StreamEvent::Tick(_));
+        if let Err(message) = spectator.apply(event) {
+            break Some(message);
+        }
+        if tick {
+            break None;
+        }
+    };
+
+    if let Some(message) = stopped {
+        if spectator.watching() {
+            next_state.set(GameState::GameOver);
+        }
+        spectator.stop(message);
+    }
+}
+
+//  NOTE: brings the entities in line with the stream, `update_board_position` and
+//  `update_snake_sprite` draw them from there
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn update_spectated_game(
+    mut commands: Commands,
+    mut spectator: ResMut<Spectator>,
+    mut autopilot: ResMut<Autopilot>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    mut load_board: EventWriter<LoadBoard>,
+    mut direction_queue: ResMut<SnakeDirectionQueue>,
+    mut movement_timer: ResMut<MovementTimer>,
+    mut score: ResMut<Score>,
+    board: Res<Board>,
+    sprites: Res<CurrentSpritePack>,
+    theme: Res<CurrentTheme>,
+    time: Res<Time>,
+    mut segment_query: Query<(&mut Position, &mut PreviousPosition), With<SnakeSegment>>,
+    food_query: Query<(Entity, &Position), (With<Food>, Without<SnakeSegment>)>,
+    position_query: Query<Entity, With<Position>>,
+) {
+    if spectator.watching() {
+        movement_timer.tick(time.delta());
+    }
+    if !spectator.dirty {
+        return;
+    }
+    spectator.dirty = false;
+    let rebuild = std::mem::take(&mut spectator.rebuild);
+    let Some(snapshot) = &spectator.snapshot else {
+        return;
+    };
+
+    direction_queue.directions = snapshot.directions.clone();
+    score.value = snapshot.score;
+    movement_timer.follow(Duration::from_millis(snapshot.step_millis));
+
+    let spawn_segment = |commands: &mut Commands, board: &Board, pos: Position, head: bool| {
+        let mut segment = commands.spawn((
+            SpriteSheetBundle {
+                atlas: TextureAtlas {
+                    layout: sprites.layout.clone(),
+                    index: sprites.index(SpritePart::BodyHorizontal),
+                },
+                texture: sprites.texture.clone(),
+                transform: Transform::from_xyz(
+                    board.position_translate(pos.x),
+                    board.position_translate(pos.y),
+                    1.0,
+                ),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+            SnakeSegment,
+            pos,
+            PreviousPosition(pos),
+        ));
+        //  NOTE: same components as `spawn_snake`, `update_snake_sprite` relies on the head
+        //  coming first
+        if head {
+            segment.insert((SnakeHead, SnakeHeadDirection::default()));
+        }
+    };
+    let spawn_food = |commands: &mut Commands, board: &Board, pos: Position| {
+        commands.spawn((
+            SpriteSheetBundle {
+                atlas: TextureAtlas {
+                    layout: sprites.layout.clone(),
+                    index: sprites.index(SpritePart::Apple),
+                },
+                texture: sprites.texture.clone(),
+                transform: Transform::from_xyz(
+                    board.position_translate(pos.x),
+                    board.position_translate(pos.y),
+                    1.0,
+                ),
+                sprite: Sprite {
+                    color: theme.food,
+                    custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+            Food,
+            pos,
+        ));
+    };
+
+    if rebuild {
+        for entity in position_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        //  NOTE: positions are placed on the board the stream was recorded on
+        let streamed = Board::new(snapshot.board_size);
+        if board.size != snapshot.board_size {
+            load_board.send(LoadBoard {
+                size: snapshot.board_size,
+            });
+        }
+        for (i, &pos) in snapshot.segments.iter().enumerate() {
+            spawn_segment(&mut commands, &streamed, pos, i == 0);
+        }
+        if let Some(food) = snapshot.food {
+            spawn_food(&mut commands, &streamed, food);
+        }
+
+        if !spectator.watching() {
+            spectator.status = SpectatorStatus::Watching;
+            spectator.last_message = None;
+            autopilot.enabled = false;
+            next_menu_state.set(MenuState::Off);
+            next_state.set(GameState::Playing);
+        }
+        return;
+    }
+
+    //  NOTE: segments are queried in the order they were spawned, head first
+    let mut segments = segment_query.iter_mut();
+    for &pos in &snapshot.segments {
+        match segments.next() {
+            Some((mut current, mut previous)) => {
+                previous.0 = *current;
+                *current = pos;
+            }
+            None => spawn_segment(&mut commands, &board, pos, false),
+        }
+    }
+
+    let food = food_query.get_single().ok();
+    if food.map(|(_, &pos)| pos) != snapshot.food {
+        if let Some((entity, _)) = food {
+            commands.entity(entity).despawn();
+        }
+        if let Some(pos) = snapshot.food {
+            spawn_food(&mut commands, &board, pos);
+        }
+    }
+}
+
+fn update_spectator_hud(
+    mut contexts: EguiContexts,
+    spectator: Res<Spectator>,
+    settings: Res<Settings>,
+    theme: Res<CurrentTheme>,
+) {
+    let mut text = match spectator.source {
+        Source::Server => format!("Watching {}", settings.gameplay.stream_name),
+        Source::Recording => "Watching a recording".to_string(),
+    };
+    if spectator.over {
+        text.push_str("  ·  game over, waiting for the next one");
+    }
+
+    egui::Area::new(egui::Id::new("spectator-hud"))
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 70.0))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                egui::RichText::new(text)
+                    .color(color32(theme.text))
+                    .font(egui::FontId::monospace(18.0)),
+            );
+        });
+}
+
+//  NOTE: local games only, and from their first frame on so spectators get the start
+#[allow(clippy::too_many_arguments)]
+fn broadcast_game(
+    settings: Res<Settings>,
+    game_state: Res<State<GameState>>,
+    spectator: Res<Spectator>,
+    online: Res<Online>,
+    board: Res<Board>,
+    score: Res<Score>,
+    movement_timer: Res<MovementTimer>,
+    direction_queue: Res<SnakeDirectionQueue>,
+    head_query: Query<&Position, With<SnakeHead>>,
+    body_query: Query<&Position, (With<SnakeSegment>, Without<SnakeHead>)>,
+    food_query: Query<&Position, With<Food>>,
+    mut sink: NonSendMut<BroadcastSink>,
+) {
+    let gameplay = &settings.gameplay;
+    let wanted = (gameplay.broadcast != BroadcastTarget::Off).then(|| {
+        (
+            gameplay.broadcast,
+            gameplay.server.clone(),
+            gameplay.stream_name.clone(),
+        )
+    });
+    if sink.opened != wanted {
+        *sink = BroadcastSink::default();
+        match gameplay.broadcast {
+            BroadcastTarget::Off => (),
+            BroadcastTarget::Server => {
+                let socket = Connection::open(&gameplay.server);
+                socket.send(ClientMessage::Broadcast {
+                    name: gameplay.stream_name.clone(),
+                });
+                sink.connection = Some(socket);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            BroadcastTarget::File => match recording::Writer::create() {
+                Ok(file) => sink.file = Some(file),
+                Err(err) => warn!("could not create the recording: {err}"),
+            },
+            #[cfg(target_arch = "wasm32")]
+            BroadcastTarget::File => warn!("recordings can't be written in the browser"),
+        }
+        sink.opened = wanted;
+    }
+
+    //  NOTE: the server only answers pings, a closed socket stays closed until the settings change
+    if let Some(socket) = &sink.connection {
+        let closed = socket.poll().into_iter().find_map(|event| match event {
+            PeerEvent::Closed(reason) => Some(reason),
+            _ => None,
+        });
+        if let Some(reason) = closed {
+            warn!("broadcast connection closed: {reason}");
+            sink.connection = None;
+        }
+    }
+
+    if sink.opened.is_none()
+        || game_state.get() != &GameState::Playing
+        || spectator.watching()
+        || online.in_match()
+        || (sink.last.is_some() && !movement_timer.just_moved())
+    {
+        return;
+    }
+    let Ok(&head) = head_query.get_single() else {
+        return;
+    };
+
+    let snapshot = StreamSnapshot {
+        board_size: board.size,
+        walls: gameplay.walls(),
+        step_millis: movement_timer.step().as_millis() as u64,
+        segments: [head]
+            .into_iter()
+            .chain(body_query.iter().copied())
+            .collect(),
+        directions: direction_queue.directions.clone(),
+        food: food_query.iter().next().copied(),
+        score: score.value,
+    };
+    let event = match sink.last.as_ref().and_then(|last| last.delta(&snapshot)) {
+        Some(delta) => StreamEvent::Tick(delta),
+        None => StreamEvent::Snapshot(snapshot.clone()),
+    };
+    sink.send(event);
+    sink.last = Some(snapshot);
+}
+
+fn broadcast_game_over(mut sink: NonSendMut<BroadcastSink>) {
+    if sink.last.take().is_some() {
+        sink.send(StreamEvent::GameOver);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod recording {
+    use std::{
+        fs::{self, File},
+        io::{self, LineWriter, Write},
+    };
+
+    use crate::stream::{decode_lines, encode_line, StreamEvent, STREAM_FILE};
+
+    pub struct Writer(LineWriter<File>);
+
+    impl Writer {
+        //  NOTE: starts over, one recording at a time
+        pub fn create() -> io::Result<Self> {
+            File::create(STREAM_FILE).map(|file| Self(LineWriter::new(file)))
+        }
+
+        pub fn write(&mut self, event: &StreamEvent) -> io::Result<()> {
+            writeln!(self.0, "{}", encode_line(event))
+        }
+    }
+
+    pub fn load() -> io::Result<Vec<StreamEvent>> {
+        fs::read_to_string(STREAM_FILE).map(|text| decode_lines(&text))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod recording {
+    use std::io;
+
+    use crate::stream::StreamEvent;
+
+    pub fn load() -> io::Result<Vec<StreamEvent>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "recordings can't be read in the browser",
+        ))
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+
+use crate::{
+    asset_loader::{ImageAssets, SpritePackAssets, SpritePart},
+    food::Food,
+    settings::Settings,
+    snake::SnakeSegment,
+    state::AppState,
+};
+
+pub const DEFAULT_SPRITE_PACK: &str = "Classic";
+
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct SpritePack {
+    pub name: String,
+    pub texture: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    //  NOTE: validated on load to contain every `SpritePart`
+    parts: HashMap<SpritePart, usize>,
+}
+
+impl SpritePack {
+    pub fn index(&self, part: SpritePart) -> usize {
+        self.parts[&part]
+    }
+}
+
+//  NOTE: the pack every renderer reads from, swapped when the selected pack changes
+#[derive(Resource, Debug, Deref)]
+pub struct CurrentSpritePack(pub SpritePack);
+
+//  NOTE: start from the atlas declared in `ImageAssets`, `select_sprite_pack` swaps in the
+//  configured manifest once it is loaded
+fn init_sprite_pack(mut commands: Commands, assets: Res<ImageAssets>) {
+    commands.insert_resource(CurrentSpritePack(SpritePack {
+        name: DEFAULT_SPRITE_PACK.to_string(),
+        texture: assets.sprite_sheet.clone(),
+        layout: assets.sprite_sheet_layout.clone(),
+        parts: SpritePart::ALL
+            .iter()
+            .map(|&part| (part, part as usize))
+            .collect(),
+    }));
+}
+
+//  NOTE: on-disk manifest, `image` is relative to the assets folder
+#[derive(Deserialize)]
+struct SpritePackFile {
+    name: String,
+    image: String,
+    tile_size: (f32, f32),
+    columns: usize,
+    rows: usize,
+    parts: HashMap<SpritePart, usize>,
+}
+
+#[derive(Debug)]
+pub enum SpritePackLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    MissingParts {
+        pack: String,
+        parts: Vec<SpritePart>,
+    },
+    IndexOutOfRange {
+        pack: String,
+        part: SpritePart,
+        index: usize,
+    },
+}
+
+impl fmt::Display for SpritePackLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read sprite pack: {err}"),
+            Self::Ron(err) => write!(f, "could not parse sprite pack: {err}"),
+            Self::MissingParts { pack, parts } => {
+                write!(f, "sprite pack {pack:?} is missing parts: {parts:?}")
+            }
+            Self::IndexOutOfRange { pack, part, index } => {
+                write!(
+                    f,
+                    "sprite pack {pack:?} maps {part:?} to tile {index}, outside of its grid"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpritePackLoaderError {}
+
+impl From<std::io::Error> for SpritePackLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for SpritePackLoaderError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Ron(err)
+    }
+}
+
+#[derive(Default)]
+pub struct SpritePackLoader;
+
+impl AssetLoader for SpritePackLoader {
+    type Asset = SpritePack;
+    type Settings = ();
+    type Error = SpritePackLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<SpritePack, SpritePackLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let file: SpritePackFile = ron::de::from_bytes(&bytes)?;
+
+            let missing: Vec<SpritePart> = SpritePart::ALL
+                .into_iter()
+                .filter(|part| !file.parts.contains_key(part))
+                .collect();
+            if !missing.is_empty() {
+                return Err(SpritePackLoaderError::MissingParts {
+                    pack: file.name,
+                    parts: missing,
+                });
+            }
+
+            let tiles = file.columns * file.rows;
+            if let Some((&part, &index)) = file.parts.iter().find(|(_, &index)| index >= tiles) {
+                return Err(SpritePackLoaderError::IndexOutOfRange {
+                    pack: file.name,
+                    part,
+                    index,
+                });
+            }
+
+            let layout = TextureAtlasLayout::from_grid(
+                Vec2::new(file.tile_size.0, file.tile_size.1),
+                file.columns,
+                file.rows,
+                None,
+                None,
+            );
+
+            Ok(SpritePack {
+                name: file.name,
+                texture: load_context.load(file.image),
+                layout: load_context.add_labeled_asset("layout".to_string(), layout),
+                parts: file.parts,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pack.ron"]
+    }
+}
+
+pub struct SpritePackPlugin;
+
+impl Plugin for SpritePackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Running), init_sprite_pack)
+            .add_systems(
+                Update,
+                (select_sprite_pack, apply_sprite_pack)
+                    .chain()
+                    .run_if(in_state(AppState::Running)),
+            );
+    }
+}
+
+fn select_sprite_pack(
+    settings: Res<Settings>,
+    pack_assets: Res<SpritePackAssets>,
+    packs: Res<Assets<SpritePack>>,
+    mut asset_events: EventReader<AssetEvent<SpritePack>>,
+    mut current_pack: ResMut<CurrentSpritePack>,
+) {
+    //  NOTE: packs load asynchronously, so also look again whenever one finishes loading
+    let loaded = asset_events.read().count() > 0;
+    if !loaded && current_pack.name == settings.video.sprite_pack {
+        return;
+    }
+
+    let selected = pack_assets
+        .packs
+        .iter()
+        .filter_map(|handle| packs.get(handle))
+        .find(|pack| pack.name == settings.video.sprite_pack);
+
+    if let Some(pack) = selected {
+        current_pack.0 = pack.clone();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_sprite_pack(
+    current_pack: Res<CurrentSpritePack>,
+    mut snake_query: Query<(&mut Handle<Image>, &mut TextureAtlas), With<SnakeSegment>>,
+    mut food_query: Query<
+        (&mut Handle<Image>, &mut TextureAtlas),
+        (With<Food>, Without<SnakeSegment>),
+    >,
+) {
+    if !current_pack.is_changed() {
+        return;
+    }
+
+    //  NOTE: snake indices depend on its directions, `update_snake_sprite` picks them again
+    for (mut texture, mut atlas) in snake_query.iter_mut() {
+        *texture = current_pack.texture.clone();
+        atlas.layout = current_pack.layout.clone();
+    }
+
+    for (mut texture, mut atlas) in food_query.iter_mut() {
+        *texture = current_pack.texture.clone();
+        atlas.layout = current_pack.layout.clone();
+        atlas.index = current_pack.index(SpritePart::Apple);
+    }
+}
@@ -1,12 +1,33 @@
 use bevy::prelude::*;
 
+use crate::{online::Online, pause::can_pause, spectate::Spectator};
+
+#[derive(Debug, States, Clone, PartialEq, Eq, Hash, Copy, Default)]
+pub enum AppState {
+    //  NOTE: waiting on the asset collections, see `asset_loader.rs`
+    #[default]
+    Loading,
+    Running,
+    LoadFailed,
+}
+
 #[derive(Debug, States, Clone, PartialEq, Eq, Hash, Copy, Default)]
 pub enum GameState {
-    Playing,
+    //  NOTE: nothing is spawned until `AppState::Running`
     #[default]
+    Loading,
+    Playing,
     NewGame,
     Paused,
+    //  NOTE: counting down back into `Playing` after a pause
+    Resuming,
+    //  NOTE: the snake crashed and is blinking away before `GameOver`, see `death.rs`
+    Dying,
     GameOver,
+    //  NOTE: tear down the current board and start over without recording a score
+    Resetting,
+    //  NOTE: the level editor is open, see `editor.rs`
+    Editing,
 }
 
 #[derive(Debug, States, Clone, PartialEq, Eq, Hash, Copy, Default)]
@@ -14,19 +35,25 @@ pub enum MenuState {
     #[default]
     On,
     Off,
+    Settings,
+    Paused,
 }
 
 pub struct StatePlugin;
 
 impl Plugin for StatePlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<GameState>()
+        app.init_state::<AppState>()
+            .init_state::<GameState>()
             .init_state::<MenuState>()
             .add_systems(Update, state_input_events)
-            .add_systems(OnEnter(GameState::GameOver), transition_to_new_game);
+            .add_systems(OnEnter(GameState::GameOver), transition_to_new_game)
+            .add_systems(OnEnter(GameState::Resetting), finish_reset)
+            .add_systems(OnEnter(AppState::Running), start_first_game);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn state_input_events(
     mut next_state: ResMut<NextState<GameState>>,
     mut next_menu_state: ResMut<NextState<MenuState>>,
@@ -34,17 +61,22 @@ fn state_input_events(
     menu_state: Res<State<MenuState>>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     touches: Res<Touches>,
+    online: Res<Online>,
+    spectator: Res<Spectator>,
 ) {
     if keyboard_input.just_pressed(KeyCode::Escape) {
         match game_state.get() {
-            GameState::Playing => {
+            GameState::Playing | GameState::Resuming if can_pause(&online, &spectator) => {
                 next_state.set(GameState::Paused);
-                next_menu_state.set(MenuState::On);
-            }
-            GameState::Paused => {
-                next_state.set(GameState::Playing);
-                next_menu_state.set(MenuState::Off);
+                next_menu_state.set(MenuState::Paused);
             }
+            GameState::Paused => match menu_state.get() {
+                MenuState::Settings => next_menu_state.set(MenuState::Paused),
+                _ => {
+                    next_state.set(GameState::Resuming);
+                    next_menu_state.set(MenuState::Off);
+                }
+            },
             _ => (),
         }
     }
@@ -64,6 +96,10 @@ fn state_input_events(
     // }
 
     //  NOTE: If the menu is not showing and arrow keys are pressed start playing
+    if menu_state.get() == &MenuState::Settings {
+        return;
+    }
+
     if menu_state.get() == &MenuState::Off && touches.any_just_pressed()
         || keyboard_input.any_just_pressed([
             KeyCode::ArrowUp,
@@ -90,3 +126,11 @@ fn transition_to_new_game(
     next_game_state.set(GameState::NewGame);
     next_menu_state.set(MenuState::On);
 }
+
+fn finish_reset(mut next_game_state: ResMut<NextState<GameState>>) {
+    next_game_state.set(GameState::NewGame);
+}
+
+fn start_first_game(mut next_game_state: ResMut<NextState<GameState>>) {
+    next_game_state.set(GameState::NewGame);
+}
@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    settings::{WallsMode, MAX_BOARD_SIZE, MIN_BOARD_SIZE},
+    snake::{Direction, Position},
+};
+
+//  NOTE: a running game as a stream of events, sent through `snake-server` to spectators or written
+//  to a file. A snapshot starts every game and lets spectators join late, after that each
+//  movement tick is a small delta
+
+//  NOTE: where "File" broadcasts go and "Watch recording" reads from, one JSON event per line
+pub const STREAM_FILE: &str = "stream.jsonl";
+pub const DEFAULT_STREAM_NAME: &str = "snake";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamSnapshot {
+    pub board_size: i32,
+    pub walls: WallsMode,
+    //  NOTE: time between two ticks, spectators glide and recordings play back at this pace
+    pub step_millis: u64,
+    //  NOTE: head first
+    pub segments: VecDeque<Position>,
+    //  NOTE: the direction each segment last moved in, like `SnakeDirectionQueue`
+    pub directions: VecDeque<Direction>,
+    pub food: Option<Position>,
+    pub score: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TickDelta {
+    pub head: Position,
+    pub direction: Direction,
+    //  NOTE: the tail stayed where it was
+    pub grew: bool,
+    //  NOTE: only when new food spawned, same for the score
+    pub food: Option<Position>,
+    pub score: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StreamEvent {
+    Snapshot(StreamSnapshot),
+    Tick(TickDelta),
+    GameOver,
+}
+
+impl StreamSnapshot {
+    //  NOTE: whether the renderers can draw it, streams come from other people's games
+    pub fn is_valid(&self) -> bool {
+        (MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&self.board_size)
+            && self.segments.len() >= 2
+            && self.segments.len() == self.directions.len()
+            && self
+                .directions
+                .iter()
+                .zip(self.directions.iter().skip(1))
+                .all(|(ahead, behind)| *ahead != behind.opposite())
+    }
+
+    //  NOTE: moves the snake like `update_position` and `apply_eat_food` do
+    pub fn apply(&mut self, delta: &TickDelta) {
+        self.segments.push_front(delta.head);
+        let tail = self.segments.pop_back();
+        self.directions.push_front(delta.direction);
+        self.directions.pop_back();
+        if delta.grew {
+            self.segments.extend(tail);
+            if let Some(&last) = self.directions.back() {
+                self.directions.push_back(last);
+            }
+        }
+        if delta.food.is_some() {
+            self.food = delta.food;
+        }
+        if let Some(score) = delta.score {
+            self.score = score;
+        }
+    }
+
+    //  NOTE: the delta that turns `self` into `next`, `None` when it takes more than one tick and
+    //  a new snapshot has to be sent instead
+    pub fn delta(&self, next: &StreamSnapshot) -> Option<TickDelta> {
+        let delta = TickDelta {
+            head: *next.segments.front()?,
+            direction: *next.directions.front()?,
+            grew: next.segments.len() == self.segments.len() + 1,
+            food: next.food.filter(|_| next.food != self.food),
+            score: (next.score != self.score).then_some(next.score),
+        };
+        let mut applied = self.clone();
+        applied.apply(&delta);
+        (applied == *next).then_some(delta)
+    }
+}
+
+pub fn encode_line(event: &StreamEvent) -> String {
+    serde_json::to_string(event).expect("stream events always serialize")
+}
+
+//  NOTE: lines that don't parse are skipped, a recording cut off mid-write still plays
+pub fn decode_lines(text: &str) -> Vec<StreamEvent> {
+    text.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(cells: &[(i32, i32)]) -> VecDeque<Position> {
+        cells.iter().map(|&(x, y)| Position::new(x, y)).collect()
+    }
+
+    //  NOTE: three segments heading right in the middle of the default board
+    fn snapshot() -> StreamSnapshot {
+        StreamSnapshot {
+            board_size: 20,
+            walls: WallsMode::Solid,
+            step_millis: 150,
+            segments: cells(&[(5, 10), (4, 10), (3, 10)]),
+            directions: VecDeque::from([Direction::Right; 3]),
+            food: Some(Position::new(15, 10)),
+            score: 0,
+        }
+    }
+
+    fn round_trip(from: &StreamSnapshot, next: &StreamSnapshot) -> TickDelta {
+        let delta = from.delta(next).expect("one tick apart");
+        let mut applied = from.clone();
+        applied.apply(&delta);
+        assert_eq!(applied, *next);
+        delta
+    }
+
+    #[test]
+    fn deltas_replay_a_move() {
+        let from = snapshot();
+        let mut next = from.clone();
+        next.segments = cells(&[(5, 11), (5, 10), (4, 10)]);
+        next.directions = VecDeque::from([Direction::Up, Direction::Right, Direction::Right]);
+
+        let delta = round_trip(&from, &next);
+        assert!(!delta.grew);
+        assert_eq!((delta.food, delta.score), (None, None));
+    }
+
+    #[test]
+    fn deltas_replay_growth() {
+        let from = snapshot();
+        let mut next = from.clone();
+        next.segments = cells(&[(6, 10), (5, 10), (4, 10), (3, 10)]);
+        next.directions.push_back(Direction::Right);
+        next.food = Some(Position::new(2, 2));
+        next.score = 1;
+
+        let delta = round_trip(&from, &next);
+        assert!(delta.grew);
+        assert_eq!(delta.food, Some(Position::new(2, 2)));
+        assert_eq!(delta.score, Some(1));
+    }
+
+    #[test]
+    fn deltas_replay_food_and_score_on_their_own() {
+        let from = snapshot();
+        let mut next = from.clone();
+        next.segments = cells(&[(6, 10), (5, 10), (4, 10)]);
+        next.food = Some(Position::new(2, 2));
+        assert_eq!(round_trip(&from, &next).score, None);
+
+        let mut next = from.clone();
+        next.segments = cells(&[(6, 10), (5, 10), (4, 10)]);
+        next.score = 3;
+        let delta = round_trip(&from, &next);
+        assert_eq!((delta.food, delta.score), (None, Some(3)));
+    }
+
+    #[test]
+    fn gaps_of_several_ticks_need_a_snapshot() {
+        let from = snapshot();
+        let mut next = from.clone();
+        next.segments = cells(&[(7, 10), (6, 10), (5, 10)]);
+        assert_eq!(from.delta(&next), None);
+
+        //  NOTE: growing by more than one segment can't be a single tick either
+        next.segments = cells(&[(6, 10), (5, 10), (4, 10), (3, 10), (2, 10)]);
+        next.directions = VecDeque::from([Direction::Right; 5]);
+        assert_eq!(from.delta(&next), None);
+    }
+
+    #[test]
+    fn broken_snapshots_are_invalid() {
+        assert!(snapshot().is_valid());
+
+        let mut small = snapshot();
+        small.board_size = MIN_BOARD_SIZE - 1;
+        let mut uneven = snapshot();
+        uneven.directions.pop_back();
+        let mut reversed = snapshot();
+        reversed.directions[1] = Direction::Left;
+        let mut headless = snapshot();
+        headless.segments.truncate(1);
+        headless.directions.truncate(1);
+
+        for snapshot in [small, uneven, reversed, headless] {
+            assert!(!snapshot.is_valid(), "{snapshot:?}");
+        }
+    }
+
+    #[test]
+    fn truncated_lines_are_skipped() {
+        let delta = TickDelta {
+            head: Position::new(6, 10),
+            direction: Direction::Right,
+            grew: false,
+            food: None,
+            score: None,
+        };
+        let events = [
+            StreamEvent::Snapshot(snapshot()),
+            StreamEvent::Tick(delta),
+            StreamEvent::GameOver,
+        ];
+        let lines: Vec<String> = events.iter().map(encode_line).collect();
+        //  NOTE: a line cut off in the middle of the recording and one at the very end
+        let cut = &lines[1][..lines[1].len() / 2];
+        let text = [lines[0].as_str(), cut, &lines[1], &lines[2], cut].join("\n");
+
+        assert_eq!(decode_lines(&text), events);
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use bevy_egui::{egui, EguiContexts};
+use serde::Deserialize;
+
+use crate::{
+    asset_loader::ThemeAssets, colors::COLORS, food::Food, settings::Settings, snake::SnakeSegment,
+    state::AppState,
+};
+
+pub const DEFAULT_THEME: &str = "Classic";
+
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    //  NOTE: base egui visuals, light or dark widgets
+    pub dark_mode: bool,
+    pub background: Color,
+    pub board: Color,
+    pub tile_light: Color,
+    pub tile_dark: Color,
+    pub grid_line: Color,
+    //  NOTE: tints multiply the sprite sheet colours, white keeps them as drawn
+    pub snake: Color,
+    pub food: Color,
+    pub panel: Color,
+    pub menu: Color,
+    pub button: Color,
+    pub text: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_THEME.to_string(),
+            dark_mode: false,
+            background: COLORS.background,
+            board: COLORS.board,
+            tile_light: COLORS.tile_placeholder,
+            tile_dark: COLORS.tile_placeholder_dark,
+            grid_line: COLORS.grid_line,
+            snake: Color::WHITE,
+            food: Color::WHITE,
+            panel: COLORS.panel,
+            menu: COLORS.menu,
+            button: COLORS.button,
+            text: COLORS.text,
+        }
+    }
+}
+
+//  NOTE: the theme every renderer reads from, swapped when the selected theme changes
+#[derive(Resource, Debug, Default, Deref)]
+pub struct CurrentTheme(pub Theme);
+
+pub fn color32(color: Color) -> egui::Color32 {
+    let [r, g, b, a] = color.as_rgba_u8();
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+//  NOTE: on-disk format, colours are hex strings like "#578a34" or "#00000026"
+#[derive(Deserialize)]
+struct ThemeFile {
+    name: String,
+    #[serde(default)]
+    dark_mode: bool,
+    background: String,
+    board: String,
+    tile_light: String,
+    tile_dark: String,
+    grid_line: String,
+    snake: String,
+    food: String,
+    panel: String,
+    menu: String,
+    button: String,
+    text: String,
+}
+
+#[derive(Debug)]
+pub enum ThemeLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Color { field: &'static str, value: String },
+}
+
+impl fmt::Display for ThemeLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read theme: {err}"),
+            Self::Ron(err) => write!(f, "could not parse theme: {err}"),
+            Self::Color { field, value } => write!(f, "invalid colour for `{field}`: {value:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ThemeLoaderError {}
+
+impl From<std::io::Error> for ThemeLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for ThemeLoaderError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Ron(err)
+    }
+}
+
+impl TryFrom<ThemeFile> for Theme {
+    type Error = ThemeLoaderError;
+
+    fn try_from(file: ThemeFile) -> Result<Self, Self::Error> {
+        let parse = |field: &'static str, value: &str| {
+            Color::hex(value).map_err(|_| ThemeLoaderError::Color {
+                field,
+                value: value.to_string(),
+            })
+        };
+
+        Ok(Self {
+            dark_mode: file.dark_mode,
+            background: parse("background", &file.background)?,
+            board: parse("board", &file.board)?,
+            tile_light: parse("tile_light", &file.tile_light)?,
+            tile_dark: parse("tile_dark", &file.tile_dark)?,
+            grid_line: parse("grid_line", &file.grid_line)?,
+            snake: parse("snake", &file.snake)?,
+            food: parse("food", &file.food)?,
+            panel: parse("panel", &file.panel)?,
+            menu: parse("menu", &file.menu)?,
+            button: parse("button", &file.button)?,
+            text: parse("text", &file.text)?,
+            name: file.name,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    type Asset = Theme;
+    type Settings = ();
+    type Error = ThemeLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Theme, ThemeLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let file: ThemeFile = ron::de::from_bytes(&bytes)?;
+            Theme::try_from(file)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentTheme>().add_systems(
+            Update,
+            (
+                select_theme.run_if(in_state(AppState::Running)),
+                (apply_clear_color, apply_egui_visuals, tint_sprites),
+            )
+                .chain(),
+        );
+    }
+}
+
+fn select_theme(
+    settings: Res<Settings>,
+    theme_assets: Res<ThemeAssets>,
+    themes: Res<Assets<Theme>>,
+    mut asset_events: EventReader<AssetEvent<Theme>>,
+    mut current_theme: ResMut<CurrentTheme>,
+) {
+    //  NOTE: themes load asynchronously, so also look again whenever one finishes loading
+    let loaded = asset_events.read().count() > 0;
+    if !loaded && current_theme.name == settings.video.theme {
+        return;
+    }
+
+    let selected = theme_assets
+        .themes
+        .iter()
+        .filter_map(|handle| themes.get(handle))
+        .find(|theme| theme.name == settings.video.theme);
+
+    if let Some(theme) = selected {
+        current_theme.0 = theme.clone();
+    }
+}
+
+fn apply_clear_color(current_theme: Res<CurrentTheme>, mut clear_color: ResMut<ClearColor>) {
+    if current_theme.is_changed() {
+        clear_color.0 = current_theme.background;
+    }
+}
+
+fn apply_egui_visuals(mut contexts: EguiContexts, current_theme: Res<CurrentTheme>) {
+    if !current_theme.is_changed() {
+        return;
+    }
+
+    let mut visuals = if current_theme.dark_mode {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    };
+    visuals.selection.bg_fill = color32(current_theme.button);
+    visuals.widgets.inactive.bg_fill = color32(current_theme.panel);
+    visuals.widgets.inactive.weak_bg_fill = color32(current_theme.panel);
+    contexts.ctx_mut().set_visuals(visuals);
+}
+
+fn tint_sprites(
+    current_theme: Res<CurrentTheme>,
+    mut snake_query: Query<(&mut Sprite, Ref<SnakeSegment>), Without<Food>>,
+    mut food_query: Query<(&mut Sprite, Ref<Food>), Without<SnakeSegment>>,
+) {
+    let theme_changed = current_theme.is_changed();
+
+    for (mut sprite, segment) in snake_query.iter_mut() {
+        if theme_changed || segment.is_added() {
+            sprite.color = current_theme.snake;
+        }
+    }
+
+    for (mut sprite, food) in food_query.iter_mut() {
+        if theme_changed || food.is_added() {
+            sprite.color = current_theme.food;
+        }
+    }
+}
@@ -1,55 +1,62 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{asset::LoadState, prelude::*, window::PrimaryWindow};
 use bevy_egui::{
     egui::{self, epaint::Shadow, TopBottomPanel},
     EguiContexts, EguiPlugin,
 };
 
 use crate::{
-    asset_loader::{ImageAssets, SpritePart},
+    arena,
+    asset_loader::{ImageAssets, SpritePackAssets, SpritePart, ThemeAssets},
+    autopilot::Autopilot,
+    battle_royale::BattleRoyale,
+    board::Board,
+    level::CurrentLevel,
+    mode::ModeClock,
+    online::Online,
+    royale::{MAX_ROYALE_HUMANS, MAX_ROYALE_SNAKES, MIN_ROYALE_SNAKES},
     score::Score,
-    state::{GameState, MenuState},
+    settings::{
+        AiPlayer, ArenaDifficulty, BroadcastTarget, CameraMode, GameMode, Settings, Speed,
+        WallsMode, MAX_BOARD_SIZE, MAX_UI_SCALE, MIN_BOARD_SIZE, MIN_UI_SCALE,
+    },
+    snake::MovementTimer,
+    spectate::Spectator,
+    sprite_pack::{CurrentSpritePack, SpritePack},
+    state::{AppState, GameState, MenuState},
+    theme::{color32, CurrentTheme, Theme},
 };
 
-struct Images {
-    play_icon: Handle<Image>,
-    apple_icon: Handle<Image>,
-    trophy_icon: Handle<Image>,
-}
-
-impl FromWorld for Images {
-    fn from_world(world: &mut World) -> Self {
-        let asset_server = world.get_resource_mut::<AssetServer>().unwrap();
-        Self {
-            play_icon: asset_server.load("play.png"),
-            apple_icon: asset_server.load("snake-graphics.png"),
-            trophy_icon: asset_server.load("trophy.png"),
-        }
-    }
-}
-
 pub struct GameUiPlugin;
 
 impl Plugin for GameUiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(EguiPlugin)
-            .add_systems(Update, update_menu.run_if(in_state(MenuState::On)))
-            .add_systems(Update, update_top_bar);
+        app.add_plugins(EguiPlugin).add_systems(
+            Update,
+            (
+                update_menu.run_if(in_state(MenuState::On)),
+                update_settings_menu.run_if(in_state(MenuState::Settings)),
+                update_top_bar,
+            )
+                .run_if(in_state(AppState::Running)),
+        );
     }
 }
 
 fn update_top_bar(
     mut contexts: EguiContexts,
     score: Res<Score>,
-    images: Local<Images>,
+    clock: Res<ModeClock>,
+    settings: Res<Settings>,
     texture_atlas: Res<Assets<TextureAtlasLayout>>,
-    assets: Res<ImageAssets>,
+    sprites: Res<CurrentSpritePack>,
+    theme: Res<CurrentTheme>,
 ) {
-    let apple_icon = contexts.add_image(images.apple_icon.clone());
+    let apple_icon = contexts.add_image(sprites.texture.clone());
     TopBottomPanel::top("hi")
         .min_height(60.)
         .show_separator_line(true)
         .frame(egui::Frame {
-            fill: egui::Color32::from_hex("#4a752c").unwrap(),
+            fill: color32(theme.panel),
             inner_margin: egui::Margin {
                 left: 15.0,
                 ..Default::default()
@@ -61,8 +68,9 @@ fn update_top_bar(
                 egui::Layout::centered_and_justified(egui::Direction::LeftToRight),
                 |ui| {
                     ui.horizontal(|ui| {
-                        if let Some(atlas) = texture_atlas.get(&assets.sprite_sheet_layout) {
-                            let apple_rect = atlas.textures[SpritePart::Apple as usize].clone();
+                        if let Some(atlas) = texture_atlas.get(&sprites.layout) {
+                            let apple_rect =
+                                atlas.textures[sprites.index(SpritePart::Apple)].clone();
                             let uv: egui::Rect = egui::Rect::from_min_max(
                                 egui::pos2(
                                     apple_rect.min.x / atlas.size.x,
@@ -84,9 +92,24 @@ fn update_top_bar(
                         }
                         ui.label(
                             egui::RichText::new(format!("{}", score.value))
-                                .color(egui::Color32::WHITE)
+                                .color(color32(theme.text))
                                 .font(egui::FontId::monospace(20.0)),
                         );
+
+                        //  NOTE: time left in time attack, until the snake starves in survival
+                        if let Some(remaining) = clock.remaining() {
+                            let seconds = remaining.as_secs_f32().ceil() as u32;
+                            let text = match settings.gameplay.mode {
+                                GameMode::Survival => format!("Hunger {seconds}"),
+                                _ => format!("{}:{:02}", seconds / 60, seconds % 60),
+                            };
+                            ui.add_space(30.0);
+                            ui.label(
+                                egui::RichText::new(text)
+                                    .color(color32(theme.text))
+                                    .font(egui::FontId::monospace(20.0)),
+                            );
+                        }
                     });
                 },
             );
@@ -96,28 +119,40 @@ fn update_top_bar(
 fn update_menu(
     mut contexts: EguiContexts,
     mut next_menu_state: ResMut<NextState<MenuState>>,
-    game_state: Res<State<GameState>>,
-    mut next_game_state: ResMut<NextState<GameState>>,
-    images: Local<Images>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut autopilot: ResMut<Autopilot>,
+    mut online: ResMut<Online>,
+    mut spectator: ResMut<Spectator>,
+    mut royale: ResMut<BattleRoyale>,
+    mut level: ResMut<CurrentLevel>,
+    mut settings: ResMut<Settings>,
+    images: Res<ImageAssets>,
     score: Res<Score>,
     window: Query<&mut Window, With<PrimaryWindow>>,
     texture_atlas: Res<Assets<TextureAtlasLayout>>,
-    assets: Res<ImageAssets>,
+    sprites: Res<CurrentSpritePack>,
+    theme: Res<CurrentTheme>,
 ) {
     let Ok(window) = window.get_single() else {
         return;
     };
 
-    let apple_icon = contexts.add_image(images.apple_icon.clone());
+    //  NOTE: the record for the mode picked below
+    let highest = score.highest(settings.gameplay.mode);
+
+    let apple_icon = contexts.add_image(sprites.texture.clone());
     let play_icon = contexts.add_image(images.play_icon.clone());
     let trophy_icon = contexts.add_image(images.trophy_icon.clone());
+    let settings_icon = contexts.add_image(images.settings_icon.clone());
 
+    //  NOTE: egui coordinates, they differ from the window size when the UI is scaled
+    let screen = contexts.ctx_mut().screen_rect();
     egui::Window::new("button-group")
         .title_bar(false)
         .default_size(egui::vec2(300.0, 600.0))
         .current_pos(egui::pos2(
-            (window.width() - 300.0) / 2.0,
-            (window.height() - 400.0) / 2.0,
+            (screen.width() - 300.0) / 2.0,
+            (screen.height() - 400.0) / 2.0,
         ))
         .movable(false)
         .collapsible(false)
@@ -136,7 +171,7 @@ fn update_menu(
             ui.vertical_centered(|ui| {
                 egui::Frame::group(ui.style_mut())
                     .rounding(egui::Rounding::same(10.0))
-                    .fill(egui::Color32::from_hex("#4dc1f9").unwrap())
+                    .fill(color32(theme.menu))
                     .stroke(egui::Stroke::NONE)
                     .show(ui, |ui| {
                         ui.set_width(300.0);
@@ -146,10 +181,9 @@ fn update_menu(
                             ui.add_space(75.0);
                             ui.horizontal_centered(|ui| {
                                 //  NOTE: apple
-                                if let Some(atlas) = texture_atlas.get(&assets.sprite_sheet_layout)
-                                {
+                                if let Some(atlas) = texture_atlas.get(&sprites.layout) {
                                     let apple_rect =
-                                        atlas.textures[SpritePart::Apple as usize].clone();
+                                        atlas.textures[sprites.index(SpritePart::Apple)].clone();
                                     let uv: egui::Rect = egui::Rect::from_min_max(
                                         egui::pos2(
                                             apple_rect.min.x / atlas.size.x,
@@ -172,7 +206,7 @@ fn update_menu(
                                 ui.add_space(5.0);
                                 ui.label(
                                     egui::RichText::new(format!("{}", score.previous))
-                                        .color(egui::Color32::WHITE)
+                                        .color(color32(theme.text))
                                         .font(egui::FontId::monospace(20.0)),
                                 );
                             });
@@ -187,8 +221,8 @@ fn update_menu(
                                     [40.0, 40.0],
                                 )));
                                 ui.label(
-                                    egui::RichText::new(format!("{}", score.highest))
-                                        .color(egui::Color32::WHITE)
+                                    egui::RichText::new(format!("{highest}"))
+                                        .color(color32(theme.text))
                                         .font(egui::FontId::monospace(20.0)),
                                 );
                             });
@@ -198,6 +232,20 @@ fn update_menu(
                 //  NOTE: Add some space between the scores and buttons
                 ui.add_space(10.0);
 
+                let message = online
+                    .last_result()
+                    .map(|result| result.label().to_string())
+                    .or(royale.last_result().map(|result| result.label()))
+                    .or(spectator.last_message().map(str::to_string));
+                if let Some(message) = message {
+                    ui.label(
+                        egui::RichText::new(message)
+                            .color(color32(theme.text))
+                            .font(egui::FontId::monospace(20.0)),
+                    );
+                    ui.add_space(10.0);
+                }
+
                 egui::Frame::group(ui.style_mut())
                     .fill(egui::Color32::TRANSPARENT)
                     .stroke(egui::Stroke::NONE)
@@ -206,6 +254,27 @@ fn update_menu(
                         ui.spacing_mut().icon_spacing = 85.;
                         ui.style_mut().spacing.button_padding = egui::Vec2::new(15., 10.);
 
+                        //  NOTE: only assigned on a click, `ResMut` would trip change detection
+                        //  every frame otherwise
+                        ui.horizontal_wrapped(|ui| {
+                            for mode in GameMode::ALL {
+                                let selected = settings.gameplay.mode == mode;
+                                if ui
+                                    .selectable_label(
+                                        selected,
+                                        egui::RichText::new(mode.label())
+                                            .color(color32(theme.text))
+                                            .font(egui::FontId::monospace(16.0)),
+                                    )
+                                    .clicked()
+                                    && !selected
+                                {
+                                    settings.gameplay.mode = mode;
+                                }
+                            }
+                        });
+                        ui.add_space(10.0);
+
                         //  NOTE: Menu Buttons
                         if ui
                             .add(
@@ -215,20 +284,247 @@ fn update_menu(
                                         [25.0, 25.0],
                                     )),
                                     egui::RichText::new("Play")
-                                        .color(egui::Color32::WHITE)
+                                        .color(color32(theme.text))
                                         .font(egui::FontId::monospace(20.0)),
                                 )
                                 .min_size(egui::vec2(300., 0.))
                                 .rounding(8.0)
-                                .fill(egui::Color32::from_hex("#15c").unwrap()),
+                                .fill(color32(theme.button)),
                             )
                             .clicked()
                         {
-                            match game_state.get() {
-                                GameState::Paused => next_game_state.set(GameState::Playing),
-                                _ => (),
+                            autopilot.enabled = false;
+                            next_menu_state.set(MenuState::Off);
+                        }
+
+                        ui.add_space(10.0);
+                        if ui
+                            .add(
+                                egui::Button::image_and_text(
+                                    egui::widgets::Image::new(egui::load::SizedTexture::new(
+                                        play_icon,
+                                        [25.0, 25.0],
+                                    )),
+                                    egui::RichText::new("Watch AI")
+                                        .color(color32(theme.text))
+                                        .font(egui::FontId::monospace(20.0)),
+                                )
+                                .min_size(egui::vec2(300., 0.))
+                                .rounding(8.0)
+                                .fill(color32(theme.button)),
+                            )
+                            .clicked()
+                        {
+                            //  NOTE: no key press is coming to start the game, so start it here
+                            autopilot.enabled = true;
+                            next_menu_state.set(MenuState::Off);
+                            next_state.set(GameState::Playing);
+                        }
+
+                        ui.add_space(10.0);
+                        let online_label = if online.searching() {
+                            "Waiting... (cancel)"
+                        } else {
+                            "Play online"
+                        };
+                        if ui
+                            .add(
+                                egui::Button::image_and_text(
+                                    egui::widgets::Image::new(egui::load::SizedTexture::new(
+                                        play_icon,
+                                        [25.0, 25.0],
+                                    )),
+                                    egui::RichText::new(online_label)
+                                        .color(color32(theme.text))
+                                        .font(egui::FontId::monospace(20.0)),
+                                )
+                                .min_size(egui::vec2(300., 0.))
+                                .rounding(8.0)
+                                .fill(color32(theme.button)),
+                            )
+                            .clicked()
+                        {
+                            //  NOTE: the match starts once the server pairs us, see `online.rs`
+                            if online.searching() {
+                                online.cancel();
+                            } else {
+                                online.connect();
                             }
+                        }
+
+                        ui.add_space(10.0);
+                        if ui
+                            .add(
+                                egui::Button::image_and_text(
+                                    egui::widgets::Image::new(egui::load::SizedTexture::new(
+                                        play_icon,
+                                        [25.0, 25.0],
+                                    )),
+                                    egui::RichText::new("Battle royale")
+                                        .color(color32(theme.text))
+                                        .font(egui::FontId::monospace(20.0)),
+                                )
+                                .min_size(egui::vec2(300., 0.))
+                                .rounding(8.0)
+                                .fill(color32(theme.button)),
+                            )
+                            .clicked()
+                        {
+                            //  NOTE: the arena is set up on the next frame, see `battle_royale.rs`
+                            royale.start();
+                        }
+
+                        ui.add_space(10.0);
+                        if ui
+                            .add(
+                                egui::Button::image_and_text(
+                                    egui::widgets::Image::new(egui::load::SizedTexture::new(
+                                        settings_icon,
+                                        [25.0, 25.0],
+                                    )),
+                                    egui::RichText::new("Level editor")
+                                        .color(color32(theme.text))
+                                        .font(egui::FontId::monospace(20.0)),
+                                )
+                                .min_size(egui::vec2(300., 0.))
+                                .rounding(8.0)
+                                .fill(color32(theme.button)),
+                            )
+                            .clicked()
+                        {
+                            next_menu_state.set(MenuState::Off);
+                            next_state.set(GameState::Editing);
+                        }
+
+                        ui.add_space(10.0);
+                        if ui
+                            .add(
+                                egui::Button::image_and_text(
+                                    egui::widgets::Image::new(egui::load::SizedTexture::new(
+                                        play_icon,
+                                        [25.0, 25.0],
+                                    )),
+                                    egui::RichText::new("Random arena")
+                                        .color(color32(theme.text))
+                                        .font(egui::FontId::monospace(20.0)),
+                                )
+                                .min_size(egui::vec2(300., 0.))
+                                .rounding(8.0)
+                                .fill(color32(theme.button)),
+                            )
+                            .clicked()
+                        {
+                            //  NOTE: played like a level, the seed ends up in its name
+                            let seed = settings
+                                .gameplay
+                                .arena_seed
+                                .trim()
+                                .parse()
+                                .unwrap_or_else(|_| rand::random());
+                            let size = settings
+                                .gameplay
+                                .board_size
+                                .clamp(MIN_BOARD_SIZE, MAX_BOARD_SIZE);
+                            level.0 = Some(arena::generate(size, settings.gameplay.arena, seed));
                             next_menu_state.set(MenuState::Off);
+                            next_state.set(GameState::Resetting);
+                        }
+
+                        //  NOTE: back to the open board from the settings
+                        if let Some(name) = level.0.as_ref().map(|level| level.name.clone()) {
+                            ui.add_space(10.0);
+                            if ui
+                                .add(
+                                    egui::Button::new(
+                                        egui::RichText::new(format!("Leave \"{name}\""))
+                                            .color(color32(theme.text))
+                                            .font(egui::FontId::monospace(20.0)),
+                                    )
+                                    .min_size(egui::vec2(300., 0.))
+                                    .rounding(8.0)
+                                    .fill(color32(theme.button)),
+                                )
+                                .clicked()
+                            {
+                                level.0 = None;
+                                next_state.set(GameState::Resetting);
+                            }
+                        }
+
+                        ui.add_space(10.0);
+                        let spectate_label = if spectator.searching() {
+                            "Waiting for stream... (cancel)"
+                        } else {
+                            "Spectate"
+                        };
+                        if ui
+                            .add(
+                                egui::Button::image_and_text(
+                                    egui::widgets::Image::new(egui::load::SizedTexture::new(
+                                        play_icon,
+                                        [25.0, 25.0],
+                                    )),
+                                    egui::RichText::new(spectate_label)
+                                        .color(color32(theme.text))
+                                        .font(egui::FontId::monospace(20.0)),
+                                )
+                                .min_size(egui::vec2(300., 0.))
+                                .rounding(8.0)
+                                .fill(color32(theme.button)),
+                            )
+                            .clicked()
+                        {
+                            //  NOTE: watching starts with the stream's first snapshot, see `spectate.rs`
+                            if spectator.searching() {
+                                spectator.cancel();
+                            } else {
+                                spectator.watch();
+                            }
+                        }
+
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            ui.add_space(10.0);
+                            if ui
+                                .add(
+                                    egui::Button::image_and_text(
+                                        egui::widgets::Image::new(egui::load::SizedTexture::new(
+                                            play_icon,
+                                            [25.0, 25.0],
+                                        )),
+                                        egui::RichText::new("Watch recording")
+                                            .color(color32(theme.text))
+                                            .font(egui::FontId::monospace(20.0)),
+                                    )
+                                    .min_size(egui::vec2(300., 0.))
+                                    .rounding(8.0)
+                                    .fill(color32(theme.button)),
+                                )
+                                .clicked()
+                            {
+                                spectator.watch_recording();
+                            }
+                        }
+
+                        ui.add_space(10.0);
+                        if ui
+                            .add(
+                                egui::Button::image_and_text(
+                                    egui::widgets::Image::new(egui::load::SizedTexture::new(
+                                        settings_icon,
+                                        [25.0, 25.0],
+                                    )),
+                                    egui::RichText::new("Settings")
+                                        .color(color32(theme.text))
+                                        .font(egui::FontId::monospace(20.0)),
+                                )
+                                .min_size(egui::vec2(300., 0.))
+                                .rounding(8.0)
+                                .fill(color32(theme.button)),
+                            )
+                            .clicked()
+                        {
+                            next_menu_state.set(MenuState::Settings);
                         }
 
                         // if ui
@@ -251,3 +547,282 @@ fn update_menu(
             });
         });
 }
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum SettingsTab {
+    #[default]
+    Gameplay,
+    Video,
+    Audio,
+    Controls,
+    Accessibility,
+}
+
+fn update_settings_menu(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<Settings>,
+    mut tab: Local<SettingsTab>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    board: Res<Board>,
+    movement_timer: Res<MovementTimer>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    theme: Res<CurrentTheme>,
+    theme_assets: Res<ThemeAssets>,
+    themes: Res<Assets<Theme>>,
+    pack_assets: Res<SpritePackAssets>,
+    packs: Res<Assets<SpritePack>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+
+    //  NOTE: egui coordinates, they differ from the window size when the UI is scaled
+    let screen = contexts.ctx_mut().screen_rect();
+    egui::Window::new("settings")
+        .title_bar(false)
+        .fixed_size(egui::vec2(300.0, 320.0))
+        .current_pos(egui::pos2(
+            (screen.width() - 300.0) / 2.0,
+            (screen.height() - 400.0) / 2.0,
+        ))
+        .movable(false)
+        .collapsible(false)
+        .resizable(false)
+        .frame(egui::Frame {
+            fill: color32(theme.menu),
+            rounding: egui::Rounding::same(10.0),
+            inner_margin: egui::Margin::same(15.0),
+            shadow: Shadow {
+                color: egui::Color32::from_rgba_premultiplied(0, 0, 0, 150),
+                spread: window.width(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .show(contexts.ctx_mut(), |ui| {
+            ui.visuals_mut().override_text_color = Some(color32(theme.text));
+            ui.spacing_mut().icon_spacing = 8.;
+
+            //  NOTE: Tabs
+            ui.horizontal_wrapped(|ui| {
+                for (value, label) in [
+                    (SettingsTab::Gameplay, "Gameplay"),
+                    (SettingsTab::Video, "Video"),
+                    (SettingsTab::Audio, "Audio"),
+                    (SettingsTab::Controls, "Controls"),
+                    (SettingsTab::Accessibility, "Access"),
+                ] {
+                    ui.selectable_value(
+                        &mut *tab,
+                        value,
+                        egui::RichText::new(label).font(egui::FontId::monospace(14.0)),
+                    );
+                }
+            });
+            ui.separator();
+
+            match *tab {
+                SettingsTab::Gameplay => {
+                    ui.add(
+                        egui::Slider::new(
+                            &mut settings.gameplay.board_size,
+                            MIN_BOARD_SIZE..=MAX_BOARD_SIZE,
+                        )
+                        .text("Board size"),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Speed");
+                        for speed in Speed::ALL {
+                            ui.selectable_value(&mut settings.gameplay.speed, speed, speed.label());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Walls");
+                        ui.selectable_value(
+                            &mut settings.gameplay.walls,
+                            WallsMode::Solid,
+                            "Solid",
+                        );
+                        ui.selectable_value(&mut settings.gameplay.walls, WallsMode::Wrap, "Wrap");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("AI");
+                        for ai in AiPlayer::ALL {
+                            ui.selectable_value(&mut settings.gameplay.ai, ai, ai.label());
+                        }
+                    });
+                    ui.add(
+                        egui::Slider::new(
+                            &mut settings.gameplay.royale_snakes,
+                            MIN_ROYALE_SNAKES..=MAX_ROYALE_SNAKES,
+                        )
+                        .text("Battle royale snakes"),
+                    );
+                    ui.add(
+                        egui::Slider::new(
+                            &mut settings.gameplay.royale_humans,
+                            1..=MAX_ROYALE_HUMANS,
+                        )
+                        .text("Battle royale players"),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Arena");
+                        for difficulty in ArenaDifficulty::ALL {
+                            ui.selectable_value(
+                                &mut settings.gameplay.arena,
+                                difficulty,
+                                difficulty.label(),
+                            );
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Arena seed");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut settings.gameplay.arena_seed)
+                                .hint_text("random"),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Online server");
+                        ui.text_edit_singleline(&mut settings.gameplay.server);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Broadcast");
+                        for target in BroadcastTarget::ALL {
+                            //  NOTE: the browser has no file to write to
+                            if cfg!(target_arch = "wasm32") && target == BroadcastTarget::File {
+                                continue;
+                            }
+                            ui.selectable_value(
+                                &mut settings.gameplay.broadcast,
+                                target,
+                                target.label(),
+                            );
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Stream name");
+                        ui.text_edit_singleline(&mut settings.gameplay.stream_name);
+                    });
+                    ui.label(
+                        egui::RichText::new("Board size and speed apply to the next game.").small(),
+                    );
+                }
+                SettingsTab::Video => {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Theme");
+                        for theme in theme_assets.themes.iter().filter_map(|h| themes.get(h)) {
+                            ui.selectable_value(
+                                &mut settings.video.theme,
+                                theme.name.clone(),
+                                &theme.name,
+                            );
+                        }
+                    });
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Sprites");
+                        for pack in pack_assets.packs.iter().filter_map(|h| packs.get(h)) {
+                            ui.selectable_value(
+                                &mut settings.video.sprite_pack,
+                                pack.name.clone(),
+                                &pack.name,
+                            );
+                        }
+                    });
+                    //  NOTE: invalid packs never show up above, say why
+                    for handle in pack_assets.packs.iter() {
+                        if asset_server.get_load_state(handle) == Some(LoadState::Failed) {
+                            let path = handle
+                                .path()
+                                .map(|path| path.to_string())
+                                .unwrap_or_default();
+                            ui.label(
+                                egui::RichText::new(format!("Could not load {path}, see the log"))
+                                    .small(),
+                            );
+                        }
+                    }
+                    ui.checkbox(&mut settings.video.grid_lines, "Grid lines");
+                    ui.checkbox(&mut settings.video.interpolation, "Smooth movement");
+                    ui.horizontal(|ui| {
+                        ui.label("Camera");
+                        for mode in CameraMode::ALL {
+                            ui.selectable_value(&mut settings.video.camera, mode, mode.label());
+                        }
+                    });
+                    ui.label(
+                        egui::RichText::new("F switches camera, scroll or pinch to zoom.").small(),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut settings.video.death_animation, 0.0..=3.0)
+                            .step_by(0.25)
+                            .suffix(" s")
+                            .text("Death animation"),
+                    );
+                }
+                SettingsTab::Audio => {
+                    ui.add(egui::Slider::new(&mut settings.audio.master, 0.0..=1.0).text("Master"));
+                    ui.add(egui::Slider::new(&mut settings.audio.sfx, 0.0..=1.0).text("SFX"));
+                    ui.add(egui::Slider::new(&mut settings.audio.music, 0.0..=1.0).text("Music"));
+                    ui.checkbox(&mut settings.audio.muted, "Mute");
+                }
+                SettingsTab::Controls => {
+                    ui.checkbox(&mut settings.controls.swipe, "Swipe controls");
+                    ui.checkbox(&mut settings.controls.dpad, "On-screen D-pad");
+                }
+                SettingsTab::Accessibility => {
+                    ui.checkbox(
+                        &mut settings.accessibility.outlines,
+                        "Outline snake head and food",
+                    );
+                    ui.checkbox(&mut settings.accessibility.food_patterns, "Pattern on food");
+                    ui.checkbox(&mut settings.accessibility.reduced_motion, "Reduced motion");
+                    ui.add(
+                        egui::Slider::new(
+                            &mut settings.accessibility.ui_scale,
+                            MIN_UI_SCALE..=MAX_UI_SCALE,
+                        )
+                        .step_by(0.25)
+                        .text("UI scale"),
+                    );
+                    ui.label(
+                        egui::RichText::new(
+                            "Colour-blind palettes are available under Video > Theme.",
+                        )
+                        .small(),
+                    );
+                }
+            }
+
+            ui.add_space(15.0);
+            if ui
+                .add(
+                    egui::Button::new(
+                        egui::RichText::new("Back")
+                            .color(color32(theme.text))
+                            .font(egui::FontId::monospace(20.0)),
+                    )
+                    .min_size(egui::vec2(270., 0.))
+                    .rounding(8.0)
+                    .fill(color32(theme.button)),
+                )
+                .clicked()
+            {
+                //  NOTE: nothing has been played on a fresh board yet, rebuild it right away
+                let gameplay = &settings.gameplay;
+                if game_state.get() == &GameState::NewGame
+                    && (board.size != gameplay.board_size
+                        || movement_timer.step() != gameplay.speed.step())
+                {
+                    next_game_state.set(GameState::Resetting);
+                }
+                next_menu_state.set(match game_state.get() {
+                    GameState::Paused => MenuState::Paused,
+                    _ => MenuState::On,
+                });
+            }
+        });
+}
@@ -1,5 +1,7 @@
+use std::collections::HashSet;
+
 use bevy::math::Vec2;
-use rand::Rng;
+use rand::{seq::IteratorRandom, Rng};
 
 use crate::snake::{Direction, Position};
 
@@ -23,12 +25,16 @@ pub fn snake_starting_position(board_size: i32) -> Vec<Position> {
     ]
 }
 
-pub fn food_position(board_size: i32) -> Position {
-    let mut rng = rand::thread_rng();
-    Position {
-        x: rng.gen_range(5..board_size - 5),
-        y: rng.gen_range(5..board_size - 5),
-    }
+//  NOTE: a random cell the snake isn't on, `None` once the snake covers the whole board
+pub fn food_position(
+    board_size: i32,
+    occupied: &HashSet<Position>,
+    rng: &mut impl Rng,
+) -> Option<Position> {
+    (0..board_size)
+        .flat_map(|y| (0..board_size).map(move |x| Position { x, y }))
+        .filter(|pos| !occupied.contains(pos))
+        .choose(rng)
 }
 
 pub fn _calc_sprite_index(row: usize, column: usize, columns_per_row: usize) -> usize {
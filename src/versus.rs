@@ -0,0 +1,341 @@
+use std::collections::{HashSet, VecDeque};
+
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    protocol::MAX_INPUT_DELAY,
+    sim::{crash, next_head, place_food, slide, turn, Grid},
+    snake::{Direction, Position},
+};
+
+//  NOTE: several snakes on one board, the rules the relay server runs and the clients render.
+//  Plays like `sim::Game` with one food shared by everyone
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersusSnake {
+    //  NOTE: head first
+    pub body: VecDeque<Position>,
+    pub direction: Direction,
+    pub alive: bool,
+    pub score: u32,
+}
+
+impl VersusSnake {
+    //  NOTE: the direction each segment moved in to get where it is, like `SnakeDirectionQueue`,
+    //  what the renderers pick the sprites by
+    pub fn directions(&self, grid: &Grid) -> VecDeque<Direction> {
+        let mut directions: VecDeque<Direction> = VecDeque::with_capacity(self.body.len());
+        directions.push_back(self.direction);
+        for (&ahead, &behind) in self.body.iter().zip(self.body.iter().skip(1)) {
+            let previous = *directions.back().expect("starts with the head");
+            let direction = Direction::ALL
+                .into_iter()
+                .find(|&direction| grid.advance(behind, direction) == ahead)
+                //  NOTE: keep a broken snake drawable instead of trusting the server blindly
+                .filter(|&direction| direction != previous.opposite())
+                .unwrap_or(previous);
+            directions.push_back(direction);
+        }
+        directions
+    }
+}
+
+//  NOTE: everything the clients need to draw a tick, `tick` counts the moves made so far
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersusState {
+    pub tick: u32,
+    pub snakes: Vec<VersusSnake>,
+    pub food: Option<Position>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VersusOutcome {
+    Running,
+    //  NOTE: `None` is a draw, the last snakes died on the same tick
+    Over { winner: Option<usize> },
+}
+
+#[derive(Debug, Clone)]
+pub struct VersusGame {
+    grid: Grid,
+    state: VersusState,
+    rng: StdRng,
+    outcome: VersusOutcome,
+}
+
+impl VersusGame {
+    pub fn new(grid: Grid, players: usize, seed: u64) -> Self {
+        let snakes = (0..players)
+            .map(|player| spawn(grid.size, player, players))
+            .collect();
+        Self {
+            grid,
+            state: VersusState {
+                tick: 0,
+                snakes,
+                food: Some(Position::new(grid.size / 2, grid.size / 2)),
+            },
+            rng: StdRng::seed_from_u64(seed),
+            outcome: VersusOutcome::Running,
+        }
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn state(&self) -> &VersusState {
+        &self.state
+    }
+
+    pub fn outcome(&self) -> VersusOutcome {
+        self.outcome
+    }
+
+    //  NOTE: one move for every snake still alive, `None` keeps a snake going the way it was
+    pub fn step(&mut self, directions: &[Option<Direction>]) -> VersusOutcome {
+        if self.outcome != VersusOutcome::Running {
+            return self.outcome;
+        }
+
+        let vacated = advance(&self.grid, &mut self.state.snakes, directions);
+
+        //  NOTE: everybody moves at once, so collisions are checked against where the others ended up
+        let dead: Vec<usize> = (0..self.state.snakes.len())
+            .filter(|&i| self.state.snakes[i].alive && crashed(&self.grid, &self.state.snakes, i))
+            .collect();
+        for &i in &dead {
+            self.state.snakes[i].alive = false;
+        }
+
+        let mut ate = false;
+        for (snake, vacated) in self.state.snakes.iter_mut().zip(vacated) {
+            if snake.alive && Some(snake.body[0]) == self.state.food {
+                //  NOTE: the new segment takes the cell the tail just left
+                snake.body.extend(vacated);
+                snake.score += 1;
+                ate = true;
+            }
+        }
+        if ate {
+            let occupied: HashSet<Position> = self
+                .alive()
+                .flat_map(|snake| snake.body.iter().copied())
+                .collect();
+            self.state.food = place_food(&self.grid, None, &occupied, &mut self.rng);
+        }
+        self.state.tick += 1;
+
+        let alive: Vec<usize> = (0..self.state.snakes.len())
+            .filter(|&i| self.state.snakes[i].alive)
+            .collect();
+        let last_standing = if self.state.snakes.len() > 1 { 1 } else { 0 };
+        if alive.len() <= last_standing {
+            self.outcome = VersusOutcome::Over {
+                winner: alive.first().copied(),
+            };
+        } else if self.state.food.is_none() {
+            //  NOTE: the board is full, the longest snake takes it
+            let longest = alive
+                .iter()
+                .copied()
+                .max_by_key(|&i| self.state.snakes[i].body.len());
+            self.outcome = VersusOutcome::Over { winner: longest };
+        }
+        self.outcome
+    }
+
+    fn alive(&self) -> impl Iterator<Item = &VersusSnake> {
+        self.state.snakes.iter().filter(|snake| snake.alive)
+    }
+}
+
+//  NOTE: one player's turns waiting for their tick on the server, stamped by the client
+#[derive(Debug, Default)]
+pub struct ScheduledTurns(VecDeque<(u32, Direction)>);
+
+impl ScheduledTurns {
+    //  NOTE: never further ahead than a client may schedule, `current` is the last tick played
+    pub fn push(&mut self, current: u32, tick: u32, direction: Direction) {
+        let latest = current + 1 + MAX_INPUT_DELAY;
+        self.0.push_back((tick.min(latest), direction));
+    }
+
+    //  NOTE: one turn per tick, late turns are used on the next tick instead of being lost
+    pub fn pop(&mut self, tick: u32) -> Option<Direction> {
+        match self.0.front() {
+            Some(&(stamp, _)) if stamp <= tick => {
+                self.0.pop_front().map(|(_, direction)| direction)
+            }
+            _ => None,
+        }
+    }
+}
+
+//  NOTE: one move for every snake still alive by `sim::turn`, `sim::next_head` and `sim::slide`,
+//  returns the cells their tails left. `None` keeps a snake going the way it was
+pub(crate) fn advance(
+    grid: &Grid,
+    snakes: &mut [VersusSnake],
+    directions: &[Option<Direction>],
+) -> Vec<Option<Position>> {
+    let mut vacated = vec![None; snakes.len()];
+    for (i, snake) in snakes.iter_mut().enumerate() {
+        if !snake.alive {
+            continue;
+        }
+        if let Some(&Some(direction)) = directions.get(i) {
+            snake.direction = turn(snake.direction, direction);
+        }
+        let head = next_head(grid, None, snake.body[0], snake.direction);
+        vacated[i] = Some(slide(&mut snake.body, head));
+    }
+    vacated
+}
+
+//  NOTE: `sim::crash` against every snake's body. Running into another head counts too, so
+//  head-on crashes take out both snakes
+pub(crate) fn crashed(grid: &Grid, snakes: &[VersusSnake], i: usize) -> bool {
+    let head = snakes[i].body[0];
+    let segments = snakes
+        .iter()
+        .enumerate()
+        .filter(|(_, other)| other.alive)
+        .flat_map(|(j, other)| other.body.iter().skip(usize::from(i == j)));
+    crash(grid, None, head, head, segments, []).is_some()
+}
+
+//  NOTE: snakes start three long on evenly spaced rows, alternating between the left side heading
+//  right and the right side heading left
+fn spawn(size: i32, player: usize, players: usize) -> VersusSnake {
+    let y = size * (player as i32 + 1) / (players as i32 + 1);
+    let (head_x, direction) = if player % 2 == 0 {
+        (4, Direction::Right)
+    } else {
+        (size - 5, Direction::Left)
+    };
+    let step = if direction == Direction::Right { -1 } else { 1 };
+
+    VersusSnake {
+        body: (0..3)
+            .map(|i| Position::new(head_x + step * i, y))
+            .collect(),
+        direction,
+        alive: true,
+        score: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::WallsMode;
+
+    fn snake(body: &[(i32, i32)], direction: Direction) -> VersusSnake {
+        VersusSnake {
+            body: body.iter().map(|&(x, y)| Position::new(x, y)).collect(),
+            direction,
+            alive: true,
+            score: 0,
+        }
+    }
+
+    //  NOTE: a two player game with the snakes and the food put down by hand
+    fn game(size: i32, snakes: Vec<VersusSnake>, food: (i32, i32)) -> VersusGame {
+        let grid = Grid {
+            size,
+            walls: WallsMode::Solid,
+        };
+        let mut game = VersusGame::new(grid, snakes.len(), 0);
+        game.state.snakes = snakes;
+        game.state.food = Some(Position::new(food.0, food.1));
+        game
+    }
+
+    #[test]
+    fn head_on_crashes_are_draws() {
+        //  NOTE: into the same cell, and swapping cells
+        for gap in [0, 1] {
+            let mut game = game(
+                20,
+                vec![
+                    snake(&[(8, 10), (7, 10), (6, 10)], Direction::Right),
+                    snake(
+                        &[(10 - gap, 10), (11 - gap, 10), (12 - gap, 10)],
+                        Direction::Left,
+                    ),
+                ],
+                (0, 0),
+            );
+            let outcome = game.step(&[None, None]);
+            assert_eq!(outcome, VersusOutcome::Over { winner: None }, "gap {gap}");
+            assert!(game.state().snakes.iter().all(|snake| !snake.alive));
+        }
+    }
+
+    #[test]
+    fn eating_grows_into_the_cell_the_tail_left() {
+        let mut game = game(
+            20,
+            vec![
+                snake(&[(8, 5), (7, 5), (6, 5)], Direction::Right),
+                snake(&[(8, 15), (7, 15), (6, 15)], Direction::Right),
+            ],
+            (9, 5),
+        );
+        assert_eq!(game.step(&[None, None]), VersusOutcome::Running);
+
+        let eater = &game.state().snakes[0];
+        let body: Vec<Position> = eater.body.iter().copied().collect();
+        assert_eq!(
+            body,
+            [(9, 5), (8, 5), (7, 5), (6, 5)].map(|(x, y)| Position::new(x, y))
+        );
+        assert_eq!(eater.score, 1);
+        assert_eq!(game.state().snakes[1].body.len(), 3);
+        assert_ne!(game.state().food, Some(Position::new(9, 5)));
+    }
+
+    #[test]
+    fn the_longest_snake_wins_a_full_board() {
+        //  NOTE: on a 3x3 board the second snake circles a 2x2 square and the first one eats the
+        //  last free cell, leaving nowhere for food
+        let mut game = game(
+            3,
+            vec![
+                snake(&[(1, 0), (0, 0), (0, 1), (0, 2)], Direction::Right),
+                snake(&[(1, 1), (2, 1), (2, 2), (1, 2)], Direction::Left),
+            ],
+            (2, 0),
+        );
+        let outcome = game.step(&[None, Some(Direction::Up)]);
+
+        assert_eq!(game.state().food, None);
+        assert!(game.state().snakes.iter().all(|snake| snake.alive));
+        assert_eq!(outcome, VersusOutcome::Over { winner: Some(0) });
+    }
+
+    #[test]
+    fn scheduled_turns_wait_for_their_tick() {
+        let mut turns = ScheduledTurns::default();
+        turns.push(10, 12, Direction::Up);
+        //  NOTE: late, it goes on the next tick
+        turns.push(10, 3, Direction::Left);
+
+        assert_eq!(turns.pop(11), None);
+        assert_eq!(turns.pop(12), Some(Direction::Up));
+        assert_eq!(turns.pop(13), Some(Direction::Left));
+        assert_eq!(turns.pop(14), None);
+    }
+
+    #[test]
+    fn scheduled_turns_are_clamped_to_the_input_delay() {
+        let mut turns = ScheduledTurns::default();
+        turns.push(10, u32::MAX, Direction::Down);
+
+        let latest = 10 + 1 + MAX_INPUT_DELAY;
+        assert_eq!(turns.pop(latest - 1), None);
+        assert_eq!(turns.pop(latest), Some(Direction::Down));
+    }
+}